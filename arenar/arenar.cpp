@@ -1,5 +1,6 @@
 #include <cassert>
 
+#include "../libsrc/bullet3-3.24/BulletCollision/CollisionDispatch/btManifoldResult.h"
 #include "arenar.h"
 
 const CarConfig &getOctane() { return CAR_CONFIG_OCTANE; }
@@ -101,6 +102,20 @@ bool Arenar::RemoveCar(uint32_t carID) {
   return true;
 }
 
+bool Arenar::AddCarWithId(uint32_t carID, Team team, const CarConfig &config) {
+  if (carID == 0 || a->GetCar(carID) != NULL) {
+    return false;
+  }
+
+  // AddCar always uses the ID after the last one it gave out
+  uint32_t lastCarID = a->_lastCarID;
+  a->_lastCarID = carID - 1;
+  a->AddCar(team, config);
+  a->_lastCarID = std::max(lastCarID, carID);
+
+  return true;
+}
+
 bool Arenar::SetCarControls(uint32_t carID, const CarControls controls) {
   Car *car = a->GetCar(carID);
   if (car == NULL) {
@@ -111,6 +126,38 @@ bool Arenar::SetCarControls(uint32_t carID, const CarControls controls) {
   return true;
 }
 
+bool Arenar::GetCarWheels(uint32_t carID, ECarWheels &wheels) const {
+  Car *car = a->GetCar(carID);
+  if (car == NULL) {
+    return false;
+  }
+
+  // Bullet works in its own units, which are 50 times smaller
+  constexpr float BULLET_TO_UU = 50.f;
+  auto toVec = [](const btVector3 &vec, float scale) {
+    return Vec(vec.x() * scale, vec.y() * scale, vec.z() * scale);
+  };
+
+  auto &vehicle = car->_bulletVehicle;
+  for (int i = 0; i < vehicle.getNumWheels() && i < 4; i++) {
+    const auto &wheelInfo = vehicle.getWheelInfo(i);
+    const auto &raycastInfo = wheelInfo.m_raycastInfo;
+
+    wheels.wheels[i] = EWheelInfo{
+        .isInContact = raycastInfo.m_isInContact,
+        .contactPoint = toVec(raycastInfo.m_contactPointWS, BULLET_TO_UU),
+        .contactNormal = toVec(raycastInfo.m_contactNormalWS, 1.f),
+        .suspensionLength = raycastInfo.m_suspensionLength * BULLET_TO_UU,
+        .suspensionRestLength = wheelInfo.m_suspensionRestLength1 * BULLET_TO_UU,
+        .radius = wheelInfo.m_wheelsRadius * BULLET_TO_UU,
+        .frictionSlip = wheelInfo.m_frictionSlip,
+        .steering = wheelInfo.m_steering,
+        .skidInfo = wheelInfo.m_skidInfo};
+  }
+
+  return true;
+}
+
 bool Arenar::DemolishCar(uint32_t carID) {
   Car *car = a->GetCar(carID);
   if (car == NULL) {
@@ -162,3 +209,224 @@ EBoostPadState Arenar::GetPadState(size_t index) const {
       state.prevLockedCarID,
   };
 }
+
+/// @brief Finds the nth manifold of the collision algorithm between two
+/// objects, creating the algorithm and its manifolds if the objects separated
+/// since. Compound shapes have a manifold for every child, so there can be more
+/// than one. Recreating them allocates through Bullet's dispatcher.
+static btPersistentManifold *
+FindOrCreateManifold(btCollisionWorld &world, const btCollisionObject *body0,
+                     const btCollisionObject *body1, int nth) {
+  btBroadphasePair *pair = world.getPairCache()->findPair(
+      body0->getBroadphaseHandle(), body1->getBroadphaseHandle());
+  if (pair == NULL) {
+    return NULL;
+  }
+
+  // algorithms are always created with the objects in the order of the pair
+  auto *obj0 =
+      static_cast<btCollisionObject *>(pair->m_pProxy0->m_clientObject);
+  auto *obj1 =
+      static_cast<btCollisionObject *>(pair->m_pProxy1->m_clientObject);
+  btCollisionObjectWrapper wrap0(NULL, obj0->getCollisionShape(), obj0,
+                                 obj0->getWorldTransform(), -1, -1);
+  btCollisionObjectWrapper wrap1(NULL, obj1->getCollisionShape(), obj1,
+                                 obj1->getWorldTransform(), -1, -1);
+
+  if (pair->m_algorithm == NULL) {
+    pair->m_algorithm = world.getDispatcher()->findAlgorithm(
+        &wrap0, &wrap1, NULL, BT_CONTACT_POINT_ALGORITHMS);
+  }
+
+  btManifoldArray manifolds;
+  pair->m_algorithm->getAllContactManifolds(manifolds);
+  if (manifolds.size() == 0) {
+    // most algorithms only create their manifold the first time they look for
+    // contacts, the points found here are replaced by the ones in the snapshot
+    // and mustn't reach RocketSim's callback as new hits
+    ContactAddedCallback contactAddedCallback = gContactAddedCallback;
+    gContactAddedCallback = NULL;
+
+    btManifoldResult result(&wrap0, &wrap1);
+    pair->m_algorithm->processCollision(&wrap0, &wrap1, world.getDispatchInfo(),
+                                        &result);
+
+    gContactAddedCallback = contactAddedCallback;
+    pair->m_algorithm->getAllContactManifolds(manifolds);
+  }
+
+  for (int i = 0; i < manifolds.size(); i++) {
+    if (manifolds[i]->getBody0() == body0 &&
+        manifolds[i]->getBody1() == body1 && nth-- == 0) {
+      manifolds[i]->clearManifold();
+      return manifolds[i];
+    }
+  }
+
+  return NULL;
+}
+
+void BodySnapshot::Save(const btRigidBody &body) {
+  worldTransform = body.getWorldTransform();
+  interpolationWorldTransform = body.getInterpolationWorldTransform();
+  linearVelocity = body.getLinearVelocity();
+  angularVelocity = body.getAngularVelocity();
+  interpolationLinearVelocity = body.getInterpolationLinearVelocity();
+  interpolationAngularVelocity = body.getInterpolationAngularVelocity();
+  activationState = body.getActivationState();
+  deactivationTime = body.getDeactivationTime();
+}
+
+void BodySnapshot::Load(btRigidBody &body) const {
+  body.setWorldTransform(worldTransform);
+  body.setInterpolationWorldTransform(interpolationWorldTransform);
+  body.setLinearVelocity(linearVelocity);
+  body.setAngularVelocity(angularVelocity);
+  body.setInterpolationLinearVelocity(interpolationLinearVelocity);
+  body.setInterpolationAngularVelocity(interpolationAngularVelocity);
+  body.forceActivationState(activationState);
+  body.setDeactivationTime(deactivationTime);
+}
+
+std::unique_ptr<ArenaSnapshot> Arenar::Snapshot() const {
+  auto snapshot = std::make_unique<ArenaSnapshot>();
+  SnapshotInto(*snapshot);
+  return snapshot;
+}
+
+void Arenar::SnapshotInto(ArenaSnapshot &snapshot) const {
+  snapshot.tickCount = a->tickCount;
+  snapshot.lastCarId = a->_lastCarID;
+
+  snapshot.ballState = a->ball->GetState();
+  snapshot.ball.Save(a->ball->_rigidBody);
+
+  // clear() keeps the capacity, so taking snapshots into the same object
+  // doesn't allocate once it's big enough
+  snapshot.cars.clear();
+  for (Car *car : a->_cars) {
+    CarSnapshot &carSnapshot = snapshot.cars.emplace_back();
+    carSnapshot.id = car->id;
+    carSnapshot.state = car->GetState();
+    carSnapshot.controls = car->controls;
+    carSnapshot.body.Save(car->_rigidBody);
+
+    auto &vehicle = car->_bulletVehicle;
+    for (int i = 0; i < vehicle.getNumWheels() && i < 4; i++) {
+      carSnapshot.wheels[i] = vehicle.getWheelInfo(i);
+    }
+  }
+
+  snapshot.pads.clear();
+  for (BoostPad *pad : a->_boostPads) {
+    snapshot.pads.push_back(pad->GetState());
+  }
+
+  snapshot.tiles = a->_dropshotTilesState;
+
+  btDispatcher *dispatcher = a->_bulletWorld.getDispatcher();
+  snapshot.manifolds.clear();
+  for (int i = 0; i < dispatcher->getNumManifolds(); i++) {
+    const btPersistentManifold *manifold =
+        dispatcher->getManifoldByIndexInternal(i);
+
+    ManifoldSnapshot &manifoldSnapshot = snapshot.manifolds.emplace_back();
+    manifoldSnapshot.body0 = manifold->getBody0();
+    manifoldSnapshot.body1 = manifold->getBody1();
+    manifoldSnapshot.numContacts = manifold->getNumContacts();
+    for (int j = 0; j < manifoldSnapshot.numContacts; j++) {
+      manifoldSnapshot.points[j] = manifold->getContactPoint(j);
+    }
+  }
+}
+
+uint32_t Arenar::Restore(const ArenaSnapshot &snapshot) {
+  for (const CarSnapshot &carSnapshot : snapshot.cars) {
+    if (a->GetCar(carSnapshot.id) == NULL) {
+      return carSnapshot.id;
+    }
+  }
+
+  if (a->_cars.size() != snapshot.cars.size()) {
+    for (Car *car : a->_cars) {
+      bool found = false;
+      for (const CarSnapshot &carSnapshot : snapshot.cars) {
+        found |= carSnapshot.id == car->id;
+      }
+
+      if (!found) {
+        return car->id;
+      }
+    }
+  }
+
+  a->tickCount = snapshot.tickCount;
+  a->_lastCarID = snapshot.lastCarId;
+
+  a->ball->SetState(snapshot.ballState);
+  snapshot.ball.Load(a->ball->_rigidBody);
+
+  for (const CarSnapshot &carSnapshot : snapshot.cars) {
+    Car *car = a->GetCar(carSnapshot.id);
+    car->SetState(carSnapshot.state);
+    car->controls = carSnapshot.controls;
+    carSnapshot.body.Load(car->_rigidBody);
+
+    auto &vehicle = car->_bulletVehicle;
+    for (int i = 0; i < vehicle.getNumWheels() && i < 4; i++) {
+      vehicle.getWheelInfo(i) = carSnapshot.wheels[i];
+    }
+  }
+
+  for (size_t i = 0; i < snapshot.pads.size() && i < a->_boostPads.size();
+       i++) {
+    a->_boostPads[i]->SetState(snapshot.pads[i]);
+  }
+
+  a->SetDropshotTilesState(snapshot.tiles);
+
+  // forget every cached contact point, then put back the ones from the
+  // snapshot, recreating the manifolds of pairs that separated since
+  btDiscreteDynamicsWorld &world = a->_bulletWorld;
+  btDispatcher *dispatcher = world.getDispatcher();
+  for (int i = 0; i < dispatcher->getNumManifolds(); i++) {
+    dispatcher->getManifoldByIndexInternal(i)->clearManifold();
+  }
+
+  // bring the broadphase pairs up to date with the restored transforms, the
+  // same way the next step would, this only allocates for pairs that were
+  // removed since the snapshot
+  world.updateAabbs();
+  world.computeOverlappingPairs();
+
+  int numRestored = 0;
+  for (size_t i = 0; i < snapshot.manifolds.size(); i++) {
+    const ManifoldSnapshot &manifoldSnapshot = snapshot.manifolds[i];
+
+    int nth = 0;
+    for (size_t j = 0; j < i; j++) {
+      nth += snapshot.manifolds[j].body0 == manifoldSnapshot.body0 &&
+             snapshot.manifolds[j].body1 == manifoldSnapshot.body1;
+    }
+
+    btPersistentManifold *manifold = FindOrCreateManifold(
+        world, manifoldSnapshot.body0, manifoldSnapshot.body1, nth);
+    if (manifold == NULL) {
+      continue;
+    }
+
+    for (int j = 0; j < manifoldSnapshot.numContacts; j++) {
+      manifold->addManifoldPoint(manifoldSnapshot.points[j]);
+    }
+
+    // the order of the manifolds decides the order contacts are solved in, so
+    // it's put back to the order in the snapshot
+    btPersistentManifold **manifolds = dispatcher->getInternalManifoldPointer();
+    std::swap(manifolds[manifold->m_index1a], manifolds[numRestored]);
+    manifolds[manifold->m_index1a]->m_index1a = manifold->m_index1a;
+    manifold->m_index1a = numRestored;
+    numRestored++;
+  }
+
+  return 0;
+}
@@ -1,5 +1,12 @@
 #pragma once
 
+#include <algorithm>
+#include <array>
+#include <memory>
+#include <type_traits>
+#include <utility>
+#include <vector>
+
 #include "RocketSim.h"
 #include "rust/cxx.h"
 
@@ -34,6 +41,68 @@ struct EBoostPadState {
   uint32_t prevLockedCarID = 0;
 };
 
+struct EWheelInfo {
+  bool isInContact;
+  Vec contactPoint;
+  Vec contactNormal;
+  float suspensionLength;
+  float suspensionRestLength;
+  float radius;
+  float frictionSlip;
+  float steering;
+  float skidInfo;
+};
+
+struct ECarWheels {
+  std::array<EWheelInfo, 4> wheels;
+};
+
+// the snapshot code reaches into these directly, so a change in how RocketSim
+// stores them has to fail here instead of compiling into something else
+static_assert(std::is_same_v<decltype(Ball::_rigidBody), btRigidBody>);
+static_assert(std::is_same_v<decltype(Car::_rigidBody), btRigidBody>);
+static_assert(std::is_same_v<decltype(Car::_bulletVehicle), btVehicleRL>);
+static_assert(
+    std::is_same_v<decltype(Arena::_bulletWorld), btDiscreteDynamicsWorld>);
+
+/// @brief Everything Bullet keeps about a rigid body between ticks
+struct BodySnapshot {
+  btTransform worldTransform, interpolationWorldTransform;
+  btVector3 linearVelocity, angularVelocity;
+  btVector3 interpolationLinearVelocity, interpolationAngularVelocity;
+  int activationState;
+  btScalar deactivationTime;
+
+  void Save(const btRigidBody &body);
+  void Load(btRigidBody &body) const;
+};
+
+struct CarSnapshot {
+  uint32_t id;
+  CarState state;
+  CarControls controls;
+  BodySnapshot body;
+  std::array<btWheelInfoRL, 4> wheels;
+};
+
+/// @brief The cached contact points between two objects
+struct ManifoldSnapshot {
+  const btCollisionObject *body0, *body1;
+  int numContacts;
+  std::array<btManifoldPoint, MANIFOLD_CACHE_SIZE> points;
+};
+
+struct ArenaSnapshot {
+  uint64_t tickCount;
+  uint32_t lastCarId;
+  BallState ballState;
+  BodySnapshot ball;
+  std::vector<CarSnapshot> cars;
+  std::vector<BoostPadState> pads;
+  DropshotTilesState tiles;
+  std::vector<ManifoldSnapshot> manifolds;
+};
+
 struct Arenar {
   Arena *a;
 
@@ -99,6 +168,10 @@ struct Arenar {
     return a->AddCar(team, config)->id;
   }
 
+  /// @brief Adds a car with a specific ID instead of the next free one
+  /// @return False if a car with the ID already exists
+  bool AddCarWithId(uint32_t car_id, Team team, const CarConfig &config);
+
   bool RemoveCar(uint32_t car_id);
   /// @brief Sets the controls of a car for the next tick
   /// @param arena
@@ -107,6 +180,10 @@ struct Arenar {
   /// @return True if the car was found and the state was set, false otherwise
   bool SetCarControls(uint32_t car_id, const CarControls controls);
   bool DemolishCar(uint32_t car_id);
+  /// @brief Gets the info of every wheel of a car from Bullet, in unreal units
+  /// @return True if the car was found and the wheels were set, false
+  /// otherwise
+  bool GetCarWheels(uint32_t car_id, ECarWheels &wheels) const;
   bool RespawnCar(uint32_t car_id, int32_t seed, float boost_amount);
 
   // extra ball stuff
@@ -129,18 +206,40 @@ struct Arenar {
 
   void ResetTickCount() { a->tickCount = 0; }
 
+  void SetTickCount(uint64_t ticks) { a->tickCount = ticks; }
+
   uint64_t GetTickCount() const { return a->tickCount; }
 
   float GetTickRate() const { return 1 / a->tickTime; }
 
   GameMode GetGameMode() const { return a->gameMode; }
 
+  EArenaConfig GetArenaConfig() const {
+    const ArenaConfig &config = a->GetArenaConfig();
+    return EArenaConfig{.memWeightMode = config.memWeightMode,
+                        .minPos = config.minPos,
+                        .maxPos = config.maxPos,
+                        .maxAABBLen = config.maxAABBLen,
+                        .noBallRot = config.noBallRot,
+                        .useCustomBroadphase = config.useCustomBroadphase,
+                        .maxObjects = config.maxObjects};
+  }
+
   void ResetToRandomKickoff(int32_t seed = -1) {
     a->ResetToRandomKickoff(seed);
   }
 
   void Step(uint32_t ticks = 1) { a->Step(ticks); }
 
+  std::unique_ptr<ArenaSnapshot> Snapshot() const;
+  /// @brief Same as Snapshot, but reuses the memory of an existing snapshot
+  void SnapshotInto(ArenaSnapshot &snapshot) const;
+  /// @brief Restores the arena in place to the state it was in when the
+  /// snapshot was taken
+  /// @return 0 on success, otherwise the ID of the first car that was added
+  /// or removed since the snapshot was taken
+  uint32_t Restore(const ArenaSnapshot &snapshot);
+
   bool IsBallProbablyGoingIn(float maxTime = 2.f, float extraMargin = 0) const {
     return a->IsBallProbablyGoingIn(maxTime, extraMargin);
   }
@@ -1,52 +1,16 @@
 use rocketsim_rs::{
-    bytes::{FromBytes, FromBytesExact, ToBytes},
     cxx::UniquePtr,
     math::Vec3,
+    rlviser::{RlviserConnection, RlviserEvent},
     sim::{Arena, ArenaConfig, BallState, CarConfig, CarControls, GameMode, Team},
-    GameState,
 };
 use std::{
     io,
-    net::{IpAddr, SocketAddr, UdpSocket},
-    str::FromStr,
     sync::mpsc::{channel, Receiver},
     thread::sleep,
     time::{Duration, Instant},
 };
 
-// Pass this into rlviser as the first argument
-// default: 45243
-const RLVISER_PORT: u16 = 45243;
-
-// Pass this into rlviser as the second argument
-// default: 34254
-const ROCKETSIM_PORT: u16 = 34254;
-
-#[repr(u8)]
-#[derive(Debug, Clone, Copy)]
-enum UdpPacketTypes {
-    Quit,
-    GameState,
-    Connection,
-    Paused,
-    Speed,
-    Render,
-}
-
-impl From<u8> for UdpPacketTypes {
-    fn from(val: u8) -> Self {
-        match val {
-            0 => Self::Quit,
-            1 => Self::GameState,
-            2 => Self::Connection,
-            3 => Self::Paused,
-            4 => Self::Speed,
-            5 => Self::Render,
-            _ => panic!("Invalid packet type"),
-        }
-    }
-}
-
 fn ctrl_channel() -> Result<Receiver<()>, ctrlc::Error> {
     let (sender, receiver) = channel();
 
@@ -73,147 +37,65 @@ fn main() -> io::Result<()> {
         _ => GameMode::Soccar,
     };
 
-    RLViserSocketHandler::new()?.run(arena_type)?;
+    println!("\nPress enter to start...");
+    io::stdin().read_line(&mut String::new())?;
 
-    Ok(())
-}
+    // rlviser should be started with the default ports,
+    // use `RlviserConnection::with_addrs` for anything else
+    //
+    // once connected, rlviser sends us the desired game speed / if the game should be paused
+    let mut rlviser = RlviserConnection::new()?;
 
-struct RLViserSocketHandler {
-    socket: UdpSocket,
-    rlviser_addr: SocketAddr,
-    min_game_state_buf: [u8; GameState::MIN_NUM_BYTES],
-    game_state_buffer: Vec<u8>,
-    paused: bool,
+    run(&mut rlviser, arena_type)
 }
 
-impl RLViserSocketHandler {
-    pub fn new() -> io::Result<Self> {
-        let socket = UdpSocket::bind(("0.0.0.0", ROCKETSIM_PORT))?;
-        // print the socket address
-        println!("Listening on {}", socket.local_addr()?);
-
-        let rlviser_addr = SocketAddr::new(IpAddr::from_str("127.0.0.1").unwrap(), RLVISER_PORT);
-
-        println!("\nPress enter to start...");
-        io::stdin().read_line(&mut String::new())?;
-
-        // We now don't want to wait for anything UDP so set to non-blocking
-        socket.set_nonblocking(true)?;
-
-        // notify rlviser that we're connected
-        // it will send us info on the desired game speed / if the game should be paused
-        // if you choose to ignore this, at least send the right game speed / paused state back
-        // otherwise things like packet interpolation will be off
-        socket.send_to(&[UdpPacketTypes::Connection as u8], rlviser_addr)?;
-
-        Ok(Self {
-            socket,
-            rlviser_addr,
-            min_game_state_buf: [0; GameState::MIN_NUM_BYTES],
-            game_state_buffer: Vec::new(),
-            paused: false,
-        })
-    }
-
-    pub fn run(&mut self, arena_type: GameMode) -> io::Result<()> {
-        // Note: RLViser supports on-the-fly changing of the arena type
-        let mut arena = setup_arena(arena_type);
-
-        // listen for Ctrl+C signal
-        let break_signal = ctrl_channel().unwrap();
-
-        // we only want to loop at 120hz
-        // speed 0.5 = half speed
-        // speed 2 = double speed
-        let mut interval = Duration::from_secs_f32(1. / 120.);
-        let mut next_time = Instant::now() + interval;
-
-        // we loop forever - can be broken by pressing Ctrl+C in terminal
-        loop {
-            if break_signal.try_recv().is_ok() {
-                self.socket.send_to(&[UdpPacketTypes::Quit as u8], self.rlviser_addr)?;
-                println!("Sent quit signal to rlviser");
-
-                // Then break the loop
-                break Ok(());
-            }
+fn run(rlviser: &mut RlviserConnection, arena_type: GameMode) -> io::Result<()> {
+    // Note: RLViser supports on-the-fly changing of the arena type
+    let mut arena = setup_arena(arena_type);
 
-            self.handle_return_message(&mut arena, &mut interval)?;
+    // listen for Ctrl+C signal
+    let break_signal = ctrl_channel().unwrap();
 
-            if !self.paused {
-                // advance the simulation by 1 tick
-                arena.pin_mut().step(1);
+    let mut next_time = Instant::now();
 
-                // send the new game state back
-                let game_state = arena.pin_mut().get_game_state();
+    // we loop forever - can be broken by pressing Ctrl+C in terminal
+    loop {
+        if break_signal.try_recv().is_ok() {
+            rlviser.send_quit()?;
+            println!("Sent quit signal to rlviser");
 
-                // Send the packet type
-                self.socket.send_to(&[UdpPacketTypes::GameState as u8], self.rlviser_addr)?;
-                // Then send the packet
-                self.socket.send_to(&game_state.to_bytes(), self.rlviser_addr)?;
-            }
-
-            // ensure we only calculate 120 steps per second
-            let wait_time = next_time - Instant::now();
-            if wait_time > Duration::default() {
-                sleep(wait_time);
-            }
-            next_time += interval;
+            // Then break the loop
+            break Ok(());
         }
-    }
-
-    fn handle_return_message(&mut self, arena: &mut UniquePtr<Arena>, interval: &mut Duration) -> io::Result<()> {
-        let mut byte_buffer = [0];
-
-        while let Ok((_, src)) = self.socket.recv_from(&mut byte_buffer) {
-            let packet_type = UdpPacketTypes::from(byte_buffer[0]);
 
-            match packet_type {
-                UdpPacketTypes::GameState => {
-                    self.socket.set_nonblocking(false)?;
-
-                    let _ = self.socket.peek_from(&mut self.min_game_state_buf);
-
-                    let num_bytes = GameState::get_num_bytes(&self.min_game_state_buf);
-                    self.game_state_buffer.resize(num_bytes, 0);
-                    self.socket.recv_from(&mut self.game_state_buffer)?;
-
-                    self.socket.set_nonblocking(true)?;
-
-                    // set the game state
-                    let game_state = GameState::from_bytes(&self.game_state_buffer);
-                    if let Err(e) = arena.pin_mut().set_game_state(&game_state) {
-                        println!("Error setting game state: {e}");
-                    };
-                }
-                UdpPacketTypes::Connection => {
-                    println!("Connection established to {src}");
+        // changes to the game state made in rlviser are applied to the arena here
+        for event in rlviser.update(arena.pin_mut())? {
+            match event {
+                RlviserEvent::Connected(addr) => println!("Connection established to {addr}"),
+                RlviserEvent::Quit => {
+                    println!("rlviser was closed");
+                    return Ok(());
                 }
-                UdpPacketTypes::Speed => {
-                    self.socket.set_nonblocking(false)?;
-
-                    let mut speed_buffer = [0; f32::NUM_BYTES];
-                    self.socket.recv_from(&mut speed_buffer)?;
-
-                    self.socket.set_nonblocking(true)?;
+                _ => {}
+            }
+        }
 
-                    let speed = f32::from_bytes(&speed_buffer);
-                    *interval = Duration::from_secs_f32(1. / (120. * speed));
-                }
-                UdpPacketTypes::Paused => {
-                    self.socket.set_nonblocking(false)?;
-                    self.socket.recv_from(&mut byte_buffer)?;
-                    self.socket.set_nonblocking(true)?;
+        if !rlviser.is_paused() {
+            // advance the simulation by 1 tick
+            arena.pin_mut().step(1);
 
-                    self.paused = byte_buffer[0] == 1;
-                }
-                UdpPacketTypes::Quit | UdpPacketTypes::Render => {
-                    panic!("We shouldn't be receiving packets of type {packet_type:?}")
-                }
-            }
+            // send the new game state back
+            rlviser.send_game_state(&arena.pin_mut().get_game_state())?;
         }
 
-        Ok(())
+        // ensure we only calculate as many steps per second as the speed in rlviser allows
+        // speed 0.5 = half speed
+        // speed 2 = double speed
+        next_time += rlviser.tick_interval(arena.get_tick_rate());
+        let wait_time = next_time - Instant::now();
+        if wait_time > Duration::default() {
+            sleep(wait_time);
+        }
     }
 }
 
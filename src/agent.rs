@@ -0,0 +1,200 @@
+use crate::{
+    rules::{Match, MatchConfig},
+    sim::{Arena, ArenaConfig, CarConfig, CarControls, GameMode, Team},
+    stats::PlayerStats,
+    GameState,
+};
+
+#[cfg(feature = "serde_utils")]
+use serde::{Deserialize, Serialize};
+
+/// Something that can drive a car
+///
+/// Any `FnMut(&GameState, u32) -> CarControls` closure is also an agent.
+pub trait Agent {
+    /// Gets the controls for the car with the given id
+    fn get_controls(&mut self, state: &GameState, car_id: u32) -> CarControls;
+
+    /// Called before every match the agent plays in
+    fn reset(&mut self) {}
+}
+
+impl<F: FnMut(&GameState, u32) -> CarControls> Agent for F {
+    #[inline]
+    fn get_controls(&mut self, state: &GameState, car_id: u32) -> CarControls {
+        self(state, car_id)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RunnerConfig {
+    pub game_mode: GameMode,
+    pub arena_config: ArenaConfig,
+    /// Tick rate of the arena, MUST be equal to or between 15 and 120
+    pub tick_rate: u8,
+    /// How many ticks the arena is stepped between each time the agents are asked for controls
+    pub tick_skip: u32,
+    pub match_config: MatchConfig,
+    /// Stops the match after this many ticks, even if it hasn't ended
+    pub max_ticks: Option<u64>,
+}
+
+impl Default for RunnerConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            game_mode: GameMode::Soccar,
+            arena_config: ArenaConfig::default(),
+            tick_rate: 120,
+            tick_skip: 8,
+            match_config: MatchConfig::default(),
+            max_ticks: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub struct AgentResult {
+    /// The index the agent was added at
+    pub index: usize,
+    pub car_id: u32,
+    pub team: Team,
+    pub stats: PlayerStats,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub struct MatchResult {
+    /// The score as `[blue, orange]`
+    pub score: [u16; 2],
+    /// `None` if the match was a draw or didn't finish
+    pub winner: Option<Team>,
+    /// How many ticks the match lasted, including the kickoff countdowns and goal delays
+    pub ticks: u64,
+    pub overtime: bool,
+    /// If the match was stopped by `RunnerConfig::max_ticks` before it ended
+    pub timed_out: bool,
+    /// One result for every agent, in the order they were added
+    pub agents: Vec<AgentResult>,
+}
+
+struct Entry {
+    team: Team,
+    car_config: CarConfig,
+    agent: Box<dyn Agent>,
+}
+
+/// Plays full matches between agents without any rendering
+///
+/// A new arena is created for every call to `run`, so the same runner can be used for many matches.
+pub struct MatchRunner {
+    pub config: RunnerConfig,
+    agents: Vec<Entry>,
+}
+
+impl MatchRunner {
+    #[inline]
+    #[must_use]
+    pub const fn new(config: RunnerConfig) -> Self {
+        Self {
+            config,
+            agents: Vec::new(),
+        }
+    }
+
+    /// Adds an agent that will drive a car on the given team, returning its index
+    pub fn add_agent(&mut self, team: Team, car_config: &CarConfig, agent: impl Agent + 'static) -> usize {
+        self.add_boxed_agent(team, car_config, Box::new(agent))
+    }
+
+    /// Same as `add_agent`, for agents that are already boxed
+    pub fn add_boxed_agent(&mut self, team: Team, car_config: &CarConfig, agent: Box<dyn Agent>) -> usize {
+        self.agents.push(Entry {
+            team,
+            car_config: *car_config,
+            agent,
+        });
+
+        self.agents.len() - 1
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn num_agents(&self) -> usize {
+        self.agents.len()
+    }
+
+    /// Removes all agents
+    pub fn clear_agents(&mut self) {
+        self.agents.clear();
+    }
+
+    /// Plays a match until it ends or `RunnerConfig::max_ticks` is reached
+    pub fn run(&mut self) -> MatchResult {
+        let mut arena = Arena::new(self.config.game_mode, self.config.arena_config, self.config.tick_rate);
+
+        let car_ids = self
+            .agents
+            .iter()
+            .map(|entry| arena.pin_mut().add_car(entry.team, &entry.car_config))
+            .collect::<Vec<_>>();
+
+        for entry in &mut self.agents {
+            entry.agent.reset();
+        }
+
+        let mut game = Match::new(arena, self.config.match_config);
+        game.track_stats();
+
+        let tick_skip = self.config.tick_skip.max(1);
+        let mut ticks = 0;
+        let mut controls = Vec::with_capacity(self.agents.len());
+
+        while !game.is_ended() {
+            if self.config.max_ticks.is_some_and(|max_ticks| ticks >= max_ticks) {
+                break;
+            }
+
+            let state = game.arena_mut().get_game_state();
+
+            controls.clear();
+            controls.extend(
+                self.agents
+                    .iter_mut()
+                    .zip(&car_ids)
+                    .map(|(entry, &car_id)| (car_id, entry.agent.get_controls(&state, car_id))),
+            );
+
+            game.arena_mut()
+                .set_all_controls(&controls)
+                .expect("car ids come from add_car");
+
+            game.step(tick_skip);
+            ticks += u64::from(tick_skip);
+        }
+
+        let stats = game.stats().expect("stats are tracked");
+        let agents = self
+            .agents
+            .iter()
+            .zip(car_ids)
+            .enumerate()
+            .map(|(index, (entry, car_id))| AgentResult {
+                index,
+                car_id,
+                team: entry.team,
+                stats: stats.get(car_id).copied().unwrap_or_default(),
+            })
+            .collect();
+
+        MatchResult {
+            score: game.score(),
+            winner: game.winner(),
+            ticks,
+            overtime: game.is_overtime(),
+            timed_out: !game.is_ended(),
+            agents,
+        }
+    }
+}
@@ -0,0 +1,522 @@
+//! Occupancy heatmaps and trajectories of cars and the ball, exported as SVG or CSV
+
+use crate::{
+    consts,
+    math::Vec3,
+    sim::{GameMode, Team},
+    GameState,
+};
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Write},
+};
+
+#[cfg(feature = "serde_utils")]
+use serde::{Deserialize, Serialize};
+
+/// Pixels per unreal unit in exported SVGs
+const SVG_SCALE: f32 = 0.1;
+
+#[inline]
+const fn mirror(vec: Vec3) -> Vec3 {
+    Vec3::new(-vec.x, -vec.y, vec.z)
+}
+
+/// What positions a heatmap counts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub enum HeatmapSource {
+    Ball,
+    /// Every car that isn't demoed
+    Cars,
+    /// Every car on the team that isn't demoed
+    Team(Team),
+    /// The car with this ID, while it isn't demoed
+    Car(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub struct HeatmapConfig {
+    pub cells_x: usize,
+    pub cells_y: usize,
+    /// Set to 1 for a 2D grid
+    pub cells_z: usize,
+    /// Half the width of the area covered by the grid
+    pub extent_x: f32,
+    /// Half the length of the area covered by the grid
+    pub extent_y: f32,
+    /// The height of the area covered by the grid, which starts at the floor
+    pub height: f32,
+    /// If positions of orange cars should be rotated 180° around the z axis,
+    /// so that they look like they're playing on blue
+    pub mirror_orange: bool,
+}
+
+impl Default for HeatmapConfig {
+    #[inline]
+    fn default() -> Self {
+        Self::for_game_mode(GameMode::Soccar)
+    }
+}
+
+impl HeatmapConfig {
+    #[must_use]
+    /// A 2D grid of cells about 256 units wide that covers the field of the game mode
+    pub const fn for_game_mode(game_mode: GameMode) -> Self {
+        let (extent_x, extent_y, height) = match game_mode {
+            GameMode::Hoops => (
+                consts::ARENA_EXTENT_X_HOOPS,
+                consts::ARENA_EXTENT_Y_HOOPS,
+                consts::ARENA_HEIGHT_HOOPS,
+            ),
+            GameMode::Dropshot => (consts::ARENA_EXTENT_X, consts::ARENA_EXTENT_Y, consts::ARENA_HEIGHT_DROPSHOT),
+            _ => (consts::ARENA_EXTENT_X, consts::ARENA_EXTENT_Y, consts::ARENA_HEIGHT),
+        };
+
+        Self {
+            cells_x: (extent_x / 128.) as usize,
+            cells_y: (extent_y / 128.) as usize,
+            cells_z: 1,
+            extent_x,
+            extent_y,
+            height,
+            mirror_orange: true,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn with_cells(mut self, cells_x: usize, cells_y: usize, cells_z: usize) -> Self {
+        self.cells_x = cells_x;
+        self.cells_y = cells_y;
+        self.cells_z = cells_z;
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn num_cells(&self) -> usize {
+        self.cells_x * self.cells_y * self.cells_z
+    }
+
+    #[inline]
+    #[must_use]
+    /// The size of each cell, in unreal units
+    pub fn cell_size(&self) -> Vec3 {
+        Vec3::new(
+            self.extent_x * 2. / self.cells_x as f32,
+            self.extent_y * 2. / self.cells_y as f32,
+            self.height / self.cells_z as f32,
+        )
+    }
+}
+
+/// The heatmaps have a different number of cells, so they can't be merged
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridMismatch {
+    pub cells: [usize; 3],
+    pub other: [usize; 3],
+}
+
+impl fmt::Display for GridMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "A heatmap with {:?} cells can't be merged into one with {:?} cells.",
+            self.other, self.cells
+        )
+    }
+}
+
+impl Error for GridMismatch {}
+
+/// Counts how often positions land in each cell of a grid over the field
+///
+/// Positions outside of the grid, like in the goals, are counted in the closest cell.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub struct Heatmap {
+    config: HeatmapConfig,
+    counts: Vec<u32>,
+    total: u64,
+}
+
+impl Default for Heatmap {
+    #[inline]
+    fn default() -> Self {
+        Self::new(HeatmapConfig::default())
+    }
+}
+
+impl Heatmap {
+    #[must_use]
+    /// # Panics
+    ///
+    /// If the config has no cells in any direction
+    pub fn new(config: HeatmapConfig) -> Self {
+        assert!(config.num_cells() != 0, "The heatmap must have at least one cell");
+
+        Self {
+            config,
+            counts: vec![0; config.num_cells()],
+            total: 0,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn config(&self) -> &HeatmapConfig {
+        &self.config
+    }
+
+    #[inline]
+    #[must_use]
+    /// The counts of every cell, with x changing fastest and z slowest
+    pub fn counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    #[inline]
+    #[must_use]
+    /// The number of positions that were added
+    pub const fn total(&self) -> u64 {
+        self.total
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn max(&self) -> u32 {
+        self.counts.iter().copied().max().unwrap_or_default()
+    }
+
+    #[inline]
+    const fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.config.cells_y + y) * self.config.cells_x + x
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn get(&self, x: usize, y: usize, z: usize) -> u32 {
+        self.counts[self.index(x, y, z)]
+    }
+
+    #[inline]
+    #[must_use]
+    /// The fraction of all added positions that are in the cell
+    pub fn density(&self, x: usize, y: usize, z: usize) -> f32 {
+        if self.total == 0 {
+            0.
+        } else {
+            (f64::from(self.get(x, y, z)) / self.total as f64) as f32
+        }
+    }
+
+    #[must_use]
+    /// The x, y and z index of the cell that contains the position
+    pub fn cell_of(&self, pos: Vec3) -> [usize; 3] {
+        let config = &self.config;
+        let cell = |value: f32, min: f32, size: f32, cells: usize| {
+            (((value - min) / size * cells as f32).floor().max(0.) as usize).min(cells - 1)
+        };
+
+        [
+            cell(pos.x, -config.extent_x, config.extent_x * 2., config.cells_x),
+            cell(pos.y, -config.extent_y, config.extent_y * 2., config.cells_y),
+            cell(pos.z, 0., config.height, config.cells_z),
+        ]
+    }
+
+    #[must_use]
+    /// The world position of the center of the cell
+    pub fn cell_center(&self, x: usize, y: usize, z: usize) -> Vec3 {
+        let size = self.config.cell_size();
+
+        Vec3::new(
+            -self.config.extent_x + (x as f32 + 0.5) * size.x,
+            -self.config.extent_y + (y as f32 + 0.5) * size.y,
+            (z as f32 + 0.5) * size.z,
+        )
+    }
+
+    #[inline]
+    pub fn add_position(&mut self, pos: Vec3) {
+        let [x, y, z] = self.cell_of(pos);
+        let index = self.index(x, y, z);
+
+        self.counts[index] += 1;
+        self.total += 1;
+    }
+
+    /// Adds the positions from the state that the source counts
+    pub fn add_state(&mut self, state: &GameState, source: HeatmapSource) {
+        if source == HeatmapSource::Ball {
+            self.add_position(state.ball.pos);
+            return;
+        }
+
+        for car in &state.cars {
+            let is_counted = match source {
+                HeatmapSource::Ball => false,
+                HeatmapSource::Cars => true,
+                HeatmapSource::Team(team) => car.team == team,
+                HeatmapSource::Car(id) => car.id == id,
+            };
+
+            if !is_counted || car.state.is_demoed {
+                continue;
+            }
+
+            if self.config.mirror_orange && car.team == Team::Orange {
+                self.add_position(mirror(car.state.pos));
+            } else {
+                self.add_position(car.state.pos);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn add_states<'a, I: IntoIterator<Item = &'a GameState>>(&mut self, states: I, source: HeatmapSource) {
+        for state in states {
+            self.add_state(state, source);
+        }
+    }
+
+    /// Adds the counts of another heatmap with the same number of cells
+    pub fn merge(&mut self, other: &Self) -> Result<(), GridMismatch> {
+        let cells = |config: &HeatmapConfig| [config.cells_x, config.cells_y, config.cells_z];
+        if cells(&self.config) != cells(&other.config) {
+            return Err(GridMismatch {
+                cells: cells(&self.config),
+                other: cells(&other.config),
+            });
+        }
+
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+        self.total += other.total;
+
+        Ok(())
+    }
+
+    #[must_use]
+    /// The heatmap rotated 180° around the z axis, like it was seen from the other team
+    pub fn mirrored(&self) -> Self {
+        let mut mirrored = Self {
+            config: self.config,
+            counts: vec![0; self.counts.len()],
+            total: self.total,
+        };
+
+        let HeatmapConfig { cells_x, cells_y, .. } = self.config;
+        for z in 0..self.config.cells_z {
+            for y in 0..cells_y {
+                for x in 0..cells_x {
+                    let index = mirrored.index(cells_x - 1 - x, cells_y - 1 - y, z);
+                    mirrored.counts[index] = self.get(x, y, z);
+                }
+            }
+        }
+
+        mirrored
+    }
+
+    #[must_use]
+    /// Sums up every layer of a 3D heatmap into a 2D heatmap
+    pub fn flatten(&self) -> Self {
+        let config = HeatmapConfig {
+            cells_z: 1,
+            ..self.config
+        };
+        let layer_size = config.num_cells();
+
+        let mut counts = vec![0; layer_size];
+        for layer in self.counts.chunks_exact(layer_size) {
+            for (count, layer) in counts.iter_mut().zip(layer) {
+                *count += layer;
+            }
+        }
+
+        Self {
+            config,
+            counts,
+            total: self.total,
+        }
+    }
+
+    /// Writes a row with the cell's center, count, and density for every cell
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "x,y,z,count,density")?;
+
+        for z in 0..self.config.cells_z {
+            for y in 0..self.config.cells_y {
+                for x in 0..self.config.cells_x {
+                    let center = self.cell_center(x, y, z);
+                    writeln!(
+                        writer,
+                        "{},{},{},{},{}",
+                        center.x,
+                        center.y,
+                        center.z,
+                        self.get(x, y, z),
+                        self.density(x, y, z)
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes a top-down SVG of the heatmap, with orange's side at the top
+    ///
+    /// 3D heatmaps are flattened first. Cells are colored relative to the busiest cell.
+    pub fn write_svg<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.write_svg_with(writer, &[])
+    }
+
+    /// Writes a top-down SVG of the heatmap with the trajectories drawn on top
+    pub fn write_svg_with<W: Write>(&self, mut writer: W, trajectories: &[Trajectory]) -> io::Result<()> {
+        let flat = self.flatten();
+        let config = &flat.config;
+        let size = config.cell_size();
+        let max = flat.max();
+
+        write_svg_header(&mut writer, config.extent_x, config.extent_y)?;
+
+        for y in 0..config.cells_y {
+            for x in 0..config.cells_x {
+                let count = flat.get(x, y, 0);
+                if count == 0 {
+                    continue;
+                }
+
+                let [r, g, b] = heat_color(count as f32 / max as f32);
+                let left = -config.extent_x + x as f32 * size.x;
+                // svg y points down, so the top of the cell is its highest world y
+                let top = -(-config.extent_y + (y + 1) as f32 * size.y);
+                writeln!(
+                    writer,
+                    r#"<rect x="{left}" y="{top}" width="{}" height="{}" fill="rgb({r},{g},{b})"/>"#,
+                    size.x, size.y,
+                )?;
+            }
+        }
+
+        for trajectory in trajectories {
+            trajectory.write_svg_polyline(&mut writer)?;
+        }
+
+        writeln!(writer, "</svg>")
+    }
+}
+
+fn write_svg_header<W: Write>(writer: &mut W, extent_x: f32, extent_y: f32) -> io::Result<()> {
+    let width = extent_x * 2.;
+    let height = extent_y * 2.;
+
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {width} {height}">"#,
+        width * SVG_SCALE,
+        height * SVG_SCALE,
+        -extent_x,
+        -extent_y,
+    )?;
+    writeln!(
+        writer,
+        r#"<rect x="{}" y="{}" width="{width}" height="{height}" fill="rgb(20,20,24)" stroke="rgb(210,210,210)" stroke-width="16"/>"#,
+        -extent_x, -extent_y,
+    )?;
+    writeln!(
+        writer,
+        r#"<line x1="{}" y1="0" x2="{extent_x}" y2="0" stroke="rgb(210,210,210)" stroke-width="8"/>"#,
+        -extent_x,
+    )
+}
+
+/// Goes from black to red to yellow to white as `value` goes from 0 to 1
+fn heat_color(value: f32) -> [u8; 3] {
+    let value = value.clamp(0., 1.) * 3.;
+    let channel = |start: f32| ((value - start).clamp(0., 1.) * 255.).round() as u8;
+
+    [channel(0.), channel(1.), channel(2.)]
+}
+
+/// The path of the ball or a car over time, for drawing on top of heatmaps
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub struct Trajectory {
+    pub positions: Vec<Vec3>,
+    /// The color of the line, in RGB
+    pub color: [u8; 3],
+}
+
+impl Trajectory {
+    #[must_use]
+    /// The path of the ball, in white
+    pub fn ball<'a, I: IntoIterator<Item = &'a GameState>>(states: I) -> Self {
+        Self {
+            positions: states.into_iter().map(|state| state.ball.pos).collect(),
+            color: [255, 255, 255],
+        }
+    }
+
+    #[must_use]
+    /// The path of the car with the ID in its team's color, skipping states where it doesn't exist or is demoed
+    ///
+    /// If `mirror_orange` is set and the car is on orange, the path is rotated 180° around the z axis.
+    pub fn car<'a, I: IntoIterator<Item = &'a GameState>>(states: I, id: u32, mirror_orange: bool) -> Self {
+        let mut trajectory = Self::default();
+
+        for car in states
+            .into_iter()
+            .filter_map(|state| state.cars.iter().find(|car| car.id == id))
+        {
+            trajectory.color = match car.team {
+                Team::Blue => [40, 110, 255],
+                Team::Orange => [255, 140, 20],
+            };
+
+            if car.state.is_demoed {
+                continue;
+            }
+
+            if mirror_orange && car.team == Team::Orange {
+                trajectory.positions.push(mirror(car.state.pos));
+            } else {
+                trajectory.positions.push(car.state.pos);
+            }
+        }
+
+        trajectory
+    }
+
+    fn write_svg_polyline<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let [r, g, b] = self.color;
+
+        write!(
+            writer,
+            r#"<polyline fill="none" stroke="rgb({r},{g},{b})" stroke-width="24" points=""#
+        )?;
+        for (i, pos) in self.positions.iter().enumerate() {
+            let separator = if i == 0 { "" } else { " " };
+            write!(writer, "{separator}{},{}", pos.x, -pos.y)?;
+        }
+        writeln!(writer, r#""/>"#)
+    }
+
+    /// Writes a top-down SVG of the field with only the trajectories, with orange's side at the top
+    pub fn write_svg<W: Write>(mut writer: W, trajectories: &[Self], game_mode: GameMode) -> io::Result<()> {
+        let config = HeatmapConfig::for_game_mode(game_mode);
+        write_svg_header(&mut writer, config.extent_x, config.extent_y)?;
+
+        for trajectory in trajectories {
+            trajectory.write_svg_polyline(&mut writer)?;
+        }
+
+        writeln!(writer, "</svg>")
+    }
+}
@@ -0,0 +1,242 @@
+use crate::{
+    agent::Agent,
+    consts,
+    math::Vec3,
+    sim::{CarControls, CarState, Team},
+    GameState,
+};
+
+/// Half the width of a soccar goal
+const GOAL_HALF_WIDTH: f32 = 892.755;
+/// How far the ball can be from the center of the field for it to still be a kickoff
+const KICKOFF_MAX_DIST: f32 = 1.;
+
+/// Always Towards Ball Agent
+///
+/// Drives at the ball at full throttle and never boosts, jumps or flips.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Atba;
+
+impl Agent for Atba {
+    fn get_controls(&mut self, state: &GameState, car_id: u32) -> CarControls {
+        let Some(car) = find_car(state, car_id) else {
+            return CarControls::default();
+        };
+
+        CarControls {
+            throttle: 1.,
+            steer: steer_toward(car, state.ball.pos),
+            ..Default::default()
+        }
+    }
+}
+
+/// Chases the ball, boosting when it's facing it and picking up big pads when low on boost
+#[derive(Clone, Copy, Debug)]
+pub struct BallChaser {
+    /// Below this amount of boost, the bot detours to the closest big pad
+    pub min_boost: f32,
+    /// The bot won't detour for boost if the ball is closer than this
+    pub detour_max_ball_dist: f32,
+}
+
+impl Default for BallChaser {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            min_boost: 20.,
+            detour_max_ball_dist: 2500.,
+        }
+    }
+}
+
+impl Agent for BallChaser {
+    fn get_controls(&mut self, state: &GameState, car_id: u32) -> CarControls {
+        let Some(car) = find_car(state, car_id) else {
+            return CarControls::default();
+        };
+
+        let mut target = state.ball.pos;
+
+        if car.boost < self.min_boost && car.pos.distance(target) > self.detour_max_ball_dist {
+            if let Some(pad) = state
+                .pads
+                .iter()
+                .filter(|pad| pad.config.is_big && pad.state.is_active)
+                .min_by(|a, b| {
+                    car.pos
+                        .distance(a.config.position)
+                        .total_cmp(&car.pos.distance(b.config.position))
+                })
+            {
+                target = pad.config.position;
+            }
+        }
+
+        let angle = angle_to(car, target);
+
+        CarControls {
+            throttle: 1.,
+            steer: steer(angle),
+            // slide around sharp turns instead of doing a big circle
+            handbrake: car.is_on_ground && angle.abs() > 2.,
+            boost: car.is_on_ground && angle.abs() < 0.3 && !car.is_supersonic,
+            ..Default::default()
+        }
+    }
+}
+
+/// Stays between the ball and its own goal, only challenging when the ball gets close
+#[derive(Clone, Copy, Debug)]
+pub struct Goalie {
+    /// How far in front of the goal line the bot waits
+    pub goal_dist: f32,
+    /// If the ball is closer than this to the goal line, the bot goes for it
+    pub challenge_dist: f32,
+}
+
+impl Default for Goalie {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            goal_dist: 300.,
+            challenge_dist: 2000.,
+        }
+    }
+}
+
+impl Agent for Goalie {
+    fn get_controls(&mut self, state: &GameState, car_id: u32) -> CarControls {
+        let Some(info) = state.cars.iter().find(|car| car.id == car_id) else {
+            return CarControls::default();
+        };
+
+        let car = &info.state;
+        let ball = state.ball.pos;
+
+        // the direction of the goal the bot is defending
+        let goal_dir = if info.team == Team::Blue { -1. } else { 1. };
+        let goal_line_y = goal_dir * consts::ARENA_EXTENT_Y;
+
+        if (goal_line_y - ball.y).abs() < self.challenge_dist {
+            return CarControls {
+                throttle: 1.,
+                steer: steer_toward(car, ball),
+                boost: car.is_on_ground && angle_to(car, ball).abs() < 0.3,
+                ..Default::default()
+            };
+        }
+
+        // shadow the ball along the goal line
+        let target = Vec3::new(
+            ball.x.clamp(-GOAL_HALF_WIDTH, GOAL_HALF_WIDTH),
+            goal_line_y - goal_dir * self.goal_dist,
+            0.,
+        );
+
+        let target_dist = car.pos.distance(target);
+        let angle = angle_to(car, target);
+
+        CarControls {
+            // slow down when getting close so the bot doesn't overshoot
+            throttle: (target_dist / 500.).min(1.),
+            steer: steer(angle),
+            handbrake: car.is_on_ground && angle.abs() > 2.,
+            ..Default::default()
+        }
+    }
+}
+
+/// Only plays kickoffs
+///
+/// Boosts straight at the ball and front flips into it, then sits still until the next kickoff.
+#[derive(Clone, Copy, Debug)]
+pub struct KickoffBot {
+    /// How close the car gets to the ball before flipping
+    pub flip_dist: f32,
+    flip_stage: u8,
+}
+
+impl Default for KickoffBot {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            flip_dist: 750.,
+            flip_stage: 0,
+        }
+    }
+}
+
+impl Agent for KickoffBot {
+    fn get_controls(&mut self, state: &GameState, car_id: u32) -> CarControls {
+        let Some(car) = find_car(state, car_id) else {
+            return CarControls::default();
+        };
+
+        if car.is_on_ground && !car.has_jumped {
+            self.flip_stage = 0;
+        }
+
+        let ball = &state.ball;
+        let is_kickoff = ball.pos.x.abs() < KICKOFF_MAX_DIST
+            && ball.pos.y.abs() < KICKOFF_MAX_DIST
+            && ball.vel.length() < KICKOFF_MAX_DIST;
+
+        if !is_kickoff {
+            return CarControls::default();
+        }
+
+        let mut controls = CarControls {
+            throttle: 1.,
+            steer: steer_toward(car, ball.pos),
+            boost: true,
+            ..Default::default()
+        };
+
+        if car.pos.distance(ball.pos) < self.flip_dist || self.flip_stage != 0 {
+            // jump, let go of jump, then jump again while pitching forward to flip
+            match self.flip_stage {
+                0 => controls.jump = true,
+                1 => {}
+                2 => {
+                    controls.jump = true;
+                    controls.pitch = -1.;
+                }
+                _ => controls.pitch = -1.,
+            }
+
+            self.flip_stage = self.flip_stage.saturating_add(1);
+        }
+
+        controls
+    }
+
+    #[inline]
+    fn reset(&mut self) {
+        self.flip_stage = 0;
+    }
+}
+
+#[inline]
+fn find_car(state: &GameState, car_id: u32) -> Option<&CarState> {
+    state.cars.iter().find(|car| car.id == car_id).map(|car| &car.state)
+}
+
+/// The angle between the car's forward direction and the target, positive if the target is to the right
+fn angle_to(car: &CarState, target: Vec3) -> f32 {
+    let diff = target - car.pos;
+    let forward = diff.dot(car.rot_mat.forward);
+    let right = diff.dot(car.rot_mat.right);
+
+    right.atan2(forward)
+}
+
+#[inline]
+fn steer(angle: f32) -> f32 {
+    (angle * 2.).clamp(-1., 1.)
+}
+
+#[inline]
+fn steer_toward(car: &CarState, target: Vec3) -> f32 {
+    steer(angle_to(car, target))
+}
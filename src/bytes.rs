@@ -3,12 +3,17 @@ use crate::{
     math::{RotMat, Vec3},
     render::{Color, Render, RenderMessage, Vec2},
     sim::{
-        BallHitInfo, BallState, BoostPadConfig, BoostPadState, CarConfig, CarContact, CarControls, CarState, DropshotInfo,
-        GameMode, HeatseekerInfo, Team, WheelPairConfig, WorldContact,
+        ArenaConfig, ArenaMemWeightMode, BallHitInfo, BallState, BoostPadConfig, BoostPadState, CarConfig, CarContact,
+        CarControls, CarState, DemoMode, DropshotInfo, GameMode, HeatseekerInfo, MutatorConfig, Team, WheelPairConfig,
+        WorldContact,
     },
     BoostPad, CarInfo, DropshotTile, GameState, TileState,
 };
-use core::fmt;
+use core::{fmt, ops::BitOr};
+use std::{
+    error::Error,
+    io::{self, Read, Write},
+};
 
 pub trait FromBytes {
     fn from_bytes(bytes: &[u8]) -> Self;
@@ -18,6 +23,110 @@ pub trait FromBytesExact: FromBytes {
     const NUM_BYTES: usize;
 }
 
+/// Decoding that returns an error instead of panicking on malformed bytes
+pub trait TryFromBytes: Sized {
+    /// # Errors
+    ///
+    /// Returns a `DecodeError` if the bytes are too short, too long, or contain an invalid value
+    fn try_from_bytes(bytes: &[u8]) -> Result<Self, DecodeError>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeReason {
+    /// More bytes were needed than were left
+    UnexpectedEnd { needed: usize, remaining: usize },
+    /// There were bytes left over after decoding
+    TrailingBytes(usize),
+    /// The byte isn't a valid value or tag for the type with the given name
+    InvalidValue { name: &'static str, value: u8 },
+    /// The length written in the bytes doesn't match the number of bytes
+    LengthMismatch { expected: usize, found: usize },
+    /// The bytes were written with a newer version of the format
+    UnsupportedVersion(u16),
+    /// The length written in the bytes is more than the most that's allowed
+    TooLong { len: usize, max: usize },
+}
+
+impl fmt::Display for DecodeReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd { needed, remaining } => {
+                write!(f, "needed {needed} bytes but only {remaining} were left")
+            }
+            Self::TrailingBytes(num_bytes) => write!(f, "{num_bytes} bytes were left over"),
+            Self::InvalidValue { name, value } => write!(f, "{value} is not a valid {name}"),
+            Self::LengthMismatch { expected, found } => write!(f, "expected {expected} bytes but found {found}"),
+            Self::UnsupportedVersion(version) => write!(f, "version {version} is newer than the supported versions"),
+            Self::TooLong { len, max } => write!(f, "{len} bytes is more than the limit of {max}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Error for when bytes can't be decoded
+pub struct DecodeError {
+    /// Where in the bytes the problem was found
+    pub offset: usize,
+    pub reason: DecodeReason,
+}
+
+impl DecodeError {
+    #[inline]
+    #[must_use]
+    pub const fn new(offset: usize, reason: DecodeReason) -> Self {
+        Self { offset, reason }
+    }
+
+    #[inline]
+    pub(crate) const fn offset_by(self, offset: usize) -> Self {
+        Self::new(self.offset + offset, self.reason)
+    }
+}
+
+impl Error for DecodeError {}
+impl fmt::Display for DecodeError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to decode bytes at offset {}: {}.", self.offset, self.reason)
+    }
+}
+
+impl From<DecodeError> for io::Error {
+    #[inline]
+    fn from(error: DecodeError) -> Self {
+        Self::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+/// Checks a length read from untrusted bytes, before anything is allocated for it
+pub(crate) const fn check_max_len(len: usize, max: usize) -> Result<usize, DecodeError> {
+    if len > max {
+        Err(DecodeError::new(0, DecodeReason::TooLong { len, max }))
+    } else {
+        Ok(len)
+    }
+}
+
+/// Checks that there are exactly `num_bytes` bytes
+fn check_len(bytes: &[u8], num_bytes: usize) -> Result<(), DecodeError> {
+    if bytes.len() < num_bytes {
+        Err(DecodeError::new(
+            0,
+            DecodeReason::UnexpectedEnd {
+                needed: num_bytes,
+                remaining: bytes.len(),
+            },
+        ))
+    } else if bytes.len() > num_bytes {
+        Err(DecodeError::new(
+            num_bytes,
+            DecodeReason::TrailingBytes(bytes.len() - num_bytes),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
 struct ByteReader<'a> {
     idx: usize,
     bytes: &'a [u8],
@@ -42,6 +151,117 @@ impl Drop for ByteReader<'_> {
     }
 }
 
+/// Like `ByteReader`, but returns an error instead of panicking
+pub(crate) struct TryByteReader<'a> {
+    idx: usize,
+    bytes: &'a [u8],
+}
+
+impl<'a> TryByteReader<'a> {
+    #[inline]
+    pub const fn new(bytes: &'a [u8]) -> Self {
+        Self { idx: 0, bytes }
+    }
+
+    pub fn read<I: TryFromBytes + FromBytesExact>(&mut self) -> Result<I, DecodeError> {
+        let start = self.idx;
+        I::try_from_bytes(self.take(I::NUM_BYTES)?).map_err(|e| e.offset_by(start))
+    }
+
+    /// Reads `num_items` items into a vec, failing early if there aren't enough bytes left for all of them
+    pub fn read_vec<I: TryFromBytes + FromBytesExact>(&mut self, num_items: usize) -> Result<Vec<I>, DecodeError> {
+        let remaining = self.bytes.len() - self.idx;
+        if num_items.saturating_mul(I::NUM_BYTES) > remaining {
+            return Err(DecodeError::new(
+                self.idx,
+                DecodeReason::UnexpectedEnd {
+                    needed: num_items.saturating_mul(I::NUM_BYTES),
+                    remaining,
+                },
+            ));
+        }
+
+        (0..num_items).map(|_| self.read()).collect()
+    }
+
+    /// Reads a record size followed by `num_items` records of that size
+    ///
+    /// Only the first `known_num_bytes` bytes of each record are decoded,
+    /// so fields added to the end of a record by a newer encoder are skipped.
+    pub fn read_records<I>(
+        &mut self,
+        num_items: usize,
+        known_num_bytes: usize,
+        decode: impl Fn(&[u8]) -> Result<I, DecodeError>,
+    ) -> Result<Vec<I>, DecodeError> {
+        let offset = self.idx;
+        let record_num_bytes = self.read::<u32>()? as usize;
+        if record_num_bytes < known_num_bytes {
+            return Err(DecodeError::new(
+                offset,
+                DecodeReason::LengthMismatch {
+                    expected: known_num_bytes,
+                    found: record_num_bytes,
+                },
+            ));
+        }
+
+        let remaining = self.bytes.len() - self.idx;
+        if num_items.saturating_mul(record_num_bytes) > remaining {
+            return Err(DecodeError::new(
+                self.idx,
+                DecodeReason::UnexpectedEnd {
+                    needed: num_items.saturating_mul(record_num_bytes),
+                    remaining,
+                },
+            ));
+        }
+
+        (0..num_items)
+            .map(|_| {
+                let start = self.idx;
+                let record = self.take(record_num_bytes)?;
+                decode(&record[..known_num_bytes]).map_err(|e| e.offset_by(start))
+            })
+            .collect()
+    }
+
+    /// How many bytes have been read so far
+    #[inline]
+    pub const fn position(&self) -> usize {
+        self.idx
+    }
+
+    pub fn take(&mut self, num_bytes: usize) -> Result<&'a [u8], DecodeError> {
+        let remaining = self.bytes.len() - self.idx;
+        if num_bytes > remaining {
+            return Err(DecodeError::new(
+                self.idx,
+                DecodeReason::UnexpectedEnd {
+                    needed: num_bytes,
+                    remaining,
+                },
+            ));
+        }
+
+        let bytes = &self.bytes[self.idx..self.idx + num_bytes];
+        self.idx += num_bytes;
+        Ok(bytes)
+    }
+
+    /// Makes sure all of the bytes were read
+    pub fn finish(self) -> Result<(), DecodeError> {
+        if self.idx == self.bytes.len() {
+            Ok(())
+        } else {
+            Err(DecodeError::new(
+                self.idx,
+                DecodeReason::TrailingBytes(self.bytes.len() - self.idx),
+            ))
+        }
+    }
+}
+
 impl FromBytes for bool {
     #[inline]
     fn from_bytes(bytes: &[u8]) -> Self {
@@ -108,6 +328,17 @@ impl FromBytes for u64 {
     }
 }
 
+impl FromBytesExact for i16 {
+    const NUM_BYTES: usize = 2;
+}
+
+impl FromBytes for i16 {
+    #[inline]
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self::from_le_bytes([bytes[0], bytes[1]])
+    }
+}
+
 impl FromBytesExact for i32 {
     const NUM_BYTES: usize = 4;
 }
@@ -119,6 +350,20 @@ impl FromBytes for i32 {
     }
 }
 
+macro_rules! impl_try_from_bytes_via_from {
+    ($($t:ty),+) => {
+        $(impl TryFromBytes for $t {
+            #[inline]
+            fn try_from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+                check_len(bytes, Self::NUM_BYTES)?;
+                Ok(Self::from_bytes(bytes))
+            }
+        })+
+    };
+}
+
+impl_try_from_bytes_via_from!(bool, f32, u8, u16, u32, u64, i16, i32);
+
 impl<T: FromBytesExact + fmt::Debug, const N: usize> FromBytesExact for [T; N] {
     const NUM_BYTES: usize = T::NUM_BYTES * N;
 }
@@ -132,6 +377,20 @@ impl<T: FromBytesExact + fmt::Debug, const N: usize> FromBytes for [T; N] {
     }
 }
 
+impl<T: TryFromBytes + FromBytesExact + Copy + Default, const N: usize> TryFromBytes for [T; N] {
+    fn try_from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = TryByteReader::new(bytes);
+
+        let mut items = [T::default(); N];
+        for item in &mut items {
+            *item = reader.read()?;
+        }
+
+        reader.finish()?;
+        Ok(items)
+    }
+}
+
 macro_rules! impl_from_bytes_exact_for_enums {
     ($($variant:ident),*) => {
         $(
@@ -145,11 +404,26 @@ macro_rules! impl_from_bytes_exact_for_enums {
                     Self::try_from(bytes[0]).unwrap()
                 }
             }
+
+            impl TryFromBytes for $variant {
+                fn try_from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+                    check_len(bytes, Self::NUM_BYTES)?;
+                    Self::try_from(bytes[0]).map_err(|()| {
+                        DecodeError::new(
+                            0,
+                            DecodeReason::InvalidValue {
+                                name: stringify!($variant),
+                                value: bytes[0],
+                            },
+                        )
+                    })
+                }
+            }
         )*
     };
 }
 
-impl_from_bytes_exact_for_enums!(Team, GameMode, TileState);
+impl_from_bytes_exact_for_enums!(Team, GameMode, TileState, DemoMode, ArenaMemWeightMode);
 
 impl FromBytesExact for Vec3 {
     const NUM_BYTES: usize = f32::NUM_BYTES * 3;
@@ -162,6 +436,15 @@ impl FromBytes for Vec3 {
     }
 }
 
+impl TryFromBytes for Vec3 {
+    fn try_from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = TryByteReader::new(bytes);
+        let vec = Self::new(reader.read()?, reader.read()?, reader.read()?);
+        reader.finish()?;
+        Ok(vec)
+    }
+}
+
 macro_rules! impl_from_bytes_exact {
     ($t:ty, $n:expr, $($p:ident),+) => {
         impl FromBytes for $t {
@@ -173,6 +456,17 @@ macro_rules! impl_from_bytes_exact {
             }
         }
 
+        impl TryFromBytes for $t {
+            fn try_from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+                let mut reader = TryByteReader::new(bytes);
+                let item = Self {
+                    $($p: reader.read()?,)+
+                };
+                reader.finish()?;
+                Ok(item)
+            }
+        }
+
         impl FromBytesExact for $t {
             const NUM_BYTES: usize = $n;
         }
@@ -232,7 +526,7 @@ macro_rules! impl_to_bytes_exact_via_std {
     };
 }
 
-impl_to_bytes_exact_via_std!(u8, u16, u32, u64, i32, f32);
+impl_to_bytes_exact_via_std!(u8, u16, u32, u64, i16, i32, f32);
 
 macro_rules! impl_to_bytes_exact_as_u8 {
     ($($t:ty),+) => {
@@ -244,7 +538,7 @@ macro_rules! impl_to_bytes_exact_as_u8 {
     };
 }
 
-impl_to_bytes_exact_as_u8!(bool, Team, GameMode, TileState);
+impl_to_bytes_exact_as_u8!(bool, Team, GameMode, TileState, DemoMode, ArenaMemWeightMode);
 
 macro_rules! impl_to_bytes_exact {
     ($t:ty, $($p:ident),+) => {
@@ -402,254 +696,1139 @@ impl_bytes_exact!(
     config
 );
 impl_bytes_exact!(DropshotTile, Vec3::NUM_BYTES + 1, pos, state);
+impl_bytes_exact!(
+    MutatorConfig,
+    Vec3::NUM_BYTES + f32::NUM_BYTES * 24 + 5,
+    gravity,
+    car_mass,
+    car_world_friction,
+    car_world_restitution,
+    ball_mass,
+    ball_max_speed,
+    ball_drag,
+    ball_world_friction,
+    ball_world_restitution,
+    jump_accel,
+    jump_immediate_force,
+    boost_accel_ground,
+    boost_accel_air,
+    boost_used_per_second,
+    respawn_delay,
+    bump_cooldown_time,
+    boost_pad_cooldown_big,
+    boost_pad_cooldown_small,
+    car_spawn_boost_amount,
+    ball_hit_extra_force_scale,
+    bump_force_scale,
+    ball_radius,
+    unlimited_flips,
+    unlimited_double_jumps,
+    recharge_boost_enabled,
+    recharge_boost_per_second,
+    recharge_boost_delay,
+    demo_mode,
+    enable_team_demos,
+    goal_base_threshold_y
+);
+impl_bytes_exact!(
+    ArenaConfig,
+    1 + Vec3::NUM_BYTES * 2 + f32::NUM_BYTES + 2 + u32::NUM_BYTES,
+    mem_weight_mode,
+    min_pos,
+    max_pos,
+    max_aabb_len,
+    no_ball_rot,
+    use_custom_broadphase,
+    max_objects
+);
 
 impl Render {
-    fn count_bytes(&self) -> usize {
+    fn count_num_bytes(&self) -> usize {
         match self {
             Self::Line2D { .. } => 1 + Vec2::NUM_BYTES * 2 + Color::NUM_BYTES,
             Self::Line { .. } => 1 + Vec3::NUM_BYTES * 2 + Color::NUM_BYTES,
             Self::LineStrip { positions, .. } => 1 + u16::NUM_BYTES + positions.len() * Vec3::NUM_BYTES + Color::NUM_BYTES,
+            Self::Text2D { text, .. } => {
+                1 + Vec2::NUM_BYTES + u16::NUM_BYTES + text_bytes(text).len() + f32::NUM_BYTES + Color::NUM_BYTES
+            }
+            Self::Text3D { text, .. } => {
+                1 + Vec3::NUM_BYTES + u16::NUM_BYTES + text_bytes(text).len() + f32::NUM_BYTES + Color::NUM_BYTES
+            }
+            Self::Sphere { .. } => 1 + Vec3::NUM_BYTES + f32::NUM_BYTES + Color::NUM_BYTES,
+            Self::Box { .. } => 1 + Vec3::NUM_BYTES * 2 + Color::NUM_BYTES,
+            Self::OrientedBox { .. } => 1 + Vec3::NUM_BYTES * 2 + RotMat::NUM_BYTES + Color::NUM_BYTES,
+            Self::Rect2D { .. } => 1 + Vec2::NUM_BYTES * 2 + 1 + Color::NUM_BYTES,
+            Self::Arrow { .. } => 1 + Vec3::NUM_BYTES * 2 + Color::NUM_BYTES,
         }
     }
 
-    fn from_reader(reader: &mut ByteReader) -> Self {
-        match reader.read::<u8>() {
+    fn read_text(reader: &mut TryByteReader) -> Result<String, DecodeError> {
+        let len = reader.read::<u16>()?;
+        let offset = reader.idx;
+        let bytes = reader.take(len.into())?;
+
+        String::from_utf8(bytes.to_vec()).map_err(|e| {
+            let idx = e.utf8_error().valid_up_to();
+            DecodeError::new(
+                offset + idx,
+                DecodeReason::InvalidValue {
+                    name: "UTF-8 text",
+                    value: bytes[idx],
+                },
+            )
+        })
+    }
+
+    fn try_from_reader(reader: &mut TryByteReader) -> Result<Self, DecodeError> {
+        let offset = reader.idx;
+
+        Ok(match reader.read::<u8>()? {
             0 => Self::Line2D {
-                start: reader.read(),
-                end: reader.read(),
-                color: reader.read(),
+                start: reader.read()?,
+                end: reader.read()?,
+                color: reader.read()?,
             },
             1 => Self::Line {
-                start: reader.read(),
-                end: reader.read(),
-                color: reader.read(),
+                start: reader.read()?,
+                end: reader.read()?,
+                color: reader.read()?,
             },
-            2 => Self::LineStrip {
-                positions: (0..reader.read::<u16>()).map(|_| reader.read()).collect(),
-                color: reader.read(),
+            2 => {
+                let num_positions = reader.read::<u16>()?;
+                Self::LineStrip {
+                    positions: reader.read_vec(num_positions.into())?,
+                    color: reader.read()?,
+                }
+            }
+            3 => Self::Text2D {
+                pos: reader.read()?,
+                text: Self::read_text(reader)?,
+                scale: reader.read()?,
+                color: reader.read()?,
             },
-            _ => unreachable!(),
-        }
+            4 => Self::Text3D {
+                pos: reader.read()?,
+                text: Self::read_text(reader)?,
+                scale: reader.read()?,
+                color: reader.read()?,
+            },
+            5 => Self::Sphere {
+                center: reader.read()?,
+                radius: reader.read()?,
+                color: reader.read()?,
+            },
+            6 => Self::Box {
+                center: reader.read()?,
+                size: reader.read()?,
+                color: reader.read()?,
+            },
+            7 => Self::OrientedBox {
+                center: reader.read()?,
+                size: reader.read()?,
+                rot_mat: reader.read()?,
+                color: reader.read()?,
+            },
+            8 => Self::Rect2D {
+                pos: reader.read()?,
+                size: reader.read()?,
+                filled: reader.read()?,
+                color: reader.read()?,
+            },
+            9 => Self::Arrow {
+                start: reader.read()?,
+                end: reader.read()?,
+                color: reader.read()?,
+            },
+            tag => {
+                return Err(DecodeError::new(
+                    offset,
+                    DecodeReason::InvalidValue {
+                        name: "Render",
+                        value: tag,
+                    },
+                ))
+            }
+        })
     }
 }
 
 impl ToBytes for Render {
-    fn to_bytes(&self) -> Vec<u8> {
-        let num_bytes = self.count_bytes();
-        let mut bytes = Vec::with_capacity(num_bytes);
+    #[inline]
+    fn num_bytes(&self) -> usize {
+        self.count_num_bytes()
+    }
 
+    fn write_into<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
         match self {
             Self::Line2D { start, end, color } => {
-                bytes.push(0);
-                bytes.extend_from_slice(&start.to_bytes());
-                bytes.extend_from_slice(&end.to_bytes());
-                bytes.extend_from_slice(&color.to_bytes());
+                writer.write_all(&[0])?;
+                writer.write_all(&start.to_bytes())?;
+                writer.write_all(&end.to_bytes())?;
+                writer.write_all(&color.to_bytes())
             }
             Self::Line { start, end, color } => {
-                bytes.push(1);
-                bytes.extend_from_slice(&start.to_bytes());
-                bytes.extend_from_slice(&end.to_bytes());
-                bytes.extend_from_slice(&color.to_bytes());
+                writer.write_all(&[1])?;
+                writer.write_all(&start.to_bytes())?;
+                writer.write_all(&end.to_bytes())?;
+                writer.write_all(&color.to_bytes())
             }
             Self::LineStrip { positions, color } => {
-                bytes.push(2);
-                bytes.extend_from_slice(&(positions.len() as u16).to_bytes());
+                writer.write_all(&[2])?;
+                writer.write_all(&(positions.len() as u16).to_bytes())?;
 
                 for pos in positions {
-                    bytes.extend_from_slice(&pos.to_bytes());
+                    writer.write_all(&pos.to_bytes())?;
                 }
 
-                bytes.extend_from_slice(&color.to_bytes());
+                writer.write_all(&color.to_bytes())
+            }
+            Self::Text2D { pos, text, scale, color } => {
+                writer.write_all(&[3])?;
+                writer.write_all(&pos.to_bytes())?;
+                write_text(writer, text)?;
+                writer.write_all(&scale.to_bytes())?;
+                writer.write_all(&color.to_bytes())
+            }
+            Self::Text3D { pos, text, scale, color } => {
+                writer.write_all(&[4])?;
+                writer.write_all(&pos.to_bytes())?;
+                write_text(writer, text)?;
+                writer.write_all(&scale.to_bytes())?;
+                writer.write_all(&color.to_bytes())
+            }
+            Self::Sphere { center, radius, color } => {
+                writer.write_all(&[5])?;
+                writer.write_all(&center.to_bytes())?;
+                writer.write_all(&radius.to_bytes())?;
+                writer.write_all(&color.to_bytes())
+            }
+            Self::Box { center, size, color } => {
+                writer.write_all(&[6])?;
+                writer.write_all(&center.to_bytes())?;
+                writer.write_all(&size.to_bytes())?;
+                writer.write_all(&color.to_bytes())
+            }
+            Self::OrientedBox {
+                center,
+                size,
+                rot_mat,
+                color,
+            } => {
+                writer.write_all(&[7])?;
+                writer.write_all(&center.to_bytes())?;
+                writer.write_all(&size.to_bytes())?;
+                writer.write_all(&rot_mat.to_bytes())?;
+                writer.write_all(&color.to_bytes())
+            }
+            Self::Rect2D {
+                pos,
+                size,
+                filled,
+                color,
+            } => {
+                writer.write_all(&[8])?;
+                writer.write_all(&pos.to_bytes())?;
+                writer.write_all(&size.to_bytes())?;
+                writer.write_all(&filled.to_bytes())?;
+                writer.write_all(&color.to_bytes())
+            }
+            Self::Arrow { start, end, color } => {
+                writer.write_all(&[9])?;
+                writer.write_all(&start.to_bytes())?;
+                writer.write_all(&end.to_bytes())?;
+                writer.write_all(&color.to_bytes())
             }
         }
+    }
+}
 
-        debug_assert_eq!(bytes.len(), num_bytes);
-
-        bytes
+/// The bytes of the text that fit in a render, cut off at a character boundary
+fn text_bytes(text: &str) -> &[u8] {
+    let mut len = text.len().min(u16::MAX.into());
+    while !text.is_char_boundary(len) {
+        len -= 1;
     }
+
+    &text.as_bytes()[..len]
+}
+
+fn write_text<W: Write + ?Sized>(writer: &mut W, text: &str) -> io::Result<()> {
+    let bytes = text_bytes(text);
+    writer.write_all(&(bytes.len() as u16).to_bytes())?;
+    writer.write_all(bytes)
 }
 
 impl FromBytes for RenderMessage {
+    /// # Panics
+    ///
+    /// If the bytes aren't a valid render message, use `try_from_bytes` to handle this instead
+    #[inline]
     fn from_bytes(bytes: &[u8]) -> Self {
-        let mut reader = ByteReader::new(bytes);
-        reader.read::<u32>();
+        Self::try_from_bytes(bytes).unwrap_or_else(|e| panic!("{e}"))
+    }
+}
 
-        match reader.read::<u8>() {
-            0 => Self::AddRender(
-                reader.read(),
-                (0..reader.read::<u16>()).map(|_| Render::from_reader(&mut reader)).collect(),
-            ),
-            1 => Self::RemoveRender(reader.read()),
-            _ => unreachable!(),
+impl TryFromBytes for RenderMessage {
+    fn try_from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = TryByteReader::new(bytes);
+
+        let num_bytes = reader.read::<u32>()? as usize;
+        if num_bytes != bytes.len() && !Self::is_legacy_remove_render(bytes, num_bytes) {
+            return Err(DecodeError::new(
+                0,
+                DecodeReason::LengthMismatch {
+                    expected: num_bytes,
+                    found: bytes.len(),
+                },
+            ));
         }
+
+        let offset = reader.idx;
+        let message = match reader.read::<u8>()? {
+            0 => {
+                let id = reader.read()?;
+                let num_renders = reader.read::<u16>()?;
+                let renders = (0..num_renders)
+                    .map(|_| Render::try_from_reader(&mut reader))
+                    .collect::<Result<_, _>>()?;
+
+                Self::AddRender(id, renders)
+            }
+            1 => Self::RemoveRender(reader.read()?),
+            tag => {
+                return Err(DecodeError::new(
+                    offset,
+                    DecodeReason::InvalidValue {
+                        name: "RenderMessage",
+                        value: tag,
+                    },
+                ))
+            }
+        };
+
+        reader.finish()?;
+        Ok(message)
     }
 }
 
 impl RenderMessage {
     pub const MIN_NUM_BYTES: usize = u32::NUM_BYTES;
+    /// The length older encoders wrote for `RemoveRender`, which left out the tag
+    const LEGACY_REMOVE_RENDER_NUM_BYTES: usize = Self::MIN_NUM_BYTES + i32::NUM_BYTES;
+
+    /// If the bytes are a `RemoveRender` from an older encoder, which is one byte longer than its length says
+    fn is_legacy_remove_render(bytes: &[u8], num_bytes: usize) -> bool {
+        num_bytes == Self::LEGACY_REMOVE_RENDER_NUM_BYTES && bytes.len() == num_bytes + 1 && bytes[Self::MIN_NUM_BYTES] == 1
+    }
 
-    fn count_bytes(&self) -> usize {
+    fn count_num_bytes(&self) -> usize {
         match self {
             Self::AddRender(_, renders) => {
                 Self::MIN_NUM_BYTES
-                    + i32::NUM_BYTES
                     + 1
+                    + i32::NUM_BYTES
                     + u16::NUM_BYTES
-                    + renders.iter().map(Render::count_bytes).sum::<usize>()
+                    + renders.iter().map(Render::count_num_bytes).sum::<usize>()
             }
-            Self::RemoveRender(_) => Self::MIN_NUM_BYTES + i32::NUM_BYTES,
+            Self::RemoveRender(_) => Self::MIN_NUM_BYTES + 1 + i32::NUM_BYTES,
         }
     }
 
     #[inline]
     #[must_use]
+    /// The length of the message, as written by the encoder
+    ///
+    /// `RemoveRender` messages from older encoders are one byte longer than this
     pub fn get_num_bytes(bytes: &[u8]) -> usize {
         u32::from_bytes(&bytes[..u32::NUM_BYTES]) as usize
     }
 }
 
 impl ToBytes for RenderMessage {
-    fn to_bytes(&self) -> Vec<u8> {
-        let num_bytes = self.count_bytes();
-        let mut bytes = Vec::with_capacity(num_bytes);
-        bytes.extend_from_slice(&(num_bytes as u32).to_bytes());
+    #[inline]
+    fn num_bytes(&self) -> usize {
+        self.count_num_bytes()
+    }
+
+    fn write_into<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&(self.num_bytes() as u32).to_bytes())?;
 
         match self {
             Self::AddRender(id, renders) => {
-                bytes.push(0);
-                bytes.extend_from_slice(&id.to_bytes());
-                bytes.extend_from_slice(&(renders.len() as u16).to_bytes());
-                bytes.extend(renders.iter().flat_map(ToBytes::to_bytes));
+                writer.write_all(&[0])?;
+                writer.write_all(&id.to_bytes())?;
+                writer.write_all(&(renders.len() as u16).to_bytes())?;
+
+                for render in renders {
+                    render.write_into(writer)?;
+                }
+
+                Ok(())
             }
             Self::RemoveRender(id) => {
-                bytes.push(1);
-                bytes.extend_from_slice(&id.to_bytes());
+                writer.write_all(&[1])?;
+                writer.write_all(&id.to_bytes())
             }
         }
+    }
+}
+
+impl FromReader for RenderMessage {
+    fn read_from_with_limit<R: Read + ?Sized>(reader: &mut R, buf: &mut Vec<u8>, max_num_bytes: usize) -> io::Result<Self> {
+        buf.clear();
+        buf.resize(Self::MIN_NUM_BYTES, 0);
+        reader.read_exact(buf)?;
+
+        let num_bytes = Self::get_num_bytes(buf);
+        if num_bytes < Self::MIN_NUM_BYTES {
+            return Err(DecodeError::new(
+                0,
+                DecodeReason::LengthMismatch {
+                    expected: num_bytes,
+                    found: Self::MIN_NUM_BYTES,
+                },
+            )
+            .into());
+        }
 
-        debug_assert_eq!(bytes.len(), num_bytes);
+        check_max_len(num_bytes, max_num_bytes)?;
+        buf.resize(num_bytes, 0);
+        reader.read_exact(&mut buf[Self::MIN_NUM_BYTES..])?;
 
-        bytes
+        if num_bytes == Self::LEGACY_REMOVE_RENDER_NUM_BYTES && buf[Self::MIN_NUM_BYTES] == 1 {
+            // the last byte of the id isn't counted in the length
+            buf.push(0);
+            reader.read_exact(&mut buf[num_bytes..])?;
+        }
+
+        Ok(Self::try_from_bytes(buf)?)
+    }
+}
+
+/// The optional parts of an encoded `GameState`
+///
+/// Each section is written with its length, so decoders can skip sections they don't know about.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Sections(u16);
+
+impl Sections {
+    pub const NONE: Self = Self(0);
+    pub const BALL: Self = Self(1);
+    pub const CARS: Self = Self(1 << 1);
+    /// The last controls of every car, only written along with `CARS`
+    pub const CONTROLS: Self = Self(1 << 2);
+    pub const PADS: Self = Self(1 << 3);
+    pub const TILES: Self = Self(1 << 4);
+    pub const MUTATOR_CONFIG: Self = Self(1 << 5);
+    pub const ARENA_CONFIG: Self = Self(1 << 6);
+    /// Every section this version of the format knows about
+    pub const ALL: Self = Self((1 << 7) - 1);
+
+    #[inline]
+    #[must_use]
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// Every single section that's set, in the order they're written in
+    pub fn iter(self) -> impl Iterator<Item = Self> {
+        (0..u16::BITS)
+            .map(|bit| Self(1 << bit))
+            .filter(move |&section| self.contains(section))
+    }
+}
+
+impl BitOr for Sections {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
     }
 }
 
+/// Where the fields are in a game state header
+struct HeaderLayout {
+    tick_count: usize,
+    tick_rate: usize,
+    game_mode: usize,
+    num_pads: usize,
+    num_cars: usize,
+}
+
+impl HeaderLayout {
+    /// Before the format had a magic number and version
+    const LEGACY: Self = Self {
+        tick_count: 0,
+        tick_rate: 8,
+        game_mode: 12,
+        num_pads: 13,
+        num_cars: 17,
+    };
+    const CURRENT: Self = Self {
+        tick_count: 12,
+        tick_rate: 20,
+        game_mode: 24,
+        num_pads: 26,
+        num_cars: 30,
+    };
+}
+
+/// The car's state is written without its last controls, those are in the `CONTROLS` section
+const CAR_STATE_NUM_BYTES: usize = CarState::NUM_BYTES - CarControls::NUM_BYTES;
+const CAR_RECORD_NUM_BYTES: usize = u32::NUM_BYTES + Team::NUM_BYTES + CAR_STATE_NUM_BYTES + CarConfig::NUM_BYTES;
+
+fn read_car(bytes: &[u8]) -> Result<CarInfo, DecodeError> {
+    let mut reader = TryByteReader::new(bytes);
+
+    let id = reader.read()?;
+    let team = reader.read()?;
+
+    let state_offset = reader.idx;
+    let mut state_bytes = [0; CarState::NUM_BYTES];
+    state_bytes[..CAR_STATE_NUM_BYTES].copy_from_slice(reader.take(CAR_STATE_NUM_BYTES)?);
+    state_bytes[CAR_STATE_NUM_BYTES..].copy_from_slice(&CarControls::default().to_bytes());
+    let state = CarState::try_from_bytes(&state_bytes).map_err(|e| e.offset_by(state_offset))?;
+
+    let config = reader.read()?;
+    reader.finish()?;
+
+    Ok(CarInfo { id, team, state, config })
+}
+
+fn write_car<W: Write + ?Sized>(writer: &mut W, car: &CarInfo) -> io::Result<()> {
+    writer.write_all(&car.id.to_bytes())?;
+    writer.write_all(&car.team.to_bytes())?;
+    writer.write_all(&car.state.to_bytes()[..CAR_STATE_NUM_BYTES])?;
+    writer.write_all(&car.config.to_bytes())
+}
+
+fn write_records<W: Write + ?Sized, T>(
+    writer: &mut W,
+    record_num_bytes: usize,
+    items: &[T],
+    write: impl Fn(&mut W, &T) -> io::Result<()>,
+) -> io::Result<()> {
+    writer.write_all(&(record_num_bytes as u32).to_bytes())?;
+    for item in items {
+        write(writer, item)?;
+    }
+
+    Ok(())
+}
+
 impl FromBytes for GameState {
+    /// # Panics
+    ///
+    /// If the bytes aren't a valid game state, use `try_from_bytes` to handle this instead
     #[inline]
     fn from_bytes(bytes: &[u8]) -> Self {
-        let mut reader = ByteReader::new(bytes);
+        Self::try_from_bytes(bytes).unwrap_or_else(|e| panic!("{e}"))
+    }
+}
 
-        let tick_count = reader.read();
-        let tick_rate = reader.read();
-        let game_mode = reader.read();
-        let num_pads = reader.read();
-        let num_cars = reader.read();
+impl TryFromBytes for GameState {
+    /// Reads a game state written by any version of the format up to `GameState::VERSION`
+    fn try_from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        if Self::is_legacy(bytes) {
+            return Self::try_from_legacy_bytes(bytes);
+        }
 
-        Self {
-            tick_count,
-            tick_rate,
-            game_mode,
-            ball: reader.read(),
-            pads: (0..num_pads).map(|_| reader.read()).collect(),
-            cars: (0..num_cars).map(|_| reader.read()).collect(),
-            tiles: [
-                (0..consts::dropshot::NUM_TILES_PER_TEAM).map(|_| reader.read()).collect(),
-                (0..consts::dropshot::NUM_TILES_PER_TEAM).map(|_| reader.read()).collect(),
-            ],
+        // the tick count of the legacy layout can spell out the magic number by chance
+        if !Self::has_current_framing(bytes) {
+            return Self::try_from_legacy_bytes(bytes).or_else(|_| Self::try_from_current_bytes(bytes));
         }
+
+        Self::try_from_current_bytes(bytes)
     }
 }
 
 impl GameState {
-    pub const MIN_NUM_BYTES: usize = u64::NUM_BYTES + f32::NUM_BYTES + 1 + u32::NUM_BYTES * 2;
+    /// The first bytes of every encoded game state
+    pub const MAGIC: [u8; 4] = *b"RSGS";
+    /// The version of the format written by `to_bytes`
+    ///
+    /// Adding a new section or adding fields to the end of a record doesn't change the version,
+    /// since older decoders skip what they don't know about.
+    /// Any other change to the layout increases the version, and decoders keep reading all older versions.
+    pub const VERSION: u16 = 1;
+    /// The size of the header, this is enough to call `get_num_bytes`
+    pub const MIN_NUM_BYTES: usize = Self::MAGIC.len()
+        + u16::NUM_BYTES
+        + u32::NUM_BYTES
+        + u16::NUM_BYTES
+        + u64::NUM_BYTES
+        + f32::NUM_BYTES
+        + 1
+        + 1
+        + u32::NUM_BYTES * 2;
+    const LEGACY_MIN_NUM_BYTES: usize = u64::NUM_BYTES + f32::NUM_BYTES + 1 + u32::NUM_BYTES * 2;
+
+    /// Where the length is in the current layout
+    const NUM_BYTES_OFFSET: usize = Self::MAGIC.len() + u16::NUM_BYTES;
+
+    /// If the header is from before the format had a magic number and version
+    ///
+    /// Legacy states start with the tick count, so the magic number alone isn't enough.
+    /// The version after it also has to be set, and the length can't be shorter than the header.
+    /// Headers that are too short to tell are treated as the current layout.
+    #[inline]
+    fn is_legacy(bytes: &[u8]) -> bool {
+        if !bytes.starts_with(&Self::MAGIC) {
+            return true;
+        }
+
+        let Some(header) = bytes.get(..Self::NUM_BYTES_OFFSET + u32::NUM_BYTES) else {
+            return false;
+        };
+
+        let version = u16::from_bytes(&header[Self::MAGIC.len()..Self::NUM_BYTES_OFFSET]);
+        let num_bytes = u32::from_bytes(&header[Self::NUM_BYTES_OFFSET..]) as usize;
+        version == 0 || num_bytes < Self::MIN_NUM_BYTES
+    }
+
+    /// If the bytes are a complete state in the current layout,
+    /// with a version that can be read and a length that matches the bytes
+    fn has_current_framing(bytes: &[u8]) -> bool {
+        bytes.len() >= Self::MIN_NUM_BYTES
+            && !Self::is_legacy(bytes)
+            && Self::read_version(bytes) <= Self::VERSION
+            && Self::get_num_bytes(bytes) == bytes.len()
+    }
 
     #[inline]
-    fn count_bytes(&self) -> usize {
+    fn header_layout(bytes: &[u8]) -> HeaderLayout {
+        if Self::is_legacy(bytes) {
+            HeaderLayout::LEGACY
+        } else {
+            HeaderLayout::CURRENT
+        }
+    }
+
+    fn read_section(
+        &mut self,
+        section: Sections,
+        payload: &[u8],
+        num_pads: usize,
+        num_cars: usize,
+    ) -> Result<(), DecodeError> {
+        let mut reader = TryByteReader::new(payload);
+
+        // anything after the known fields of a section was added by a newer encoder and is ignored
+        match section {
+            Sections::BALL => self.ball = reader.read()?,
+            Sections::CARS => self.cars = reader.read_records(num_cars, CAR_RECORD_NUM_BYTES, read_car)?,
+            Sections::CONTROLS => {
+                let controls = reader.read_records(num_cars, CarControls::NUM_BYTES, CarControls::try_from_bytes)?;
+                for (car, controls) in self.cars.iter_mut().zip(controls) {
+                    car.state.last_controls = controls;
+                }
+            }
+            Sections::PADS => self.pads = reader.read_records(num_pads, BoostPad::NUM_BYTES, BoostPad::try_from_bytes)?,
+            Sections::TILES => {
+                for tiles in &mut self.tiles {
+                    let num_tiles = reader.read::<u32>()? as usize;
+                    *tiles = reader.read_records(num_tiles, DropshotTile::NUM_BYTES, DropshotTile::try_from_bytes)?;
+                }
+            }
+            Sections::MUTATOR_CONFIG => self.mutator_config = Some(reader.read()?),
+            Sections::ARENA_CONFIG => self.arena_config = Some(reader.read()?),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Reads the current layout
+    fn try_from_current_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = TryByteReader::new(bytes);
+        reader.take(Self::MAGIC.len())?;
+
+        let version = reader.read::<u16>()?;
+        if version > Self::VERSION {
+            return Err(DecodeError::new(Self::MAGIC.len(), DecodeReason::UnsupportedVersion(version)));
+        }
+
+        let num_bytes = reader.read::<u32>()? as usize;
+        if num_bytes != bytes.len() {
+            return Err(DecodeError::new(
+                Self::MAGIC.len() + u16::NUM_BYTES,
+                DecodeReason::LengthMismatch {
+                    expected: num_bytes,
+                    found: bytes.len(),
+                },
+            ));
+        }
+
+        let sections = Sections(reader.read()?);
+
+        let mut state = Self {
+            tick_count: reader.read()?,
+            tick_rate: reader.read()?,
+            game_mode: reader.read()?,
+            is_ball_scored: reader.read()?,
+            ..Default::default()
+        };
+
+        let num_pads = reader.read::<u32>()? as usize;
+        let num_cars = reader.read::<u32>()? as usize;
+
+        // sections are always in the order of their bits
+        for section in sections.iter() {
+            let len = reader.read::<u32>()? as usize;
+            let start = reader.idx;
+            let payload = reader.take(len)?;
+
+            state
+                .read_section(section, payload, num_pads, num_cars)
+                .map_err(|e| e.offset_by(start))?;
+        }
+
+        reader.finish()?;
+        Ok(state)
+    }
+
+    /// Reads the layout from before the format had a magic number and version
+    ///
+    /// That layout has no way to carry the ball scored state or the configs,
+    /// so they're always left at `false` and `None`.
+    fn try_from_legacy_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = TryByteReader::new(bytes);
+
+        let tick_count = reader.read()?;
+        let tick_rate = reader.read()?;
+        let game_mode = reader.read()?;
+        let num_pads = reader.read::<u32>()? as usize;
+        let num_cars = reader.read::<u32>()? as usize;
+
+        let ball = reader.read()?;
+        let pads = reader.read_vec(num_pads)?;
+        let cars = reader.read_vec(num_cars)?;
+        let tiles = if game_mode == GameMode::Dropshot {
+            let num_tiles = consts::dropshot::NUM_TILES_PER_TEAM as usize;
+            [reader.read_vec(num_tiles)?, reader.read_vec(num_tiles)?]
+        } else {
+            Default::default()
+        };
+
+        reader.finish()?;
+
+        Ok(Self {
+            tick_rate,
+            tick_count,
+            game_mode,
+            cars,
+            ball,
+            pads,
+            tiles,
+            is_ball_scored: false,
+            mutator_config: None,
+            arena_config: None,
+        })
+    }
+
+    /// The requested sections that there's something to write for
+    fn sections_to_write(&self, requested: Sections) -> Sections {
+        let mut sections = Sections::NONE;
+
+        for (section, has_data) in [
+            (Sections::BALL, true),
+            (Sections::CARS, true),
+            (Sections::CONTROLS, requested.contains(Sections::CARS)),
+            (Sections::PADS, !self.pads.is_empty()),
+            (Sections::TILES, self.tiles.iter().any(|tiles| !tiles.is_empty())),
+            (Sections::MUTATOR_CONFIG, self.mutator_config.is_some()),
+            (Sections::ARENA_CONFIG, self.arena_config.is_some()),
+        ] {
+            if has_data && requested.contains(section) {
+                sections = sections | section;
+            }
+        }
+
+        sections
+    }
+
+    /// The length of a single section, without the length field in front of it
+    fn section_num_bytes(&self, section: Sections) -> usize {
+        match section {
+            Sections::BALL => BallState::NUM_BYTES,
+            Sections::CARS => u32::NUM_BYTES + self.cars.len() * CAR_RECORD_NUM_BYTES,
+            Sections::CONTROLS => u32::NUM_BYTES + self.cars.len() * CarControls::NUM_BYTES,
+            Sections::PADS => u32::NUM_BYTES + self.pads.len() * BoostPad::NUM_BYTES,
+            Sections::TILES => self
+                .tiles
+                .iter()
+                .map(|tiles| u32::NUM_BYTES * 2 + tiles.len() * DropshotTile::NUM_BYTES)
+                .sum(),
+            Sections::MUTATOR_CONFIG => MutatorConfig::NUM_BYTES,
+            Sections::ARENA_CONFIG => ArenaConfig::NUM_BYTES,
+            _ => 0,
+        }
+    }
+
+    fn count_num_bytes(&self, sections: Sections) -> usize {
         Self::MIN_NUM_BYTES
-            + BallState::NUM_BYTES
-            + if self.game_mode == GameMode::Dropshot {
-                0
-            } else {
-                self.pads.len() * BoostPad::NUM_BYTES
+            + sections
+                .iter()
+                .map(|section| u32::NUM_BYTES + self.section_num_bytes(section))
+                .sum::<usize>()
+    }
+
+    /// Writes the length of the section, followed by the section itself
+    fn write_section<W: Write + ?Sized>(&self, writer: &mut W, section: Sections) -> io::Result<()> {
+        writer.write_all(&(self.section_num_bytes(section) as u32).to_bytes())?;
+
+        match section {
+            Sections::BALL => writer.write_all(&self.ball.to_bytes()),
+            Sections::CARS => write_records(writer, CAR_RECORD_NUM_BYTES, &self.cars, write_car),
+            Sections::CONTROLS => write_records(writer, CarControls::NUM_BYTES, &self.cars, |writer, car| {
+                writer.write_all(&car.state.last_controls.to_bytes())
+            }),
+            Sections::PADS => write_records(writer, BoostPad::NUM_BYTES, &self.pads, |writer, pad| {
+                writer.write_all(&pad.to_bytes())
+            }),
+            Sections::TILES => {
+                for tiles in &self.tiles {
+                    writer.write_all(&(tiles.len() as u32).to_bytes())?;
+                    write_records(writer, DropshotTile::NUM_BYTES, tiles, |writer, tile| {
+                        writer.write_all(&tile.to_bytes())
+                    })?;
+                }
+
+                Ok(())
             }
+            Sections::MUTATOR_CONFIG => self
+                .mutator_config
+                .map_or(Ok(()), |config| writer.write_all(&config.to_bytes())),
+            Sections::ARENA_CONFIG => self
+                .arena_config
+                .map_or(Ok(()), |config| writer.write_all(&config.to_bytes())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Same as `to_bytes`, but only writes the given sections
+    ///
+    /// Sections without any data, like `PADS` in dropshot, are always left out.
+    #[must_use]
+    pub fn to_bytes_with(&self, sections: Sections) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.num_bytes_with(sections));
+        self.write_into_with(&mut bytes, sections)
+            .expect("writing to a Vec can't fail");
+
+        bytes
+    }
+
+    /// The length of `to_bytes_with` for the same sections
+    #[must_use]
+    pub fn num_bytes_with(&self, sections: Sections) -> usize {
+        self.count_num_bytes(self.sections_to_write(sections))
+    }
+
+    /// Same as `write_into`, but only writes the given sections
+    pub fn write_into_with<W: Write + ?Sized>(&self, writer: &mut W, sections: Sections) -> io::Result<()> {
+        let sections = self.sections_to_write(sections);
+        let num_pads = if sections.contains(Sections::PADS) {
+            self.pads.len()
+        } else {
+            0
+        };
+        let num_cars = if sections.contains(Sections::CARS) {
+            self.cars.len()
+        } else {
+            0
+        };
+
+        writer.write_all(&Self::MAGIC)?;
+        writer.write_all(&Self::VERSION.to_bytes())?;
+        writer.write_all(&(self.count_num_bytes(sections) as u32).to_bytes())?;
+        writer.write_all(&sections.bits().to_bytes())?;
+        writer.write_all(&self.tick_count.to_bytes())?;
+        writer.write_all(&self.tick_rate.to_bytes())?;
+        writer.write_all(&self.game_mode.to_bytes())?;
+        writer.write_all(&self.is_ball_scored.to_bytes())?;
+        writer.write_all(&(num_pads as u32).to_bytes())?;
+        writer.write_all(&(num_cars as u32).to_bytes())?;
+
+        for section in sections.iter() {
+            self.write_section(writer, section)?;
+        }
+
+        Ok(())
+    }
+
+    /// Same as `to_bytes`, but in the layout from before the format had a magic number and version
+    ///
+    /// This is the layout rlviser reads. It has no room for the ball scored state or the configs.
+    #[must_use]
+    pub fn to_bytes_legacy(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.num_bytes_legacy());
+        self.write_into_legacy(&mut bytes).expect("writing to a Vec can't fail");
+
+        bytes
+    }
+
+    /// The length of `to_bytes_legacy`
+    #[must_use]
+    pub fn num_bytes_legacy(&self) -> usize {
+        Self::LEGACY_MIN_NUM_BYTES
+            + BallState::NUM_BYTES
+            + self.pads.len() * BoostPad::NUM_BYTES
             + self.cars.len() * CarInfo::NUM_BYTES
-            + if self.game_mode == GameMode::Dropshot {
+            + self.tiles.iter().map(Vec::len).sum::<usize>() * DropshotTile::NUM_BYTES
+    }
+
+    /// Same as `write_into`, but in the layout of `to_bytes_legacy`
+    pub fn write_into_legacy<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.tick_count.to_bytes())?;
+        writer.write_all(&self.tick_rate.to_bytes())?;
+        writer.write_all(&self.game_mode.to_bytes())?;
+        writer.write_all(&(self.pads.len() as u32).to_bytes())?;
+        writer.write_all(&(self.cars.len() as u32).to_bytes())?;
+        writer.write_all(&self.ball.to_bytes())?;
+
+        for pad in &self.pads {
+            writer.write_all(&pad.to_bytes())?;
+        }
+
+        for car in &self.cars {
+            writer.write_all(&ToBytesExact::<{ CarInfo::NUM_BYTES }>::to_bytes(car))?;
+        }
+
+        for tile in self.tiles.iter().flatten() {
+            writer.write_all(&tile.to_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn get_num_bytes(bytes: &[u8]) -> usize {
+        if !Self::is_legacy(bytes) {
+            let offset = Self::NUM_BYTES_OFFSET;
+            return u32::from_bytes(&bytes[offset..offset + u32::NUM_BYTES]) as usize;
+        }
+
+        Self::LEGACY_MIN_NUM_BYTES
+            + BallState::NUM_BYTES
+            + Self::read_num_pads(bytes) * BoostPad::NUM_BYTES
+            + Self::read_num_cars(bytes) * CarInfo::NUM_BYTES
+            + if Self::read_game_mode(bytes) == GameMode::Dropshot {
                 DropshotTile::NUM_BYTES * consts::dropshot::NUM_TILES_PER_TEAM as usize * 2
             } else {
                 0
             }
     }
 
+    /// Same as `get_num_bytes`, but checks that the header is complete and valid
+    ///
+    /// # Errors
+    ///
+    /// Returns a `DecodeError` if the header is incomplete,
+    /// the version isn't supported, or the game mode is invalid
+    pub fn try_get_num_bytes(bytes: &[u8]) -> Result<usize, DecodeError> {
+        let min_num_bytes = if Self::is_legacy(bytes) {
+            Self::LEGACY_MIN_NUM_BYTES
+        } else {
+            Self::MIN_NUM_BYTES
+        };
+
+        if bytes.len() < min_num_bytes {
+            return Err(DecodeError::new(
+                0,
+                DecodeReason::UnexpectedEnd {
+                    needed: min_num_bytes,
+                    remaining: bytes.len(),
+                },
+            ));
+        }
+
+        let version = Self::read_version(bytes);
+        if version > Self::VERSION {
+            return Err(DecodeError::new(Self::MAGIC.len(), DecodeReason::UnsupportedVersion(version)));
+        }
+
+        let game_mode_offset = Self::header_layout(bytes).game_mode;
+        GameMode::try_from_bytes(&bytes[game_mode_offset..=game_mode_offset]).map_err(|e| e.offset_by(game_mode_offset))?;
+
+        Ok(Self::get_num_bytes(bytes))
+    }
+
     #[inline]
     #[must_use]
-    pub fn get_num_bytes(bytes: &[u8]) -> usize {
-        Self::MIN_NUM_BYTES
-            + BallState::NUM_BYTES
-            + Self::read_num_pads(bytes) * BoostPad::NUM_BYTES
-            + Self::read_num_cars(bytes) * CarInfo::NUM_BYTES
-            + DropshotTile::NUM_BYTES * consts::dropshot::NUM_TILES_PER_TEAM as usize * 2
+    /// The version of the format the bytes were written with, 0 if they're from before the format was versioned
+    pub fn read_version(bytes: &[u8]) -> u16 {
+        if Self::is_legacy(bytes) {
+            0
+        } else {
+            u16::from_bytes(&bytes[Self::MAGIC.len()..Self::MAGIC.len() + u16::NUM_BYTES])
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// The sections in the bytes, including ones this version of the format doesn't know about
+    pub fn read_sections(bytes: &[u8]) -> Sections {
+        if Self::is_legacy(bytes) {
+            let sections = Sections::BALL | Sections::CARS | Sections::CONTROLS;
+            return if Self::read_game_mode(bytes) == GameMode::Dropshot {
+                sections | Sections::TILES
+            } else {
+                sections | Sections::PADS
+            };
+        }
+
+        let offset = Self::MAGIC.len() + u16::NUM_BYTES + u32::NUM_BYTES;
+        Sections(u16::from_bytes(&bytes[offset..offset + u16::NUM_BYTES]))
     }
 
     #[inline]
     #[must_use]
     pub fn read_tick_count(bytes: &[u8]) -> u64 {
-        u64::from_bytes(&bytes[..u64::NUM_BYTES])
+        let offset = Self::header_layout(bytes).tick_count;
+        u64::from_bytes(&bytes[offset..offset + u64::NUM_BYTES])
     }
 
     #[inline]
     #[must_use]
     pub fn read_tick_rate(bytes: &[u8]) -> f32 {
-        f32::from_bytes(&bytes[u64::NUM_BYTES..u64::NUM_BYTES + f32::NUM_BYTES])
+        let offset = Self::header_layout(bytes).tick_rate;
+        f32::from_bytes(&bytes[offset..offset + f32::NUM_BYTES])
     }
 
     #[inline]
     #[must_use]
     pub fn read_game_mode(bytes: &[u8]) -> GameMode {
-        GameMode::from_bytes(&bytes[(u64::NUM_BYTES + f32::NUM_BYTES)..=(u64::NUM_BYTES + f32::NUM_BYTES)])
+        let offset = Self::header_layout(bytes).game_mode;
+        GameMode::from_bytes(&bytes[offset..=offset])
     }
 
     #[inline]
     #[must_use]
     pub fn read_num_pads(bytes: &[u8]) -> usize {
-        u32::from_bytes(&bytes[u64::NUM_BYTES + f32::NUM_BYTES + 1..u64::NUM_BYTES + f32::NUM_BYTES + 1 + u32::NUM_BYTES])
-            as usize
+        let offset = Self::header_layout(bytes).num_pads;
+        u32::from_bytes(&bytes[offset..offset + u32::NUM_BYTES]) as usize
     }
 
     #[inline]
     #[must_use]
     pub fn read_num_cars(bytes: &[u8]) -> usize {
-        u32::from_bytes(&bytes[u64::NUM_BYTES + f32::NUM_BYTES + 1 + u32::NUM_BYTES..Self::MIN_NUM_BYTES]) as usize
+        let offset = Self::header_layout(bytes).num_cars;
+        u32::from_bytes(&bytes[offset..offset + u32::NUM_BYTES]) as usize
+    }
+
+    #[inline]
+    #[must_use]
+    /// Always `false` for bytes from before the format was versioned
+    pub fn read_is_ball_scored(bytes: &[u8]) -> bool {
+        if Self::is_legacy(bytes) {
+            false
+        } else {
+            let offset = HeaderLayout::CURRENT.game_mode + 1;
+            bool::from_bytes(&bytes[offset..=offset])
+        }
     }
 }
 
+/// Encoding to bytes
+///
+/// `encode_into` and `to_bytes` are based on `num_bytes` and `write_into`.
 pub trait ToBytes {
-    fn to_bytes(&self) -> Vec<u8>;
+    /// The exact number of bytes `write_into` writes
+    fn num_bytes(&self) -> usize;
+
+    fn write_into<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()>;
+
+    /// Appends the bytes to the end of an existing buffer, so it can be reused
+    fn encode_into(&self, bytes: &mut Vec<u8>) {
+        let num_bytes = self.num_bytes();
+        let start = bytes.len();
+        bytes.reserve(num_bytes);
+
+        self.write_into(bytes).expect("writing to a Vec can't fail");
+        debug_assert_eq!(bytes.len() - start, num_bytes);
+    }
+
+    #[inline]
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.encode_into(&mut bytes);
+        bytes
+    }
 }
 
 impl ToBytes for GameState {
-    fn to_bytes(&self) -> Vec<u8> {
-        let num_bytes = self.count_bytes();
-        let mut bytes = Vec::with_capacity(num_bytes);
-
-        bytes.extend(self.tick_count.to_bytes());
-        bytes.extend(self.tick_rate.to_bytes());
-        bytes.extend(self.game_mode.to_bytes());
-        bytes.extend(&(self.pads.len() as u32).to_bytes());
-        bytes.extend(&(self.cars.len() as u32).to_bytes());
-        bytes.extend(self.ball.to_bytes());
-        bytes.extend(self.pads.iter().flat_map(ToBytesExact::<{ BoostPad::NUM_BYTES }>::to_bytes));
-        bytes.extend(self.cars.iter().flat_map(ToBytesExact::<{ CarInfo::NUM_BYTES }>::to_bytes));
-        bytes.extend(
-            self.tiles[0]
-                .iter()
-                .flat_map(ToBytesExact::<{ DropshotTile::NUM_BYTES }>::to_bytes),
-        );
-        bytes.extend(
-            self.tiles[1]
-                .iter()
-                .flat_map(ToBytesExact::<{ DropshotTile::NUM_BYTES }>::to_bytes),
-        );
+    #[inline]
+    fn num_bytes(&self) -> usize {
+        self.num_bytes_with(Sections::ALL)
+    }
 
-        debug_assert_eq!(num_bytes, bytes.len());
-        bytes
+    #[inline]
+    fn write_into<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_into_with(writer, Sections::ALL)
+    }
+}
+
+/// Decoding straight from a stream, like a file or a socket
+///
+/// Only as many bytes as the value takes up are read,
+/// so multiple values can be read from the same stream one after the other.
+pub trait FromReader: TryFromBytes {
+    /// The longest value `read_from` accepts, 16 MiB
+    const MAX_NUM_BYTES: usize = 1 << 24;
+
+    /// Reads the next value, using `buf` as scratch space so it can be reused between calls
+    ///
+    /// Malformed bytes, or a value longer than `MAX_NUM_BYTES`, are returned as an error of the kind `InvalidData`.
+    #[inline]
+    fn read_from<R: Read + ?Sized>(reader: &mut R, buf: &mut Vec<u8>) -> io::Result<Self> {
+        Self::read_from_with_limit(reader, buf, Self::MAX_NUM_BYTES)
+    }
+
+    /// Same as `read_from`, but rejects values longer than `max_num_bytes` before reading the rest of them
+    fn read_from_with_limit<R: Read + ?Sized>(reader: &mut R, buf: &mut Vec<u8>, max_num_bytes: usize) -> io::Result<Self>;
+}
+
+impl FromReader for GameState {
+    fn read_from_with_limit<R: Read + ?Sized>(reader: &mut R, buf: &mut Vec<u8>, max_num_bytes: usize) -> io::Result<Self> {
+        buf.clear();
+        buf.resize(Self::MIN_NUM_BYTES, 0);
+        reader.read_exact(buf)?;
+
+        let num_bytes = Self::try_get_num_bytes(buf)?;
+        if num_bytes < Self::MIN_NUM_BYTES {
+            return Err(DecodeError::new(
+                0,
+                DecodeReason::LengthMismatch {
+                    expected: num_bytes,
+                    found: Self::MIN_NUM_BYTES,
+                },
+            )
+            .into());
+        }
+
+        check_max_len(num_bytes, max_num_bytes)?;
+        buf.resize(num_bytes, 0);
+        reader.read_exact(&mut buf[Self::MIN_NUM_BYTES..])?;
+
+        Ok(Self::try_from_bytes(buf)?)
     }
 }
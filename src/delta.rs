@@ -0,0 +1,567 @@
+use crate::{
+    bytes::{DecodeError, DecodeReason, FromBytes, FromBytesExact, ToBytes, ToBytesExact, TryByteReader, TryFromBytes},
+    math::{RotMat, Vec3},
+    sim::{ArenaConfig, BallState, BoostPadState, CarConfig, CarState, GameMode, MutatorConfig, Team},
+    CarInfo, GameState, TileState,
+};
+use core::fmt;
+use std::error::Error;
+
+/// Every frame starts with its length and kind
+const FRAME_HEADER_NUM_BYTES: usize = u32::NUM_BYTES + 1;
+
+/// The bytes of `BallState` and `CarState` both start with the ticks since their last update,
+/// followed by the position, rotation, velocity and angular velocity
+const MOTION_START: usize = u64::NUM_BYTES;
+const MOTION_END: usize = MOTION_START + Vec3::NUM_BYTES * 3 + RotMat::NUM_BYTES;
+const NUM_MOTION_VALUES: usize = (MOTION_END - MOTION_START) / f32::NUM_BYTES;
+const QUANTIZED_MOTION_NUM_BYTES: usize = NUM_MOTION_VALUES * i16::NUM_BYTES;
+
+const POS_STEP: f32 = 0.5;
+const ROT_STEP: f32 = 1. / i16::MAX as f32;
+const VEL_STEP: f32 = 0.25;
+const ANG_VEL_STEP: f32 = 1. / 2048.;
+
+mod kind {
+    pub const KEYFRAME: u8 = 0;
+    pub const DELTA: u8 = 1;
+}
+
+mod flags {
+    pub const QUANTIZED: u8 = 1;
+    /// The ball changed and its state follows
+    pub const BALL: u8 = 1 << 1;
+    pub const IS_BALL_SCORED: u8 = 1 << 2;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameKind {
+    /// The full game state, which any decoder can start from
+    Keyframe,
+    /// Only what changed since the previous frame
+    Delta,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeltaConfig {
+    /// The most frames that can be sent in a row without a keyframe
+    pub keyframe_interval: u32,
+    /// Sends the position, rotation and velocities of the ball and cars as 16-bit integers in deltas
+    ///
+    /// Positions are rounded to 0.5uu, velocities to 0.25uu/s and angular velocities to 1/2048 rad/s.
+    /// Keyframes are always in full precision, and rounding errors don't add up between deltas.
+    pub quantize: bool,
+}
+
+impl Default for DeltaConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            keyframe_interval: 120,
+            quantize: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeltaError {
+    Decode(DecodeError),
+    /// The delta is based on a different tick than the decoder's state, usually because a frame was lost
+    ///
+    /// Deltas can't be decoded again until the next keyframe.
+    MissingBase {
+        base_tick: u64,
+        current_tick: Option<u64>,
+    },
+    /// The delta has a car that the decoder doesn't know about
+    UnknownCar(u32),
+}
+
+impl Error for DeltaError {}
+impl fmt::Display for DeltaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decode(e) => e.fmt(f),
+            Self::MissingBase {
+                base_tick,
+                current_tick: Some(current_tick),
+            } => write!(
+                f,
+                "Delta is based on tick {base_tick}, but the current state is at tick {current_tick}."
+            ),
+            Self::MissingBase {
+                base_tick,
+                current_tick: None,
+            } => write!(
+                f,
+                "Delta is based on tick {base_tick}, but no keyframe has been received yet."
+            ),
+            Self::UnknownCar(car_id) => write!(f, "Delta has car with ID {car_id}, which doesn't exist."),
+        }
+    }
+}
+
+impl From<DecodeError> for DeltaError {
+    #[inline]
+    fn from(e: DecodeError) -> Self {
+        Self::Decode(e)
+    }
+}
+
+/// The size of the steps that the motion value at the given index gets rounded to
+const fn motion_step(index: usize) -> f32 {
+    match index {
+        0..3 => POS_STEP,
+        3..12 => ROT_STEP,
+        12..15 => VEL_STEP,
+        _ => ANG_VEL_STEP,
+    }
+}
+
+/// Writes the bytes of a `BallState` or `CarState`, quantizing their motion if asked to
+fn write_state(bytes: &mut Vec<u8>, state: &[u8], quantize: bool) {
+    if !quantize {
+        bytes.extend_from_slice(state);
+        return;
+    }
+
+    bytes.extend_from_slice(&state[..MOTION_START]);
+    for (i, value) in state[MOTION_START..MOTION_END].chunks_exact(f32::NUM_BYTES).enumerate() {
+        let value = (f32::from_bytes(value) / motion_step(i)).round();
+        bytes.extend((value.clamp(i16::MIN.into(), i16::MAX.into()) as i16).to_bytes());
+    }
+    bytes.extend_from_slice(&state[MOTION_END..]);
+}
+
+/// Reads a `BallState` or `CarState` written by `write_state`
+fn read_state<T: TryFromBytes + FromBytesExact>(reader: &mut TryByteReader, quantized: bool) -> Result<T, DecodeError> {
+    if !quantized {
+        return reader.read();
+    }
+
+    let start = reader.position();
+    let quantized = reader.take(T::NUM_BYTES - (MOTION_END - MOTION_START) + QUANTIZED_MOTION_NUM_BYTES)?;
+
+    let mut state = Vec::with_capacity(T::NUM_BYTES);
+    state.extend_from_slice(&quantized[..MOTION_START]);
+    for (i, value) in quantized[MOTION_START..MOTION_START + QUANTIZED_MOTION_NUM_BYTES]
+        .chunks_exact(i16::NUM_BYTES)
+        .enumerate()
+    {
+        state.extend((f32::from(i16::from_bytes(value)) * motion_step(i)).to_bytes());
+    }
+    state.extend_from_slice(&quantized[MOTION_START + QUANTIZED_MOTION_NUM_BYTES..]);
+
+    T::try_from_bytes(&state).map_err(|e| DecodeError::new(start, e.reason))
+}
+
+/// Writes a bitset of which items changed, followed by the changed items
+fn write_changed<T: PartialEq + Copy + ToBytesExact<N>, const N: usize>(
+    bytes: &mut Vec<u8>,
+    base: &mut [T],
+    items: impl Iterator<Item = T>,
+) {
+    let bitset_idx = bytes.len();
+    bytes.resize(bitset_idx + base.len().div_ceil(8), 0);
+
+    for (i, (base, item)) in base.iter_mut().zip(items).enumerate() {
+        if *base != item {
+            bytes[bitset_idx + i / 8] |= 1 << (i % 8);
+            bytes.extend(item.to_bytes());
+            *base = item;
+        }
+    }
+}
+
+/// Reads the items written by `write_changed` over the existing ones
+fn read_changed<'a, T: TryFromBytes + FromBytesExact + 'a>(
+    reader: &mut TryByteReader,
+    items: impl ExactSizeIterator<Item = &'a mut T>,
+) -> Result<(), DecodeError> {
+    let bitset = reader.take(items.len().div_ceil(8))?;
+
+    for (i, item) in items.enumerate() {
+        if bitset[i / 8] & 1 << (i % 8) != 0 {
+            *item = reader.read()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A car as the decoder knows it
+struct BaseCar {
+    id: u32,
+    /// The team and config of the car, as written in full frames
+    info: Vec<u8>,
+    /// The state, as written by `write_state`
+    state: Vec<u8>,
+}
+
+impl BaseCar {
+    fn new(car: &CarInfo, quantize: bool) -> Self {
+        let mut state = Vec::new();
+        write_state(&mut state, &car.state.to_bytes(), quantize);
+
+        Self {
+            id: car.id,
+            info: car_info_bytes(car),
+            state,
+        }
+    }
+}
+
+fn car_info_bytes(car: &CarInfo) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(Team::NUM_BYTES + CarConfig::NUM_BYTES);
+    bytes.extend(car.team.to_bytes());
+    bytes.extend(car.config.to_bytes());
+    bytes
+}
+
+/// What the decoder's state is known to be, as of the last frame
+struct Base {
+    tick_count: u64,
+    tick_rate: f32,
+    game_mode: GameMode,
+    mutator_config: Option<[u8; MutatorConfig::NUM_BYTES]>,
+    arena_config: Option<ArenaConfig>,
+    /// The ball, as written by `write_state`
+    ball: Vec<u8>,
+    cars: Vec<BaseCar>,
+    pads: Vec<BoostPadState>,
+    tiles: [Vec<TileState>; 2],
+}
+
+impl Base {
+    fn new(state: &GameState, quantize: bool) -> Self {
+        let mut ball = Vec::new();
+        write_state(&mut ball, &state.ball.to_bytes(), quantize);
+
+        Self {
+            tick_count: state.tick_count,
+            tick_rate: state.tick_rate,
+            game_mode: state.game_mode,
+            mutator_config: state.mutator_config.map(|config| config.to_bytes()),
+            arena_config: state.arena_config,
+            ball,
+            cars: state.cars.iter().map(|car| BaseCar::new(car, quantize)).collect(),
+            pads: state.pads.iter().map(|pad| pad.state).collect(),
+            tiles: state
+                .tiles
+                .each_ref()
+                .map(|tiles| tiles.iter().map(|tile| tile.state).collect()),
+        }
+    }
+
+    /// If the state changed in a way that deltas can't describe
+    fn is_outdated(&self, state: &GameState) -> bool {
+        state.tick_count < self.tick_count
+            || state.tick_rate != self.tick_rate
+            || state.game_mode != self.game_mode
+            || state.mutator_config.map(|config| config.to_bytes()) != self.mutator_config
+            || state.arena_config != self.arena_config
+            || state.pads.len() != self.pads.len()
+            || state
+                .tiles
+                .iter()
+                .zip(&self.tiles)
+                .any(|(tiles, base)| tiles.len() != base.len())
+    }
+}
+
+/// Encodes game states as keyframes with the full state, followed by deltas with only what changed
+///
+/// Deltas include the ball and cars whose state changed, the ids of every car so removed cars can be dropped,
+/// and bitsets of which boost pads and dropshot tiles changed.
+/// Every frame must be decoded in order by a `DeltaDecoder`, if one gets lost call `force_keyframe`.
+pub struct DeltaEncoder {
+    pub config: DeltaConfig,
+    base: Option<Base>,
+    frames_since_keyframe: u32,
+}
+
+impl Default for DeltaEncoder {
+    #[inline]
+    fn default() -> Self {
+        Self::new(DeltaConfig::default())
+    }
+}
+
+impl DeltaEncoder {
+    #[inline]
+    #[must_use]
+    pub const fn new(config: DeltaConfig) -> Self {
+        Self {
+            config,
+            base: None,
+            frames_since_keyframe: 0,
+        }
+    }
+
+    #[inline]
+    /// Makes the next frame a keyframe, like when a new viewer connects
+    pub fn force_keyframe(&mut self) {
+        self.base = None;
+    }
+
+    #[must_use]
+    pub fn encode(&mut self, state: &GameState) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.encode_into(state, &mut bytes);
+        bytes
+    }
+
+    /// Appends the next frame to the end of an existing buffer, so it can be reused
+    pub fn encode_into(&mut self, state: &GameState, bytes: &mut Vec<u8>) -> FrameKind {
+        let start = bytes.len();
+
+        let kind = match &mut self.base {
+            Some(base) if self.frames_since_keyframe < self.config.keyframe_interval && !base.is_outdated(state) => {
+                Self::write_delta(base, state, self.config.quantize, bytes);
+                self.frames_since_keyframe += 1;
+                FrameKind::Delta
+            }
+            _ => {
+                bytes.extend([0; u32::NUM_BYTES]);
+                bytes.push(kind::KEYFRAME);
+                state.encode_into(bytes);
+
+                self.base = Some(Base::new(state, self.config.quantize));
+                self.frames_since_keyframe = 1;
+                FrameKind::Keyframe
+            }
+        };
+
+        let num_bytes = (bytes.len() - start) as u32;
+        bytes[start..start + u32::NUM_BYTES].copy_from_slice(&num_bytes.to_bytes());
+
+        kind
+    }
+
+    fn write_delta(base: &mut Base, state: &GameState, quantize: bool, bytes: &mut Vec<u8>) {
+        bytes.extend([0; u32::NUM_BYTES]);
+        bytes.push(kind::DELTA);
+        bytes.extend(base.tick_count.to_bytes());
+        bytes.extend(state.tick_count.to_bytes());
+
+        let flags_idx = bytes.len();
+        let mut flags = if quantize { flags::QUANTIZED } else { 0 };
+        if state.is_ball_scored {
+            flags |= flags::IS_BALL_SCORED;
+        }
+        bytes.push(0);
+
+        let ball_idx = bytes.len();
+        write_state(bytes, &state.ball.to_bytes(), quantize);
+        if bytes[ball_idx..] == base.ball {
+            bytes.truncate(ball_idx);
+        } else {
+            flags |= flags::BALL;
+            base.ball.clear();
+            base.ball.extend_from_slice(&bytes[ball_idx..]);
+        }
+        bytes[flags_idx] = flags;
+
+        // the ids of every car in order, cars that aren't in here anymore were removed
+        base.cars
+            .retain(|base_car| state.cars.iter().any(|car| car.id == base_car.id));
+        bytes.extend((state.cars.len() as u32).to_bytes());
+        for car in &state.cars {
+            bytes.extend(car.id.to_bytes());
+        }
+
+        let num_changed_idx = bytes.len();
+        bytes.extend([0; u32::NUM_BYTES]);
+
+        let mut num_changed = 0u32;
+        for car in &state.cars {
+            let car_idx = bytes.len();
+            bytes.extend(car.id.to_bytes());
+
+            // a car that was replaced under the same id, with a different team or config, is sent in full again
+            let info = car_info_bytes(car);
+            let existing = base.cars.iter().position(|base_car| base_car.id == car.id);
+            let is_full = existing.is_none_or(|i| base.cars[i].info != info);
+            bytes.push(u8::from(is_full));
+            if is_full {
+                bytes.extend_from_slice(&info);
+            }
+
+            let state_idx = bytes.len();
+            write_state(bytes, &car.state.to_bytes(), quantize);
+
+            match existing {
+                Some(i) if !is_full && bytes[state_idx..] == base.cars[i].state => {
+                    bytes.truncate(car_idx);
+                    continue;
+                }
+                Some(i) => {
+                    let base_car = &mut base.cars[i];
+                    base_car.info = info;
+                    base_car.state.clear();
+                    base_car.state.extend_from_slice(&bytes[state_idx..]);
+                }
+                None => base.cars.push(BaseCar {
+                    id: car.id,
+                    info,
+                    state: bytes[state_idx..].to_vec(),
+                }),
+            }
+
+            num_changed += 1;
+        }
+        bytes[num_changed_idx..num_changed_idx + u32::NUM_BYTES].copy_from_slice(&num_changed.to_bytes());
+
+        write_changed(bytes, &mut base.pads, state.pads.iter().map(|pad| pad.state));
+        for (base, tiles) in base.tiles.iter_mut().zip(&state.tiles) {
+            write_changed(bytes, base, tiles.iter().map(|tile| tile.state));
+        }
+
+        base.tick_count = state.tick_count;
+    }
+}
+
+/// Decodes the frames written by a `DeltaEncoder`
+#[derive(Clone, Debug, Default)]
+pub struct DeltaDecoder {
+    state: Option<GameState>,
+}
+
+impl DeltaDecoder {
+    /// The size of the frame header, this is enough to call `get_num_bytes`
+    pub const MIN_NUM_BYTES: usize = FRAME_HEADER_NUM_BYTES;
+
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { state: None }
+    }
+
+    #[inline]
+    #[must_use]
+    /// The length of the frame that starts with the given bytes
+    pub fn get_num_bytes(bytes: &[u8]) -> usize {
+        u32::from_bytes(&bytes[..u32::NUM_BYTES]) as usize
+    }
+
+    #[inline]
+    #[must_use]
+    /// The state after the last frame, `None` until the first keyframe
+    pub const fn state(&self) -> Option<&GameState> {
+        self.state.as_ref()
+    }
+
+    /// Decodes the next frame, returning the new state
+    ///
+    /// If the frame can't be decoded the state isn't changed.
+    pub fn decode(&mut self, frame: &[u8]) -> Result<&GameState, DeltaError> {
+        let mut reader = TryByteReader::new(frame);
+
+        let num_bytes = reader.read::<u32>()? as usize;
+        if num_bytes != frame.len() {
+            return Err(DecodeError::new(
+                0,
+                DecodeReason::LengthMismatch {
+                    expected: num_bytes,
+                    found: frame.len(),
+                },
+            )
+            .into());
+        }
+
+        let offset = reader.position();
+        let state = match reader.read::<u8>()? {
+            kind::KEYFRAME => GameState::try_from_bytes(&frame[FRAME_HEADER_NUM_BYTES..])
+                .map_err(|e| e.offset_by(FRAME_HEADER_NUM_BYTES))?,
+            kind::DELTA => self.decode_delta(reader)?,
+            value => {
+                return Err(DecodeError::new(
+                    offset,
+                    DecodeReason::InvalidValue {
+                        name: "FrameKind",
+                        value,
+                    },
+                )
+                .into())
+            }
+        };
+
+        Ok(self.state.insert(state))
+    }
+
+    fn decode_delta(&self, mut reader: TryByteReader) -> Result<GameState, DeltaError> {
+        let base_tick = reader.read::<u64>()?;
+        let mut state = match &self.state {
+            Some(state) if state.tick_count == base_tick => state.clone(),
+            state => {
+                return Err(DeltaError::MissingBase {
+                    base_tick,
+                    current_tick: state.as_ref().map(|state| state.tick_count),
+                })
+            }
+        };
+
+        state.tick_count = reader.read()?;
+
+        let flags = reader.read::<u8>()?;
+        let quantized = flags & flags::QUANTIZED != 0;
+        state.is_ball_scored = flags & flags::IS_BALL_SCORED != 0;
+
+        if flags & flags::BALL != 0 {
+            state.ball = read_state::<BallState>(&mut reader, quantized)?;
+        }
+
+        let num_cars = reader.read::<u32>()?;
+        let car_ids = reader.read_vec::<u32>(num_cars as usize)?;
+
+        let num_changed = reader.read::<u32>()?;
+        for _ in 0..num_changed {
+            let id = reader.read::<u32>()?;
+
+            if reader.read::<bool>()? {
+                let team = reader.read::<Team>()?;
+                let config = reader.read::<CarConfig>()?;
+                let car_state = read_state::<CarState>(&mut reader, quantized)?;
+
+                let car = CarInfo {
+                    id,
+                    team,
+                    state: car_state,
+                    config,
+                };
+
+                match state.cars.iter_mut().find(|car| car.id == id) {
+                    Some(existing) => *existing = car,
+                    None => state.cars.push(car),
+                }
+            } else {
+                let car_state = read_state::<CarState>(&mut reader, quantized)?;
+                let car = state
+                    .cars
+                    .iter_mut()
+                    .find(|car| car.id == id)
+                    .ok_or(DeltaError::UnknownCar(id))?;
+                car.state = car_state;
+            }
+        }
+
+        let mut cars = Vec::with_capacity(car_ids.len());
+        for car_id in car_ids {
+            let car = state
+                .cars
+                .iter()
+                .find(|car| car.id == car_id)
+                .ok_or(DeltaError::UnknownCar(car_id))?;
+            cars.push(*car);
+        }
+        state.cars = cars;
+
+        read_changed(&mut reader, state.pads.iter_mut().map(|pad| &mut pad.state))?;
+        for tiles in &mut state.tiles {
+            read_changed(&mut reader, tiles.iter_mut().map(|tile| &mut tile.state))?;
+        }
+
+        reader.finish()?;
+        Ok(state)
+    }
+}
@@ -0,0 +1,384 @@
+use crate::{
+    math::{RotMat, Vec3},
+    sim::{Arena, BallState, CarControls, CarState},
+    GameState, NoCarFound,
+};
+use core::fmt;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// A single value in a `GameState`
+pub enum FieldValue {
+    Float(f32),
+    Int(u64),
+    Bool(bool),
+}
+
+impl FieldValue {
+    fn matches(self, other: Self, tolerance: f32) -> bool {
+        match (self, other) {
+            (Self::Float(a), Self::Float(b)) => {
+                a.to_bits() == b.to_bits() || (a.is_nan() && b.is_nan()) || (a - b).abs() <= tolerance
+            }
+            _ => self == other,
+        }
+    }
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Float(value) => write!(f, "{value}"),
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Bool(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// The first field that was different between two game states
+pub struct Divergence {
+    /// Which run diverged from the first run, starting at 1
+    pub run: usize,
+    pub tick_count: u64,
+    /// The path to the field, like `cars[1].state.vel.x`
+    pub field: String,
+    pub expected: FieldValue,
+    pub found: FieldValue,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Run {} diverged at tick {}: {} was {} instead of {}",
+            self.run, self.tick_count, self.field, self.found, self.expected
+        )
+    }
+}
+
+impl GameState {
+    #[must_use]
+    /// A hash of the car, ball, pad and tile state that is the same across platforms and versions of Rust
+    ///
+    /// Floats are rounded to the nearest multiple of `tolerance` before hashing,
+    /// so tiny differences can be ignored. A tolerance of 0 hashes the exact values.
+    pub fn stable_hash(&self, tolerance: f32) -> u64 {
+        let mut hash = FNV_OFFSET_BASIS;
+
+        walk(self, |_, value| {
+            let bytes = match value {
+                FieldValue::Float(value) => quantize(value, tolerance).to_le_bytes(),
+                FieldValue::Int(value) => value.to_le_bytes(),
+                FieldValue::Bool(value) => u64::from(value).to_le_bytes(),
+            };
+
+            for byte in bytes {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        });
+
+        hash
+    }
+
+    #[must_use]
+    /// Finds the first field that differs by more than `tolerance` from `other`,
+    /// returning the path to the field and both values
+    pub fn first_difference(&self, other: &Self, tolerance: f32) -> Option<(String, FieldValue, FieldValue)> {
+        let expected = collect(self);
+        let found = collect(other);
+
+        expected
+            .into_iter()
+            .zip(found)
+            .find(|((path, a), (other_path, b))| path != other_path || !a.matches(*b, tolerance))
+            .map(|((path, a), (_, b))| (path, a, b))
+    }
+}
+
+/// Checks that stepping clones of the arena with the same controls always gives the same result
+///
+/// Each item in `controls` is used for one tick. The arena is cloned `runs` times without callbacks,
+/// and every run is compared against the first one after every tick.
+///
+/// Returns the first divergence found, or `None` if every run was the same
+pub fn verify_determinism(
+    arena: &Arena,
+    controls: &[Vec<(u32, CarControls)>],
+    runs: usize,
+) -> Result<Option<Divergence>, NoCarFound> {
+    verify_determinism_with_tolerance(arena, controls, runs, 0.)
+}
+
+/// Same as `verify_determinism`, but floats that differ by at most `tolerance` are considered equal
+pub fn verify_determinism_with_tolerance(
+    arena: &Arena,
+    controls: &[Vec<(u32, CarControls)>],
+    runs: usize,
+    tolerance: f32,
+) -> Result<Option<Divergence>, NoCarFound> {
+    let mut expected = Vec::with_capacity(controls.len());
+
+    let mut reference = arena.clone(false);
+    for tick_controls in controls {
+        reference.pin_mut().set_all_controls(tick_controls)?;
+        reference.pin_mut().step(1);
+
+        let state = reference.pin_mut().get_game_state();
+        expected.push((state.stable_hash(tolerance), state));
+    }
+
+    for run in 1..runs {
+        let mut arena = arena.clone(false);
+
+        for (tick_controls, (expected_hash, expected_state)) in controls.iter().zip(&expected) {
+            arena.pin_mut().set_all_controls(tick_controls)?;
+            arena.pin_mut().step(1);
+
+            let state = arena.pin_mut().get_game_state();
+            if state.stable_hash(tolerance) == *expected_hash {
+                continue;
+            }
+
+            // hashes can differ when values within the tolerance get rounded differently
+            if let Some((field, expected, found)) = expected_state.first_difference(&state, tolerance) {
+                return Ok(Some(Divergence {
+                    run,
+                    tick_count: state.tick_count,
+                    field,
+                    expected,
+                    found,
+                }));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+#[inline]
+fn quantize(value: f32, tolerance: f32) -> u64 {
+    if tolerance > 0. && value.is_finite() {
+        ((f64::from(value) / f64::from(tolerance)).round() as i64) as u64
+    } else if value == 0. {
+        // make -0 and 0 hash the same
+        0
+    } else {
+        u64::from(value.to_bits())
+    }
+}
+
+fn collect(state: &GameState) -> Vec<(String, FieldValue)> {
+    let mut fields = Vec::new();
+
+    walk(state, |path, value| {
+        let mut name = String::new();
+        for segment in path {
+            match segment {
+                Segment::Field(field) => {
+                    if !name.is_empty() {
+                        name.push('.');
+                    }
+                    name.push_str(field);
+                }
+                Segment::Index(i) => name.push_str(&format!("[{i}]")),
+            }
+        }
+
+        fields.push((name, value));
+    });
+
+    fields
+}
+
+#[derive(Clone, Copy)]
+enum Segment {
+    Field(&'static str),
+    Index(usize),
+}
+
+/// Visits every value in the game state in a fixed order, along with its path
+fn walk<F: FnMut(&[Segment], FieldValue)>(state: &GameState, visit: F) {
+    let mut walker = Walker { path: Vec::new(), visit };
+
+    walker.int("tick_count", state.tick_count);
+    walker.bool("is_ball_scored", state.is_ball_scored);
+    walker.nested("ball", |w| w.ball(&state.ball));
+    walker.nested("cars", |w| {
+        w.int("len", state.cars.len() as u64);
+        for (i, car) in state.cars.iter().enumerate() {
+            w.index(i, |w| {
+                w.int("id", u64::from(car.id));
+                w.int("team", car.team as u64);
+                w.nested("state", |w| w.car(&car.state));
+            });
+        }
+    });
+    walker.nested("pads", |w| {
+        w.int("len", state.pads.len() as u64);
+        for (i, pad) in state.pads.iter().enumerate() {
+            w.index(i, |w| {
+                w.nested("state", |w| {
+                    w.bool("is_active", pad.state.is_active);
+                    w.float("cooldown", pad.state.cooldown);
+                    w.int("cur_locked_car_id", u64::from(pad.state.cur_locked_car_id));
+                    w.int("prev_locked_car_id", u64::from(pad.state.prev_locked_car_id));
+                });
+            });
+        }
+    });
+    walker.nested("tiles", |w| {
+        for (team, tiles) in state.tiles.iter().enumerate() {
+            w.index(team, |w| {
+                w.int("len", tiles.len() as u64);
+                for (i, tile) in tiles.iter().enumerate() {
+                    w.index(i, |w| w.int("state", tile.state as u64));
+                }
+            });
+        }
+    });
+}
+
+struct Walker<F: FnMut(&[Segment], FieldValue)> {
+    path: Vec<Segment>,
+    visit: F,
+}
+
+impl<F: FnMut(&[Segment], FieldValue)> Walker<F> {
+    fn value(&mut self, name: &'static str, value: FieldValue) {
+        self.path.push(Segment::Field(name));
+        (self.visit)(&self.path, value);
+        self.path.pop();
+    }
+
+    #[inline]
+    fn float(&mut self, name: &'static str, value: f32) {
+        self.value(name, FieldValue::Float(value));
+    }
+
+    #[inline]
+    fn int(&mut self, name: &'static str, value: u64) {
+        self.value(name, FieldValue::Int(value));
+    }
+
+    #[inline]
+    fn bool(&mut self, name: &'static str, value: bool) {
+        self.value(name, FieldValue::Bool(value));
+    }
+
+    fn nested(&mut self, name: &'static str, f: impl FnOnce(&mut Self)) {
+        self.path.push(Segment::Field(name));
+        f(self);
+        self.path.pop();
+    }
+
+    fn index(&mut self, i: usize, f: impl FnOnce(&mut Self)) {
+        self.path.push(Segment::Index(i));
+        f(self);
+        self.path.pop();
+    }
+
+    fn vec3(&mut self, name: &'static str, vec: Vec3) {
+        self.nested(name, |w| {
+            w.float("x", vec.x);
+            w.float("y", vec.y);
+            w.float("z", vec.z);
+        });
+    }
+
+    fn rot_mat(&mut self, name: &'static str, mat: RotMat) {
+        self.nested(name, |w| {
+            w.vec3("forward", mat.forward);
+            w.vec3("right", mat.right);
+            w.vec3("up", mat.up);
+        });
+    }
+
+    fn ball(&mut self, ball: &BallState) {
+        self.vec3("pos", ball.pos);
+        self.rot_mat("rot_mat", ball.rot_mat);
+        self.vec3("vel", ball.vel);
+        self.vec3("ang_vel", ball.ang_vel);
+        self.nested("hs_info", |w| {
+            w.float("y_target_dir", ball.hs_info.y_target_dir);
+            w.float("cur_target_speed", ball.hs_info.cur_target_speed);
+            w.float("time_since_hit", ball.hs_info.time_since_hit);
+        });
+        self.nested("ds_info", |w| {
+            w.int("charge_level", ball.ds_info.charge_level as u64);
+            w.float("accumulated_hit_force", ball.ds_info.accumulated_hit_force);
+            w.float("y_target_dir", ball.ds_info.y_target_dir);
+            w.bool("has_damaged", ball.ds_info.has_damaged);
+            w.int("last_damage_tick", ball.ds_info.last_damage_tick);
+        });
+    }
+
+    fn car(&mut self, car: &CarState) {
+        self.vec3("pos", car.pos);
+        self.rot_mat("rot_mat", car.rot_mat);
+        self.vec3("vel", car.vel);
+        self.vec3("ang_vel", car.ang_vel);
+        self.bool("is_on_ground", car.is_on_ground);
+        self.nested("wheels_with_contact", |w| {
+            for (i, &contact) in car.wheels_with_contact.iter().enumerate() {
+                w.index(i, |w| w.bool("has_contact", contact));
+            }
+        });
+        self.bool("has_jumped", car.has_jumped);
+        self.bool("has_double_jumped", car.has_double_jumped);
+        self.bool("has_flipped", car.has_flipped);
+        self.vec3("flip_rel_torque", car.flip_rel_torque);
+        self.float("jump_time", car.jump_time);
+        self.float("flip_time", car.flip_time);
+        self.bool("is_flipping", car.is_flipping);
+        self.bool("is_jumping", car.is_jumping);
+        self.float("air_time", car.air_time);
+        self.float("air_time_since_jump", car.air_time_since_jump);
+        self.float("boost", car.boost);
+        self.float("time_since_boosted", car.time_since_boosted);
+        self.bool("is_boosting", car.is_boosting);
+        self.float("boosting_time", car.boosting_time);
+        self.bool("is_supersonic", car.is_supersonic);
+        self.float("supersonic_time", car.supersonic_time);
+        self.float("handbrake_val", car.handbrake_val);
+        self.bool("is_auto_flipping", car.is_auto_flipping);
+        self.float("auto_flip_timer", car.auto_flip_timer);
+        self.float("auto_flip_torque_scale", car.auto_flip_torque_scale);
+        self.nested("world_contact", |w| {
+            w.bool("has_contact", car.world_contact.has_contact);
+            w.vec3("contact_normal", car.world_contact.contact_normal);
+        });
+        self.nested("car_contact", |w| {
+            w.int("other_car_id", u64::from(car.car_contact.other_car_id));
+            w.float("cooldown_timer", car.car_contact.cooldown_timer);
+        });
+        self.bool("is_demoed", car.is_demoed);
+        self.float("demo_respawn_timer", car.demo_respawn_timer);
+        self.nested("ball_hit_info", |w| {
+            let info = &car.ball_hit_info;
+            w.bool("is_valid", info.is_valid);
+            w.vec3("relative_pos_on_ball", info.relative_pos_on_ball);
+            w.vec3("ball_pos", info.ball_pos);
+            w.vec3("extra_hit_vel", info.extra_hit_vel);
+            w.int("tick_count_when_hit", info.tick_count_when_hit);
+            w.int(
+                "tick_count_when_extra_impulse_applied",
+                info.tick_count_when_extra_impulse_applied,
+            );
+        });
+        self.nested("last_controls", |w| {
+            let controls = &car.last_controls;
+            w.float("throttle", controls.throttle);
+            w.float("steer", controls.steer);
+            w.float("pitch", controls.pitch);
+            w.float("yaw", controls.yaw);
+            w.float("roll", controls.roll);
+            w.bool("jump", controls.jump);
+            w.bool("boost", controls.boost);
+            w.bool("handbrake", controls.handbrake);
+        });
+    }
+}
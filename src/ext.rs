@@ -1,15 +1,16 @@
 use crate::{
     base, consts,
-    math::{Angle, RotMat, Vec3},
+    math::{Angle, Quat, RotMat, Vec3},
     sim::{
-        Arena, ArenaConfig, ArenaMemWeightMode, BallHitInfo, BallState, BoostPadConfig, BoostPadState, CarConfig,
-        CarContact, CarControls, CarState, DemoMode, DropshotInfo, DropshotTileState, DropshotTilesState, GameMode,
-        GetTilePos, HeatseekerInfo, MutatorConfig, Team, WorldContact,
+        Arena, ArenaConfig, ArenaMemWeightMode, ArenaSnapshot, BallHitInfo, BallState, BoostPadConfig, BoostPadState,
+        CarConfig, CarContact, CarControls, CarState, CarWheels, DemoMode, DropshotInfo, DropshotTileState,
+        DropshotTilesState, GameMode, GetTilePos, HeatseekerInfo, MutatorConfig, Team, WheelInfo, WheelPairConfig,
+        WorldContact,
     },
 };
 use core::pin::Pin;
 use cxx::UniquePtr;
-use std::{error::Error, fmt};
+use std::{error::Error, fmt, iter, ops};
 
 #[cfg(feature = "serde_utils")]
 use crate::serde_utils;
@@ -32,6 +33,31 @@ impl TryFrom<u8> for GameMode {
     }
 }
 
+impl TryFrom<u8> for DemoMode {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Normal),
+            1 => Ok(Self::OnContact),
+            2 => Ok(Self::Disabled),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TryFrom<u8> for ArenaMemWeightMode {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Heavy),
+            1 => Ok(Self::Light),
+            _ => Err(()),
+        }
+    }
+}
+
 impl TryFrom<u8> for Team {
     type Error = ();
 
@@ -273,6 +299,47 @@ impl PartialEq for BoostPadState {
     }
 }
 
+impl PartialEq for ArenaConfig {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.mem_weight_mode == other.mem_weight_mode
+            && self.min_pos == other.min_pos
+            && self.max_pos == other.max_pos
+            && self.max_aabb_len == other.max_aabb_len
+            && self.no_ball_rot == other.no_ball_rot
+            && self.use_custom_broadphase == other.use_custom_broadphase
+            && self.max_objects == other.max_objects
+    }
+}
+
+impl PartialEq for BoostPadConfig {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.position == other.position && self.is_big == other.is_big
+    }
+}
+
+impl PartialEq for WheelPairConfig {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.wheel_radius == other.wheel_radius
+            && self.suspension_rest_length == other.suspension_rest_length
+            && self.connection_point_offset == other.connection_point_offset
+    }
+}
+
+impl PartialEq for CarConfig {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.hitbox_size == other.hitbox_size
+            && self.hitbox_pos_offset == other.hitbox_pos_offset
+            && self.front_wheels == other.front_wheels
+            && self.back_wheels == other.back_wheels
+            && self.three_wheels == other.three_wheels
+            && self.dodge_deadzone == other.dodge_deadzone
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 /// Error for when a car with a given ID cannot be found
 pub struct NoCarFound(pub u32);
@@ -285,6 +352,85 @@ impl fmt::Display for NoCarFound {
     }
 }
 
+#[derive(Clone, Copy, Debug)]
+pub struct CarsChanged(pub u32);
+
+impl Error for CarsChanged {}
+impl fmt::Display for CarsChanged {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The car with ID {} was added or removed after the snapshot was taken.",
+            self.0
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+/// Part of a game state that `Arena::sync_game_state` couldn't apply to the arena
+pub enum UnappliedField {
+    /// The tick rate can only be chosen when creating the arena
+    TickRate { state: f32, arena: f32 },
+    /// The game mode can only be chosen when creating the arena
+    GameMode { state: GameMode, arena: GameMode },
+    /// The arena config can only be chosen when creating the arena
+    ArenaConfig,
+    /// The number of boost pads is different, only the pads that exist in both were set
+    Pads { state: usize, arena: usize },
+    /// The position or size of the boost pad at the given index is different, only its state was set
+    PadConfig(usize),
+    /// The number of dropshot tiles for a team is different, only the tiles that exist in both were set
+    Tiles { team: usize, state: usize, arena: usize },
+    /// The car couldn't be added with its ID from the game state
+    CarId(u32),
+}
+
+impl fmt::Display for UnappliedField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TickRate { state, arena } => write!(
+                f,
+                "Tick rate of {state} can't be applied to an arena with a tick rate of {arena}."
+            ),
+            Self::GameMode { state, arena } => write!(f, "Game mode {state:?} can't be applied to a {arena:?} arena."),
+            Self::Pads { state, arena } => {
+                write!(f, "{state} boost pads can't be applied to an arena with {arena} boost pads.")
+            }
+            Self::ArenaConfig => write!(f, "The arena config can't be changed after creating the arena."),
+            Self::PadConfig(index) => write!(f, "The config of the boost pad at index {index} can't be changed."),
+            Self::Tiles { team, state, arena } => write!(
+                f,
+                "{state} dropshot tiles for team {team} can't be applied to an arena with {arena} tiles."
+            ),
+            Self::CarId(car_id) => write!(f, "The car with ID {car_id} couldn't be added with the same ID."),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+/// Everything `Arena::sync_game_state` changed, and everything it couldn't
+pub struct SyncReport {
+    /// IDs of the cars that were only in the game state
+    pub added: Vec<u32>,
+    /// IDs of the cars that were only in the arena
+    pub removed: Vec<u32>,
+    /// IDs of the cars that were removed and added back to change their team or config
+    pub replaced: Vec<u32>,
+    pub unapplied: Vec<UnappliedField>,
+}
+
+impl SyncReport {
+    #[inline]
+    #[must_use]
+    /// Returns true if the arena now fully matches the game state
+    pub fn is_complete(&self) -> bool {
+        self.unapplied.is_empty()
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default)]
 #[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
 pub struct BoostPad {
@@ -305,6 +451,24 @@ pub struct CarInfo {
     pub config: CarConfig,
 }
 
+impl CarInfo {
+    #[must_use]
+    /// Returns the corners of the car's hitbox in world space
+    ///
+    /// Bits 0, 1 and 2 of the index say if the corner is on the front, right, and top of the hitbox.
+    pub fn hitbox_corners(&self) -> [Vec3; 8] {
+        let half_size = self.config.hitbox_size / 2.;
+        let offset = self.config.hitbox_pos_offset;
+
+        std::array::from_fn(|i| {
+            let sign = |bit: usize| if i & (1 << bit) == 0 { -1. } else { 1. };
+            let corner = Vec3::new(half_size.x * sign(0), half_size.y * sign(1), half_size.z * sign(2));
+
+            self.state.to_world(offset + corner)
+        })
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
 pub struct GameState {
@@ -316,6 +480,15 @@ pub struct GameState {
     pub ball: BallState,
     pub pads: Vec<BoostPad>,
     pub tiles: [Vec<DropshotTile>; 2],
+    /// If the ball is in a goal
+    #[cfg_attr(feature = "serde_utils", serde(default))]
+    pub is_ball_scored: bool,
+    /// The physics the state was simulated with, `None` if it wasn't saved
+    #[cfg_attr(feature = "serde_utils", serde(default, with = "serde_utils::option_mutator_config"))]
+    pub mutator_config: Option<MutatorConfig>,
+    /// The config the arena was created with, `None` if it wasn't saved
+    #[cfg_attr(feature = "serde_utils", serde(default, with = "serde_utils::option_arena_config"))]
+    pub arena_config: Option<ArenaConfig>,
 }
 
 impl Arena {
@@ -386,6 +559,25 @@ impl Arena {
         }
     }
 
+    #[inline]
+    /// Puts the arena back to exactly how it was when the snapshot was taken
+    ///
+    /// Restoring can allocate if objects that were touching in the snapshot have separated since,
+    /// because Bullet has to recreate the broadphase pairs and contact manifolds between them.
+    ///
+    /// The snapshot should come from this arena,
+    /// and cars can't be added or removed between taking the snapshot and restoring it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CarsChanged` with the ID of the first car that was added or removed, leaving the arena untouched
+    pub fn restore(self: Pin<&mut Self>, snapshot: &ArenaSnapshot) -> Result<(), CarsChanged> {
+        match self.rstr(snapshot) {
+            0 => Ok(()),
+            car_id => Err(CarsChanged(car_id)),
+        }
+    }
+
     #[inline]
     /// Sets the state of the car at the given ID
     ///
@@ -462,6 +654,35 @@ impl Arena {
         }
     }
 
+    #[inline]
+    /// Returns the wheels of the car at the given ID, in the order front left, front right, back left, back right
+    ///
+    /// # Errors
+    ///
+    /// If there is no car with the given ID, this will return an error
+    pub fn get_car_wheels(&self, car_id: u32) -> Result<[WheelInfo; 4], NoCarFound> {
+        let mut wheels = CarWheels::default();
+        if self.gcw(car_id, &mut wheels) {
+            Ok(wheels.wheels)
+        } else {
+            Err(NoCarFound(car_id))
+        }
+    }
+
+    #[inline]
+    /// Returns the corners of the hitbox of the car at the given ID in world space, see `CarInfo::hitbox_corners`
+    ///
+    /// # Errors
+    ///
+    /// If there is no car with the given ID, this will return an error
+    pub fn get_car_hitbox_corners(self: Pin<&mut Self>, car_id: u32) -> Result<[Vec3; 8], NoCarFound> {
+        if self.get_cars().contains(&car_id) {
+            Ok(self.get_car_info(car_id).hitbox_corners())
+        } else {
+            Err(NoCarFound(car_id))
+        }
+    }
+
     #[inline]
     #[must_use]
     /// Returns all of the `CarInfo`s in the arena
@@ -544,13 +765,18 @@ impl Arena {
             } else {
                 Default::default()
             },
+            is_ball_scored: self.is_ball_scored(),
+            mutator_config: Some(self.get_mutator_config()),
+            arena_config: Some(self.get_arena_config()),
             cars: self.get_car_infos(),
         }
     }
 
     /// Full game state setter
     ///
-    /// Note: Some things cannot be state set, such game tick count/tick rate - these will be ignored
+    /// Note: Some things cannot be state set, such game tick count/tick rate/arena config - these will be ignored
+    ///
+    /// The mutator config is ignored as well, use `sync_game_state` to apply it
     ///
     /// # Errors
     ///
@@ -579,6 +805,136 @@ impl Arena {
         Ok(())
     }
 
+    /// Makes the arena match the game state as closely as possible
+    ///
+    /// Unlike `set_game_state`, cars are added or removed so the arena has exactly the cars in the game state,
+    /// with the same IDs, teams and configs. The tick count is also restored.
+    ///
+    /// Anything that can't be changed after creating the arena, like the tick rate, game mode or arena config,
+    /// is left as-is and listed in `SyncReport::unapplied`.
+    pub fn sync_game_state(mut self: Pin<&mut Self>, game_state: &GameState) -> SyncReport {
+        let mut report = SyncReport::default();
+
+        let tick_rate = self.get_tick_rate();
+        if (tick_rate - game_state.tick_rate).abs() > f32::EPSILON * tick_rate.max(1.) {
+            report.unapplied.push(UnappliedField::TickRate {
+                state: game_state.tick_rate,
+                arena: tick_rate,
+            });
+        }
+
+        let game_mode = self.get_game_mode();
+        if game_mode != game_state.game_mode {
+            report.unapplied.push(UnappliedField::GameMode {
+                state: game_state.game_mode,
+                arena: game_mode,
+            });
+        }
+
+        if let Some(arena_config) = game_state.arena_config {
+            if arena_config != self.get_arena_config() {
+                report.unapplied.push(UnappliedField::ArenaConfig);
+            }
+        }
+
+        if let Some(mutator_config) = game_state.mutator_config {
+            self.as_mut().set_mutator_config(mutator_config);
+        }
+
+        for car_id in self.get_cars() {
+            let Some(car) = game_state.cars.iter().find(|car| car.id == car_id) else {
+                self.as_mut().remove_car(car_id).expect("car id comes from get_cars");
+                report.removed.push(car_id);
+                continue;
+            };
+
+            if car.team != self.get_car_team(car_id) || car.config != self.get_car_config(car_id) {
+                self.as_mut().remove_car(car_id).expect("car id comes from get_cars");
+                report.replaced.push(car_id);
+            }
+        }
+
+        let existing = self.get_cars();
+        for car in &game_state.cars {
+            if existing.contains(&car.id) {
+                continue;
+            }
+
+            if self.as_mut().acwi(car.id, car.team, &car.config) {
+                if !report.replaced.contains(&car.id) {
+                    report.added.push(car.id);
+                }
+            } else {
+                report.replaced.retain(|&car_id| car_id != car.id);
+                report.unapplied.push(UnappliedField::CarId(car.id));
+            }
+        }
+
+        for car in &game_state.cars {
+            // only fails if the car couldn't be added, which has already been reported
+            let _ = self.as_mut().set_car(car.id, car.state);
+        }
+
+        if game_mode == GameMode::Dropshot {
+            let mut tile_states = DropshotTilesState::DEFAULT;
+            for (team, tiles) in game_state.tiles.iter().enumerate() {
+                let arena_tiles = tile_states.states[team].len();
+                if tiles.len() != arena_tiles {
+                    report.unapplied.push(UnappliedField::Tiles {
+                        team,
+                        state: tiles.len(),
+                        arena: arena_tiles,
+                    });
+                }
+
+                for (state, tile) in tile_states.states[team].iter_mut().zip(tiles) {
+                    state.damage_state = tile.state as u8;
+                }
+            }
+            self.as_mut().set_dropshot_tiles_state(&tile_states);
+
+            // dropshot arenas don't have boost pads
+            if !game_state.pads.is_empty() {
+                report.unapplied.push(UnappliedField::Pads {
+                    state: game_state.pads.len(),
+                    arena: 0,
+                });
+            }
+        } else {
+            // only dropshot arenas have tiles
+            for (team, tiles) in game_state.tiles.iter().enumerate() {
+                if !tiles.is_empty() {
+                    report.unapplied.push(UnappliedField::Tiles {
+                        team,
+                        state: tiles.len(),
+                        arena: 0,
+                    });
+                }
+            }
+
+            let num_pads = self.num_pads();
+            if game_state.pads.len() != num_pads {
+                report.unapplied.push(UnappliedField::Pads {
+                    state: game_state.pads.len(),
+                    arena: num_pads,
+                });
+            }
+
+            for (i, pad) in game_state.pads.iter().enumerate().take(num_pads) {
+                if pad.config != self.get_pad_config(i) {
+                    report.unapplied.push(UnappliedField::PadConfig(i));
+                }
+
+                self.as_mut().set_pad_state(i, pad.state);
+            }
+        }
+
+        self.as_mut().set_ball(game_state.ball);
+        self.set_tick_count(game_state.tick_count);
+
+        report
+    }
+
     #[inline]
     #[must_use]
     /// Returns true if the ball is probably going in, does not account for wall or ceiling bounces
@@ -693,6 +1049,78 @@ impl CarState {
             Some(arena.get_car(self.car_contact.other_car_id))
         }
     }
+
+    #[inline]
+    #[must_use]
+    /// Transforms a world position into the car's local space,
+    /// where x is forward, y is right, and z is up from the car's origin
+    pub fn to_local(&self, point: Vec3) -> Vec3 {
+        self.rot_mat.transpose() * (point - self.pos)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Transforms a position in the car's local space into world space
+    pub fn to_world(&self, local: Vec3) -> Vec3 {
+        self.pos + self.rot_mat * local
+    }
+
+    #[must_use]
+    /// Interpolates between two snapshots of the car, where `t` of 0 is `self` and 1 is `other`
+    ///
+    /// Position and velocities are interpolated linearly and the rotation spherically.
+    /// Boost is only interpolated while it's being used, since pickups are instant.
+    /// Everything else, like `has_jumped` and timers, comes from the closest snapshot.
+    /// If the car was demoed in either snapshot, it teleports so the closest snapshot is returned as-is.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let closest = if t < 0.5 { self } else { other };
+        if self.is_demoed || other.is_demoed {
+            return *closest;
+        }
+
+        let boost = if other.boost <= self.boost {
+            self.boost + (other.boost - self.boost) * t
+        } else {
+            closest.boost
+        };
+
+        Self {
+            pos: self.pos.lerp(other.pos, t),
+            rot_mat: self.rot_mat.slerp(other.rot_mat, t),
+            vel: self.vel.lerp(other.vel, t),
+            ang_vel: self.ang_vel.lerp(other.ang_vel, t),
+            boost,
+            ..*closest
+        }
+    }
+}
+
+impl BallState {
+    #[must_use]
+    /// Interpolates between two snapshots of the ball, where `t` of 0 is `self` and 1 is `other`
+    ///
+    /// Position and velocities are interpolated linearly and the rotation spherically.
+    /// The heatseeker and dropshot info come from the closest snapshot.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let closest = if t < 0.5 { self } else { other };
+
+        Self {
+            pos: self.pos.lerp(other.pos, t),
+            rot_mat: self.rot_mat.slerp(other.rot_mat, t),
+            vel: self.vel.lerp(other.vel, t),
+            ang_vel: self.ang_vel.lerp(other.ang_vel, t),
+            ..*closest
+        }
+    }
+}
+
+impl WheelInfo {
+    #[inline]
+    #[must_use]
+    /// How far the suspension is pushed in from its rest length
+    pub fn suspension_compression(&self) -> f32 {
+        self.suspension_rest_length - self.suspension_length
+    }
 }
 
 impl fmt::Display for RotMat {
@@ -714,6 +1142,283 @@ impl RotMat {
     pub const fn new(forward: Vec3, right: Vec3, up: Vec3) -> Self {
         Self { forward, right, up }
     }
+
+    #[must_use]
+    /// Converts the angle to a rotation matrix the same way RocketSim does, without going through C++
+    pub fn from_angle(angle: Angle) -> Self {
+        let (sp, cp) = angle.pitch.sin_cos();
+        let (sy, cy) = angle.yaw.sin_cos();
+        let (sr, cr) = angle.roll.sin_cos();
+
+        Self {
+            forward: Vec3::new(cp * cy, cp * sy, sp),
+            right: Vec3::new(cy * sp * sr - cr * sy, sy * sp * sr + cr * cy, -cp * sr),
+            up: Vec3::new(-cr * cy * sp - sr * sy, -cr * sy * sp + sr * cy, cp * cr),
+        }
+    }
+
+    #[must_use]
+    /// Converts the rotation matrix to an angle the same way RocketSim does, without going through C++
+    pub fn to_angle(self) -> Angle {
+        let forward = self.forward;
+
+        Angle {
+            yaw: forward.y.atan2(forward.x),
+            pitch: forward.z.atan2(forward.x.hypot(forward.y)),
+            roll: (-self.right.z).atan2(self.up.z),
+        }
+    }
+
+    #[must_use]
+    /// Converts the quaternion, which doesn't need to be normalized, to a rotation matrix
+    ///
+    /// With the `glam` feature, glam quaternions can be passed in too.
+    pub fn from_quat(quat: impl Into<Quat>) -> Self {
+        let Quat { x, y, z, w } = quat.into().normalize();
+
+        Self {
+            forward: Vec3::new(1. - 2. * (y * y + z * z), 2. * (x * y + w * z), 2. * (x * z - w * y)),
+            right: Vec3::new(2. * (x * y - w * z), 1. - 2. * (x * x + z * z), 2. * (y * z + w * x)),
+            up: Vec3::new(2. * (x * z + w * y), 2. * (y * z - w * x), 1. - 2. * (x * x + y * y)),
+        }
+    }
+
+    #[must_use]
+    /// Converts the rotation matrix, which should be orthonormal, to a unit quaternion
+    pub fn to_quat(self) -> Quat {
+        // forward, right and up are the columns of the matrix
+        let Self {
+            forward: f,
+            right: r,
+            up: u,
+        } = self;
+        let trace = f.x + r.y + u.z;
+
+        let quat = if trace > 0. {
+            let s = (trace + 1.).sqrt() * 2.;
+            Quat::new((r.z - u.y) / s, (u.x - f.z) / s, (f.y - r.x) / s, s / 4.)
+        } else if f.x > r.y && f.x > u.z {
+            let s = (1. + f.x - r.y - u.z).sqrt() * 2.;
+            Quat::new(s / 4., (r.x + f.y) / s, (u.x + f.z) / s, (r.z - u.y) / s)
+        } else if r.y > u.z {
+            let s = (1. + r.y - f.x - u.z).sqrt() * 2.;
+            Quat::new((r.x + f.y) / s, s / 4., (u.y + r.z) / s, (u.x - f.z) / s)
+        } else {
+            let s = (1. + u.z - f.x - r.y).sqrt() * 2.;
+            Quat::new((u.x + f.z) / s, (u.y + r.z) / s, s / 4., (f.y - r.x) / s)
+        };
+
+        quat.normalize()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Swaps the rows and columns, which is the inverse if the matrix is a rotation
+    pub fn transpose(self) -> Self {
+        let Self {
+            forward: f,
+            right: r,
+            up: u,
+        } = self;
+
+        Self {
+            forward: Vec3::new(f.x, r.x, u.x),
+            right: Vec3::new(f.y, r.y, u.y),
+            up: Vec3::new(f.z, r.z, u.z),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn determinant(self) -> f32 {
+        self.forward.dot(self.right.cross(self.up))
+    }
+
+    #[must_use]
+    /// Returns the inverse of any matrix, or `None` if its determinant is 0
+    ///
+    /// Use `transpose` instead if the matrix is known to be a rotation.
+    pub fn inverse(self) -> Option<Self> {
+        let det = self.determinant();
+        if det == 0. || !det.is_finite() {
+            return None;
+        }
+
+        let Self {
+            forward: f,
+            right: r,
+            up: u,
+        } = self;
+        let rows = Self {
+            forward: r.cross(u) / det,
+            right: u.cross(f) / det,
+            up: f.cross(r) / det,
+        };
+
+        Some(rows.transpose())
+    }
+
+    #[inline]
+    #[must_use]
+    /// Spherically interpolates between two rotations, where `t` of 0 is `self` and 1 is `other`
+    pub fn slerp(self, other: Self, t: f32) -> Self {
+        Self::from_quat(self.to_quat().slerp(other.to_quat(), t))
+    }
+
+    #[must_use]
+    /// Makes the axes perpendicular and of length 1 again, keeping the direction of `forward`
+    ///
+    /// Useful after many small rotations have been applied, since rounding errors build up.
+    pub fn orthonormalize(self) -> Self {
+        let forward = self.forward.normalize_or_zero();
+        let right = self.right.reject(forward).normalize_or_zero();
+
+        Self {
+            forward,
+            right,
+            up: forward.cross(right),
+        }
+    }
+}
+
+impl ops::Mul<Vec3> for RotMat {
+    type Output = Vec3;
+
+    /// Rotates a vector from local space into world space
+    #[inline]
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        self.forward * rhs.x + self.right * rhs.y + self.up * rhs.z
+    }
+}
+
+impl ops::Mul for RotMat {
+    type Output = Self;
+
+    /// Applies `rhs` first, then `self`
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            forward: self * rhs.forward,
+            right: self * rhs.right,
+            up: self * rhs.up,
+        }
+    }
+}
+
+impl From<Angle> for Quat {
+    #[inline]
+    fn from(value: Angle) -> Self {
+        RotMat::from_angle(value).to_quat()
+    }
+}
+
+impl Default for Quat {
+    #[inline]
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Quat {
+    pub const IDENTITY: Self = Self::new(0., 0., 0., 1.);
+
+    #[inline]
+    #[must_use]
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    #[inline]
+    #[must_use]
+    /// A rotation of `angle` radians around `axis`, which should have a length of 1
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let (sin, cos) = (angle / 2.).sin_cos();
+        Self::new(axis.x * sin, axis.y * sin, axis.z * sin, cos)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the quaternion with a length of 1, or the identity if its length is 0
+    pub fn normalize(self) -> Self {
+        let length = self.length();
+        if length == 0. || !length.is_finite() {
+            Self::IDENTITY
+        } else {
+            Self::new(self.x / length, self.y / length, self.z / length, self.w / length)
+        }
+    }
+
+    #[must_use]
+    /// Spherically interpolates between two rotations along the shortest path,
+    /// where `t` of 0 is `self` and 1 is `other`
+    pub fn slerp(self, other: Self, t: f32) -> Self {
+        // a quaternion and its negative are the same rotation, so pick the one that's closer
+        let (other, dot) = if self.dot(other) < 0. {
+            (Self::new(-other.x, -other.y, -other.z, -other.w), -self.dot(other))
+        } else {
+            (other, self.dot(other))
+        };
+
+        let (a, b) = if dot > 0.9995 {
+            // nearly the same rotation, where slerp divides by almost 0
+            (1. - t, t)
+        } else {
+            let theta = dot.acos();
+            let sin = theta.sin();
+            (((1. - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+
+        Self::new(
+            self.x * a + other.x * b,
+            self.y * a + other.y * b,
+            self.z * a + other.z * b,
+            self.w * a + other.w * b,
+        )
+        .normalize()
+    }
+
+    #[inline]
+    #[must_use]
+    /// The opposite rotation, if the quaternion has a length of 1
+    pub const fn conjugate(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z, self.w)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Rotates the vector by the quaternion, which should have a length of 1
+    pub fn rotate(self, vec: Vec3) -> Vec3 {
+        let axis = Vec3::new(self.x, self.y, self.z);
+        let t = axis.cross(vec) * 2.;
+        vec + t * self.w + axis.cross(t)
+    }
+}
+
+impl ops::Mul for Quat {
+    type Output = Self;
+
+    /// Applies `rhs` first, then `self`
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+        )
+    }
 }
 
 impl Angle {
@@ -744,11 +1449,296 @@ impl Vec3 {
     pub const Y: Self = Self::new(0., 1., 0.);
     pub const Z: Self = Self::new(0., 0., 1.);
 
+    pub const ONE: Self = Self::splat(1.);
+    pub const NEG_X: Self = Self::new(-1., 0., 0.);
+    pub const NEG_Y: Self = Self::new(0., -1., 0.);
+    pub const NEG_Z: Self = Self::new(0., 0., -1.);
+
     #[inline]
     #[must_use]
     pub const fn new(x: f32, y: f32, z: f32) -> Self {
         Self { x, y, z, w: 0. }
     }
+
+    #[inline]
+    #[must_use]
+    /// Creates a vector with all components set to `value`
+    pub const fn splat(value: f32) -> Self {
+        Self::new(value, value, value)
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn to_array(self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn cross(self, other: Self) -> Self {
+        Self::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn length_squared(self) -> f32 {
+        self.dot(self)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn length(self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn distance_squared(self, other: Self) -> f32 {
+        (self - other).length_squared()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn distance(self, other: Self) -> f32 {
+        (self - other).length()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the vector with a length of 1, or NaN components if its length is 0
+    pub fn normalize(self) -> Self {
+        self / self.length()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the vector with a length of 1, or `Vec3::ZERO` if that's not possible
+    pub fn normalize_or_zero(self) -> Self {
+        let normalized = self.normalize();
+        if normalized.is_finite() {
+            normalized
+        } else {
+            Self::ZERO
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_finite(self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_nan(self) -> bool {
+        self.x.is_nan() || self.y.is_nan() || self.z.is_nan()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Linearly interpolates towards `other`, where `t` of 0 is `self` and 1 is `other`
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the part of the vector that points along `other`, or `Vec3::ZERO` if `other` has a length of 0
+    pub fn project(self, other: Self) -> Self {
+        let length_squared = other.length_squared();
+        if length_squared == 0. {
+            Self::ZERO
+        } else {
+            other * (self.dot(other) / length_squared)
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the part of the vector that's perpendicular to `other`
+    pub fn reject(self, other: Self) -> Self {
+        self - self.project(other)
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the angle between the vectors in radians, or 0 if either has a length of 0
+    pub fn angle_between(self, other: Self) -> f32 {
+        let lengths = (self.length_squared() * other.length_squared()).sqrt();
+        if lengths == 0. {
+            0.
+        } else {
+            (self.dot(other) / lengths).clamp(-1., 1.).acos()
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Scales the vector so that its length is between `min` and `max`, keeping its direction
+    pub fn clamp_length(self, min: f32, max: f32) -> Self {
+        debug_assert!(min <= max);
+
+        let length_squared = self.length_squared();
+        if length_squared < min * min {
+            self * (min / length_squared.sqrt())
+        } else if length_squared > max * max {
+            self * (max / length_squared.sqrt())
+        } else {
+            self
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Scales the vector down if its length is more than `max`, keeping its direction
+    pub fn clamp_length_max(self, max: f32) -> Self {
+        let length_squared = self.length_squared();
+        if length_squared > max * max {
+            self * (max / length_squared.sqrt())
+        } else {
+            self
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn abs(self) -> Self {
+        Self::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the smallest of each component
+    pub fn min(self, other: Self) -> Self {
+        Self::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z))
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the largest of each component
+    pub fn max(self, other: Self) -> Self {
+        Self::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z))
+    }
+
+    #[inline]
+    #[must_use]
+    /// Clamps each component between the components of `min` and `max`
+    pub fn clamp(self, min: Self, max: Self) -> Self {
+        self.max(min).min(max)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn min_element(self) -> f32 {
+        self.x.min(self.y).min(self.z)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn max_element(self) -> f32 {
+        self.x.max(self.y).max(self.z)
+    }
+}
+
+impl From<[f32; 3]> for Vec3 {
+    #[inline]
+    fn from([x, y, z]: [f32; 3]) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
+impl From<Vec3> for [f32; 3] {
+    #[inline]
+    fn from(value: Vec3) -> Self {
+        value.to_array()
+    }
+}
+
+/// Implements a component-wise operator between two vectors, and between a vector and a scalar
+///
+/// Every result is made with `new`, so the padding stays 0.
+macro_rules! impl_vec3_op {
+    ($trait:ident, $fn:ident, $assign_trait:ident, $assign_fn:ident, $op:tt) => {
+        impl ops::$trait for Vec3 {
+            type Output = Self;
+
+            #[inline]
+            fn $fn(self, rhs: Self) -> Self {
+                Self::new(self.x $op rhs.x, self.y $op rhs.y, self.z $op rhs.z)
+            }
+        }
+
+        impl ops::$trait<f32> for Vec3 {
+            type Output = Self;
+
+            #[inline]
+            fn $fn(self, rhs: f32) -> Self {
+                Self::new(self.x $op rhs, self.y $op rhs, self.z $op rhs)
+            }
+        }
+
+        impl ops::$trait<Vec3> for f32 {
+            type Output = Vec3;
+
+            #[inline]
+            fn $fn(self, rhs: Vec3) -> Vec3 {
+                Vec3::new(self $op rhs.x, self $op rhs.y, self $op rhs.z)
+            }
+        }
+
+        impl ops::$assign_trait for Vec3 {
+            #[inline]
+            fn $assign_fn(&mut self, rhs: Self) {
+                *self = *self $op rhs;
+            }
+        }
+
+        impl ops::$assign_trait<f32> for Vec3 {
+            #[inline]
+            fn $assign_fn(&mut self, rhs: f32) {
+                *self = *self $op rhs;
+            }
+        }
+    };
+}
+
+impl_vec3_op!(Add, add, AddAssign, add_assign, +);
+impl_vec3_op!(Sub, sub, SubAssign, sub_assign, -);
+impl_vec3_op!(Mul, mul, MulAssign, mul_assign, *);
+impl_vec3_op!(Div, div, DivAssign, div_assign, /);
+impl_vec3_op!(Rem, rem, RemAssign, rem_assign, %);
+
+impl ops::Neg for Vec3 {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl iter::Sum for Vec3 {
+    #[inline]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, ops::Add::add)
+    }
+}
+
+impl<'a> iter::Sum<&'a Self> for Vec3 {
+    #[inline]
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
 }
 
 pub struct LinearPieceCurve<const N: usize> {
@@ -16,10 +16,10 @@ use core::pin::Pin;
 use glam::{EulerRot, Mat3, Mat3A, Quat, Vec3, Vec3A, Vec4};
 
 use crate::{
-    math::{Angle, RotMat, Vec3 as Vec3R},
+    math::{Angle, Quat as QuatR, RotMat, Vec3 as Vec3R},
     sim::{
-        Arena, BallHitInfo, BallState, BoostPadConfig, BoostPadState, CarConfig, CarContact, CarControls, CarState,
-        DropshotInfo, GameMode, HeatseekerInfo, Team, WheelPairConfig, WorldContact,
+        Arena, ArenaConfig, BallHitInfo, BallState, BoostPadConfig, BoostPadState, CarConfig, CarContact, CarControls,
+        CarState, DropshotInfo, GameMode, HeatseekerInfo, MutatorConfig, Team, WheelPairConfig, WorldContact,
     },
     BoostPad, CarInfo, DropshotTile, GameState, TileState,
 };
@@ -110,6 +110,20 @@ impl From<Quat> for Angle {
     }
 }
 
+impl From<QuatR> for Quat {
+    #[inline]
+    fn from(value: QuatR) -> Self {
+        Self::from_xyzw(value.x, value.y, value.z, value.w)
+    }
+}
+
+impl From<Quat> for QuatR {
+    #[inline]
+    fn from(value: Quat) -> Self {
+        Self::new(value.x, value.y, value.z, value.w)
+    }
+}
+
 impl From<Vec3R> for Vec3A {
     #[inline]
     fn from(value: Vec3R) -> Self {
@@ -170,12 +184,6 @@ impl RotMat {
     pub fn to_glam(self) -> Mat3A {
         Mat3A::from(self)
     }
-
-    #[inline]
-    #[must_use]
-    pub fn from_quat(quat: Quat) -> Self {
-        Self::from(Mat3::from_quat(quat))
-    }
 }
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -739,6 +747,9 @@ pub struct GameStateA {
     pub ball: BallA,
     pub pads: Vec<BoostPadA>,
     pub tiles: [Vec<DropshotTileA>; 2],
+    pub is_ball_scored: bool,
+    pub mutator_config: Option<MutatorConfig>,
+    pub arena_config: Option<ArenaConfig>,
 }
 
 impl From<GameState> for GameStateA {
@@ -756,6 +767,9 @@ impl From<GameState> for GameStateA {
                 blue_tiles.into_iter().map(DropshotTileA::from).collect(),
                 orange_tiles.into_iter().map(DropshotTileA::from).collect(),
             ],
+            is_ball_scored: value.is_ball_scored,
+            mutator_config: value.mutator_config,
+            arena_config: value.arena_config,
         }
     }
 }
@@ -7,18 +7,34 @@
 
 #[cfg(feature = "bin")]
 pub mod bytes;
+#[cfg(feature = "bin")]
+pub mod delta;
 #[cfg(feature = "glam")]
 pub mod glam_ext;
+#[cfg(feature = "minimap")]
+pub mod minimap;
 #[cfg(feature = "bin")]
 pub mod render;
+#[cfg(feature = "bin")]
+pub mod replay;
+#[cfg(feature = "bin")]
+pub mod rlviser;
 #[cfg(feature = "serde_utils")]
 mod serde_utils;
 #[cfg(feature = "serde_utils")]
 pub use serde;
 
+pub mod agent;
+pub mod analysis;
+pub mod bots;
 pub mod consts;
+pub mod determinism;
 pub mod math;
+pub mod obs;
+pub mod rules;
 pub mod sim;
+pub mod stats;
+pub mod tournament;
 
 mod ext;
 
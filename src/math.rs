@@ -34,6 +34,16 @@ unsafe impl cxx::ExternType for RotMat {
     type Kind = cxx::kind::Trivial;
 }
 
+/// A rotation as a unit quaternion, in the same coordinate system as `RotMat`
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
 #[cxx::bridge(namespace = "RocketSim")]
 mod inner_math {
     unsafe extern "C++" {
@@ -0,0 +1,471 @@
+//! Headless top-down minimap images, drawn on the CPU
+
+use crate::{
+    consts,
+    math::Vec3,
+    sim::{GameMode, Team},
+    CarInfo, GameState, TileState,
+};
+use std::{borrow::Cow, error::Error, fmt, fs::File, io, io::Write, path::Path, time::Duration};
+
+/// Space around the field in each direction, in unreal units
+const MARGIN_X: f32 = 256.;
+/// Space around the field in each direction, in unreal units, which also fits the goals
+const MARGIN_Y: f32 = 1024.;
+/// Distance from the side walls to where the corners of the field are cut off
+const CORNER_CUT: f32 = 1152.;
+const GOAL_HALF_WIDTH: f32 = 892.755;
+const GOAL_DEPTH: f32 = 880.;
+const TILE_BT_TO_UU: f32 = 50.;
+
+const BACKGROUND: u8 = 0;
+const FIELD: u8 = 1;
+const LINE: u8 = 2;
+const BLUE: u8 = 3;
+const ORANGE: u8 = 4;
+const BALL: u8 = 5;
+const PAD_SMALL: u8 = 6;
+const PAD_BIG: u8 = 7;
+const PAD_INACTIVE: u8 = 8;
+const TILE_BLUE: u8 = 9;
+const TILE_ORANGE: u8 = 10;
+const TILE_DAMAGED: u8 = 11;
+const GOAL_BLUE: u8 = 12;
+const GOAL_ORANGE: u8 = 13;
+
+/// The RGB colors of the minimap, indexed by the values of `Minimap::pixels`
+///
+/// It's padded to 16 colors so it can be used as a GIF palette as-is.
+pub const PALETTE: [[u8; 3]; 16] = [
+    [20, 20, 24],
+    [36, 72, 44],
+    [210, 210, 210],
+    [40, 110, 255],
+    [255, 140, 20],
+    [245, 245, 245],
+    [190, 170, 90],
+    [255, 215, 0],
+    [70, 70, 60],
+    [34, 62, 128],
+    [128, 72, 26],
+    [96, 96, 96],
+    [28, 52, 110],
+    [110, 60, 24],
+    [0, 0, 0],
+    [0, 0, 0],
+];
+
+#[derive(Debug)]
+pub enum MinimapError {
+    Io(io::Error),
+    Png(png::EncodingError),
+    Gif(gif::EncodingError),
+}
+
+impl fmt::Display for MinimapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Couldn't write the minimap: {e}"),
+            Self::Png(e) => write!(f, "Couldn't encode the minimap as PNG: {e}"),
+            Self::Gif(e) => write!(f, "Couldn't encode the minimap as GIF: {e}"),
+        }
+    }
+}
+
+impl Error for MinimapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Png(e) => Some(e),
+            Self::Gif(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for MinimapError {
+    #[inline]
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<png::EncodingError> for MinimapError {
+    #[inline]
+    fn from(value: png::EncodingError) -> Self {
+        Self::Png(value)
+    }
+}
+
+impl From<gif::EncodingError> for MinimapError {
+    #[inline]
+    fn from(value: gif::EncodingError) -> Self {
+        Self::Gif(value)
+    }
+}
+
+/// A top-down image of the field, with orange's goal at the top
+///
+/// Pixels are indices into `PALETTE`, so frames can be encoded without quantizing colors.
+#[derive(Clone, Debug)]
+pub struct Minimap {
+    game_mode: GameMode,
+    width: u16,
+    height: u16,
+    /// Pixels per unreal unit
+    scale: f32,
+    extent_x: f32,
+    extent_y: f32,
+    pixels: Vec<u8>,
+}
+
+impl Minimap {
+    #[must_use]
+    /// Creates an empty minimap that's `width` pixels wide, the height follows from the game mode's field
+    pub fn new(game_mode: GameMode, width: u16) -> Self {
+        let (extent_x, extent_y) = Self::field_extents(game_mode);
+        let extent_x = extent_x + MARGIN_X;
+        let extent_y = extent_y + MARGIN_Y;
+
+        let scale = f32::from(width.max(1)) / (extent_x * 2.);
+        let height = (extent_y * 2. * scale).round().clamp(1., f32::from(u16::MAX)) as u16;
+
+        Self {
+            game_mode,
+            width: width.max(1),
+            height,
+            scale,
+            extent_x,
+            extent_y,
+            pixels: vec![BACKGROUND; usize::from(width.max(1)) * usize::from(height)],
+        }
+    }
+
+    const fn field_extents(game_mode: GameMode) -> (f32, f32) {
+        match game_mode {
+            GameMode::Hoops => (consts::ARENA_EXTENT_X_HOOPS, consts::ARENA_EXTENT_Y_HOOPS),
+            _ => (consts::ARENA_EXTENT_X, consts::ARENA_EXTENT_Y),
+        }
+    }
+
+    /// The default big and small pad locations of a game mode
+    const fn static_pads(game_mode: GameMode) -> (&'static [Vec3], &'static [Vec3]) {
+        match game_mode {
+            GameMode::Soccar | GameMode::Heatseeker | GameMode::Snowday => {
+                (&consts::boostpads::LOCS_BIG_SOCCAR, &consts::boostpads::LOCS_SMALL_SOCCAR)
+            }
+            GameMode::Hoops => (&consts::boostpads::LOCS_BIG_HOOPS, &consts::boostpads::LOCS_SMALL_HOOPS),
+            GameMode::Dropshot | GameMode::TheVoid => (&[], &[]),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn game_mode(&self) -> GameMode {
+        self.game_mode
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn width(&self) -> u16 {
+        self.width
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn height(&self) -> u16 {
+        self.height
+    }
+
+    #[inline]
+    #[must_use]
+    /// The pixels row by row from the top left, as indices into `PALETTE`
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    #[must_use]
+    /// The pixels row by row from the top left, as RGB triplets
+    pub fn to_rgb(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|&i| PALETTE[usize::from(i)]).collect()
+    }
+
+    #[inline]
+    #[must_use]
+    /// The pixel coordinates of a world position
+    pub fn to_pixel(&self, pos: Vec3) -> (f32, f32) {
+        ((pos.x + self.extent_x) * self.scale, (self.extent_y - pos.y) * self.scale)
+    }
+
+    /// Redraws the minimap from the state
+    pub fn draw(&mut self, state: &GameState) {
+        self.pixels.fill(BACKGROUND);
+        self.draw_field();
+
+        for (team, tiles) in state.tiles.iter().enumerate() {
+            let full = if team == 0 { TILE_BLUE } else { TILE_ORANGE };
+
+            for tile in tiles {
+                let color = match tile.state {
+                    TileState::Full => full,
+                    TileState::Damaged => TILE_DAMAGED,
+                    TileState::Broken => BACKGROUND,
+                };
+
+                let verts = consts::dropshot::TILE_HEXAGON_VERTS_BT
+                    .map(|v| Vec3::new(tile.pos.x + v.x * TILE_BT_TO_UU, tile.pos.y + v.y * TILE_BT_TO_UU, 0.));
+                self.fill_polygon(&verts, color);
+            }
+        }
+
+        if state.pads.is_empty() {
+            // states that weren't taken from an arena have no pads, so draw the game mode's layout as active
+            let (big, small) = Self::static_pads(state.game_mode);
+            for &pos in big {
+                self.fill_circle(pos, consts::boostpads::CYL_RAD_BIG, PAD_BIG);
+            }
+            for &pos in small {
+                self.fill_circle(pos, consts::boostpads::CYL_RAD_SMALL, PAD_SMALL);
+            }
+        }
+
+        for pad in &state.pads {
+            let (radius, color) = if pad.config.is_big {
+                (consts::boostpads::CYL_RAD_BIG, PAD_BIG)
+            } else {
+                (consts::boostpads::CYL_RAD_SMALL, PAD_SMALL)
+            };
+            let color = if pad.state.is_active { color } else { PAD_INACTIVE };
+
+            self.fill_circle(pad.config.position, radius, color);
+        }
+
+        for car in &state.cars {
+            if !car.state.is_demoed {
+                self.draw_car(car);
+            }
+        }
+
+        let ball_radius = match state.game_mode {
+            GameMode::Hoops => consts::BALL_COLLISION_RADIUS_HOOPS,
+            GameMode::Dropshot => consts::BALL_COLLISION_RADIUS_DROPSHOT,
+            GameMode::Snowday => consts::snowday::PUCK_RADIUS,
+            _ => consts::BALL_COLLISION_RADIUS_SOCCAR,
+        };
+        self.fill_circle(state.ball.pos, ball_radius, BALL);
+    }
+
+    fn draw_field(&mut self) {
+        let (x, y) = Self::field_extents(self.game_mode);
+
+        let outline = match self.game_mode {
+            GameMode::Hoops | GameMode::Dropshot => vec![
+                Vec3::new(-x, -y, 0.),
+                Vec3::new(x, -y, 0.),
+                Vec3::new(x, y, 0.),
+                Vec3::new(-x, y, 0.),
+            ],
+            _ => {
+                let goal_x = GOAL_HALF_WIDTH;
+                let goal_y = y + GOAL_DEPTH;
+
+                self.fill_polygon(
+                    &[
+                        Vec3::new(-goal_x, -goal_y, 0.),
+                        Vec3::new(goal_x, -goal_y, 0.),
+                        Vec3::new(goal_x, -y, 0.),
+                        Vec3::new(-goal_x, -y, 0.),
+                    ],
+                    GOAL_BLUE,
+                );
+                self.fill_polygon(
+                    &[
+                        Vec3::new(-goal_x, y, 0.),
+                        Vec3::new(goal_x, y, 0.),
+                        Vec3::new(goal_x, goal_y, 0.),
+                        Vec3::new(-goal_x, goal_y, 0.),
+                    ],
+                    GOAL_ORANGE,
+                );
+
+                vec![
+                    Vec3::new(-x + CORNER_CUT, -y, 0.),
+                    Vec3::new(x - CORNER_CUT, -y, 0.),
+                    Vec3::new(x, -y + CORNER_CUT, 0.),
+                    Vec3::new(x, y - CORNER_CUT, 0.),
+                    Vec3::new(x - CORNER_CUT, y, 0.),
+                    Vec3::new(-x + CORNER_CUT, y, 0.),
+                    Vec3::new(-x, y - CORNER_CUT, 0.),
+                    Vec3::new(-x, -y + CORNER_CUT, 0.),
+                ]
+            }
+        };
+
+        self.fill_polygon(&outline, FIELD);
+        for (i, &start) in outline.iter().enumerate() {
+            self.draw_line(start, outline[(i + 1) % outline.len()], LINE);
+        }
+        self.draw_line(Vec3::new(-x, 0., 0.), Vec3::new(x, 0., 0.), LINE);
+    }
+
+    fn draw_car(&mut self, car: &CarInfo) {
+        let half_size = Vec3::new(car.config.hitbox_size.x / 2., car.config.hitbox_size.y / 2., 0.);
+        let offset = car.config.hitbox_pos_offset;
+
+        // the height doesn't matter from above
+        let to_world = |x: f32, y: f32| car.state.to_world(Vec3::new(x, y, 0.));
+
+        let corners = [
+            to_world(offset.x + half_size.x, offset.y + half_size.y),
+            to_world(offset.x + half_size.x, offset.y - half_size.y),
+            to_world(offset.x - half_size.x, offset.y - half_size.y),
+            to_world(offset.x - half_size.x, offset.y + half_size.y),
+        ];
+
+        let color = match car.team {
+            Team::Blue => BLUE,
+            Team::Orange => ORANGE,
+        };
+        self.fill_polygon(&corners, color);
+        self.draw_line(to_world(offset.x, offset.y), to_world(offset.x + half_size.x, offset.y), LINE);
+    }
+
+    #[inline]
+    fn set_pixel(&mut self, x: i32, y: i32, color: u8) {
+        if x >= 0 && y >= 0 && x < i32::from(self.width) && y < i32::from(self.height) {
+            self.pixels[y as usize * usize::from(self.width) + x as usize] = color;
+        }
+    }
+
+    /// Fills every pixel whose center is inside the polygon, which may be concave
+    fn fill_polygon(&mut self, verts: &[Vec3], color: u8) {
+        let points: Vec<(f32, f32)> = verts.iter().map(|&v| self.to_pixel(v)).collect();
+
+        let min_y = points.iter().map(|p| p.1).fold(f32::INFINITY, f32::min).max(0.) as i32;
+        let max_y = points
+            .iter()
+            .map(|p| p.1)
+            .fold(f32::NEG_INFINITY, f32::max)
+            .min(f32::from(self.height)) as i32;
+
+        let mut crossings = Vec::new();
+        for y in min_y..=max_y {
+            let center_y = y as f32 + 0.5;
+
+            crossings.clear();
+            for (i, &(x0, y0)) in points.iter().enumerate() {
+                let (x1, y1) = points[(i + 1) % points.len()];
+                if (y0 <= center_y) != (y1 <= center_y) {
+                    crossings.push(x0 + (center_y - y0) / (y1 - y0) * (x1 - x0));
+                }
+            }
+            crossings.sort_unstable_by(f32::total_cmp);
+
+            for span in crossings.chunks_exact(2) {
+                let start = (span[0] - 0.5).ceil() as i32;
+                let end = (span[1] - 0.5).floor() as i32;
+                for x in start..=end {
+                    self.set_pixel(x, y, color);
+                }
+            }
+        }
+    }
+
+    /// Fills a circle in the XY plane, always at least one pixel
+    fn fill_circle(&mut self, center: Vec3, radius: f32, color: u8) {
+        let (cx, cy) = self.to_pixel(center);
+        let radius = (radius * self.scale).max(0.5);
+
+        let min_x = (cx - radius).floor() as i32;
+        let max_x = (cx + radius).ceil() as i32;
+        let min_y = (cy - radius).floor() as i32;
+        let max_y = (cy + radius).ceil() as i32;
+
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let dx = x as f32 + 0.5 - cx;
+                let dy = y as f32 + 0.5 - cy;
+                if dx * dx + dy * dy <= radius * radius {
+                    self.set_pixel(x, y, color);
+                }
+            }
+        }
+
+        self.set_pixel(cx as i32, cy as i32, color);
+    }
+
+    fn draw_line(&mut self, start: Vec3, end: Vec3, color: u8) {
+        let (x0, y0) = self.to_pixel(start);
+        let (x1, y1) = self.to_pixel(end);
+
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.) as i32;
+        for i in 0..=steps {
+            let t = i as f32 / steps as f32;
+            self.set_pixel((x0 + (x1 - x0) * t) as i32, (y0 + (y1 - y0) * t) as i32, color);
+        }
+    }
+
+    fn palette_bytes() -> Vec<u8> {
+        PALETTE.concat()
+    }
+
+    /// Encodes the minimap as an indexed PNG
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), MinimapError> {
+        let mut encoder = png::Encoder::new(writer, u32::from(self.width), u32::from(self.height));
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(Self::palette_bytes());
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), MinimapError> {
+        self.write_png(io::BufWriter::new(File::create(path)?))
+    }
+
+    /// Draws every state as a frame of a looping GIF, showing each frame for `frame_time`
+    ///
+    /// GIF delays are in hundredths of a second, so `frame_time` is rounded to that.
+    /// The minimap is left showing the last state.
+    pub fn write_gif<'a, W: Write, I: IntoIterator<Item = &'a GameState>>(
+        &mut self,
+        writer: W,
+        states: I,
+        frame_time: Duration,
+    ) -> Result<(), MinimapError> {
+        let palette = Self::palette_bytes();
+        let mut encoder = gif::Encoder::new(writer, self.width, self.height, &palette)?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        let delay = (frame_time.as_millis() / 10).clamp(1, u128::from(u16::MAX)) as u16;
+
+        for state in states {
+            self.draw(state);
+
+            let frame = gif::Frame {
+                width: self.width,
+                height: self.height,
+                delay,
+                buffer: Cow::Borrowed(&self.pixels),
+                ..Default::default()
+            };
+            encoder.write_frame(&frame)?;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn save_gif<'a, P: AsRef<Path>, I: IntoIterator<Item = &'a GameState>>(
+        &mut self,
+        path: P,
+        states: I,
+        frame_time: Duration,
+    ) -> Result<(), MinimapError> {
+        self.write_gif(io::BufWriter::new(File::create(path)?), states, frame_time)
+    }
+}
@@ -0,0 +1,345 @@
+use crate::{
+    consts,
+    math::Vec3,
+    sim::{CarState, Team},
+    CarInfo, GameState,
+};
+
+#[cfg(feature = "serde_utils")]
+use serde::{Deserialize, Serialize};
+
+/// Normalization applied to world positions, per axis
+pub const POS_NORM: Vec3 = Vec3::new(
+    1. / consts::ARENA_EXTENT_X,
+    1. / consts::ARENA_EXTENT_Y,
+    1. / consts::ARENA_HEIGHT,
+);
+/// Normalization applied to positions relative to the acting car
+pub const REL_POS_NORM: f32 = 1. / consts::ARENA_EXTENT_Y;
+pub const CAR_VEL_NORM: f32 = 1. / consts::CAR_MAX_SPEED;
+pub const CAR_ANG_VEL_NORM: f32 = 1. / consts::CAR_MAX_ANG_SPEED;
+pub const BALL_VEL_NORM: f32 = 1. / consts::BALL_MAX_SPEED;
+pub const BALL_ANG_VEL_NORM: f32 = 1. / consts::BALL_MAX_ANG_SPEED;
+pub const BOOST_NORM: f32 = 1. / consts::BOOST_MAX;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+/// Which blocks are written into an observation
+pub struct ObsFeatures {
+    pub ball: bool,
+    pub player: bool,
+    pub teammates: bool,
+    pub opponents: bool,
+    pub pads: bool,
+}
+
+impl Default for ObsFeatures {
+    #[inline]
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl ObsFeatures {
+    pub const ALL: Self = Self {
+        ball: true,
+        player: true,
+        teammates: true,
+        opponents: true,
+        pads: true,
+    };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub struct ObsConfig {
+    pub features: ObsFeatures,
+    /// The number of players per team that the observation is padded to
+    ///
+    /// Cars beyond this amount are ignored, missing cars are written as zeros
+    pub max_team_size: usize,
+    /// If the observation of an orange car should be mirrored so that it always looks like it's playing on blue
+    pub mirror_orange: bool,
+}
+
+impl Default for ObsConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            features: ObsFeatures::ALL,
+            max_team_size: 3,
+            mirror_orange: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// Builds normalized observations relative to the acting car
+///
+/// All values are expressed from the perspective of the acting car's team;
+/// when `mirror_orange` is set, orange cars see the field rotated 180° around the z axis
+/// so they always defend the negative y goal.
+/// "Local" values are rotated into the acting car's frame (forward, right, up).
+///
+/// # Layout
+///
+/// The blocks are written in this order, each one only if it's enabled in `ObsFeatures`:
+///
+/// * Ball - 15 values
+///   * position (3), normalized by `POS_NORM`
+///   * velocity (3), normalized by `BALL_VEL_NORM`
+///   * angular velocity (3), normalized by `BALL_ANG_VEL_NORM`
+///   * local position relative to the acting car (3), normalized by `REL_POS_NORM`
+///   * local velocity relative to the acting car (3), normalized by `BALL_VEL_NORM`
+/// * Player - 26 values, the acting car (see "Car block")
+/// * Teammates - `max_team_size - 1` slots of 33 values, ordered by car id
+/// * Opponents - `max_team_size` slots of 33 values, ordered by car id
+/// * Pads - 1 value per boost pad, 1 if the pad is active and 0 otherwise
+///
+/// Car block (26 values):
+/// * position (3), normalized by `POS_NORM`
+/// * forward (3) and up (3) vectors
+/// * velocity (3), normalized by `CAR_VEL_NORM`
+/// * angular velocity (3), normalized by `CAR_ANG_VEL_NORM`
+/// * local velocity (3), normalized by `CAR_VEL_NORM`
+/// * local angular velocity (3), normalized by `CAR_ANG_VEL_NORM`
+/// * boost (1), normalized by `BOOST_NORM`
+/// * is on ground, has flip available, is demoed, is supersonic (4), 1 or 0
+///
+/// Other car slot (33 values):
+/// * present (1), 1 if there is a car in this slot and 0 if it's padding
+/// * car block (26)
+/// * local position relative to the acting car (3), normalized by `REL_POS_NORM`
+/// * local velocity relative to the acting car (3), normalized by `CAR_VEL_NORM`
+///
+/// Pads are ordered so that a mirrored observation lists the mirrored pad in each slot.
+pub struct ObsBuilder {
+    pub config: ObsConfig,
+    pad_mirror: Vec<usize>,
+    pad_positions: Vec<Vec3>,
+}
+
+impl ObsBuilder {
+    pub const BALL_SIZE: usize = 15;
+    pub const CAR_SIZE: usize = 26;
+    pub const OTHER_CAR_SIZE: usize = 1 + Self::CAR_SIZE + 6;
+
+    #[inline]
+    #[must_use]
+    pub const fn new(config: ObsConfig) -> Self {
+        Self {
+            config,
+            pad_mirror: Vec::new(),
+            pad_positions: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    /// The number of values in an observation for a game state with `num_pads` boost pads
+    pub const fn obs_size(&self, num_pads: usize) -> usize {
+        let features = self.config.features;
+        let mut size = 0;
+
+        if features.ball {
+            size += Self::BALL_SIZE;
+        }
+
+        if features.player {
+            size += Self::CAR_SIZE;
+        }
+
+        if features.teammates {
+            size += self.config.max_team_size.saturating_sub(1) * Self::OTHER_CAR_SIZE;
+        }
+
+        if features.opponents {
+            size += self.config.max_team_size * Self::OTHER_CAR_SIZE;
+        }
+
+        if features.pads {
+            size += num_pads;
+        }
+
+        size
+    }
+
+    #[must_use]
+    /// Builds the observation for the car with the given id,
+    /// returns `None` if there's no car with that id in the game state
+    pub fn build(&mut self, state: &GameState, car_id: u32) -> Option<Vec<f32>> {
+        let mut obs = Vec::with_capacity(self.obs_size(state.pads.len()));
+        self.build_into(state, car_id, &mut obs).then_some(obs)
+    }
+
+    /// Appends the observation for the car with the given id to `obs`
+    ///
+    /// Returns false and leaves `obs` untouched if there's no car with that id in the game state
+    pub fn build_into(&mut self, state: &GameState, car_id: u32, obs: &mut Vec<f32>) -> bool {
+        let Some(player) = state.cars.iter().find(|car| car.id == car_id) else {
+            return false;
+        };
+
+        let view = View {
+            mirror: self.config.mirror_orange && player.team == Team::Orange,
+            car: player.state,
+        };
+        let features = self.config.features;
+
+        if features.ball {
+            let pos = view.pos(state.ball.pos);
+            let vel = view.dir(state.ball.vel);
+
+            extend_vec(obs, pos * POS_NORM);
+            extend_vec(obs, vel * BALL_VEL_NORM);
+            extend_vec(obs, view.dir(state.ball.ang_vel) * BALL_ANG_VEL_NORM);
+            extend_vec(obs, view.local_pos(state.ball.pos) * REL_POS_NORM);
+            extend_vec(obs, view.local_dir(state.ball.vel - player.state.vel) * BALL_VEL_NORM);
+        }
+
+        if features.player {
+            view.write_car(obs, &player.state);
+        }
+
+        let max_team_size = self.config.max_team_size;
+
+        if features.teammates {
+            let teammates = state.cars.iter().filter(|car| car.team == player.team && car.id != car_id);
+            view.write_others(obs, teammates, max_team_size.saturating_sub(1));
+        }
+
+        if features.opponents {
+            let opponents = state.cars.iter().filter(|car| car.team != player.team);
+            view.write_others(obs, opponents, max_team_size);
+        }
+
+        if features.pads {
+            if view.mirror {
+                self.update_pad_mirror(state);
+                obs.extend(self.pad_mirror.iter().map(|&i| f32::from(state.pads[i].state.is_active)));
+            } else {
+                obs.extend(state.pads.iter().map(|pad| f32::from(pad.state.is_active)));
+            }
+        }
+
+        true
+    }
+
+    fn update_pad_mirror(&mut self, state: &GameState) {
+        let is_same_layout = self.pad_positions.len() == state.pads.len()
+            && self
+                .pad_positions
+                .iter()
+                .zip(&state.pads)
+                .all(|(&pos, pad)| pos == pad.config.position);
+        if is_same_layout {
+            return;
+        }
+
+        self.pad_positions = state.pads.iter().map(|pad| pad.config.position).collect();
+
+        // pads aren't perfectly symmetrical in every game mode, so pick the closest one
+        self.pad_mirror = state
+            .pads
+            .iter()
+            .map(|pad| {
+                let target = mirror(pad.config.position);
+
+                state
+                    .pads
+                    .iter()
+                    .enumerate()
+                    .map(|(i, other)| (i, target.distance_squared(other.config.position)))
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map(|(i, _)| i)
+                    .unwrap()
+            })
+            .collect();
+    }
+}
+
+struct View {
+    mirror: bool,
+    car: CarState,
+}
+
+impl View {
+    #[inline]
+    fn pos(&self, pos: Vec3) -> Vec3 {
+        if self.mirror {
+            mirror(pos)
+        } else {
+            pos
+        }
+    }
+
+    /// Velocities, angular velocities and orientation vectors mirror the same way as positions
+    #[inline]
+    fn dir(&self, dir: Vec3) -> Vec3 {
+        self.pos(dir)
+    }
+
+    /// Rotates a world direction into the acting car's frame
+    ///
+    /// The local frame is the same whether or not the view is mirrored
+    #[inline]
+    fn local_dir(&self, dir: Vec3) -> Vec3 {
+        let rot_mat = self.car.rot_mat;
+        Vec3::new(dir.dot(rot_mat.forward), dir.dot(rot_mat.right), dir.dot(rot_mat.up))
+    }
+
+    #[inline]
+    fn local_pos(&self, pos: Vec3) -> Vec3 {
+        self.local_dir(pos - self.car.pos)
+    }
+
+    fn write_car(&self, obs: &mut Vec<f32>, car: &CarState) {
+        let vel = self.dir(car.vel);
+        let ang_vel = self.dir(car.ang_vel);
+
+        extend_vec(obs, self.pos(car.pos) * POS_NORM);
+        extend_vec(obs, self.dir(car.rot_mat.forward));
+        extend_vec(obs, self.dir(car.rot_mat.up));
+        extend_vec(obs, vel * CAR_VEL_NORM);
+        extend_vec(obs, ang_vel * CAR_ANG_VEL_NORM);
+
+        let car_view = View {
+            mirror: false,
+            car: *car,
+        };
+        extend_vec(obs, car_view.local_dir(car.vel) * CAR_VEL_NORM);
+        extend_vec(obs, car_view.local_dir(car.ang_vel) * CAR_ANG_VEL_NORM);
+
+        obs.extend([
+            car.boost * BOOST_NORM,
+            f32::from(car.is_on_ground),
+            f32::from(car.has_flip_or_jump()),
+            f32::from(car.is_demoed),
+            f32::from(car.is_supersonic),
+        ]);
+    }
+
+    fn write_others<'a>(&self, obs: &mut Vec<f32>, cars: impl Iterator<Item = &'a CarInfo>, num_slots: usize) {
+        let mut cars = cars.collect::<Vec<_>>();
+        cars.sort_unstable_by_key(|car| car.id);
+
+        for car in cars.iter().take(num_slots) {
+            obs.push(1.);
+            self.write_car(obs, &car.state);
+            extend_vec(obs, self.local_pos(car.state.pos) * REL_POS_NORM);
+            extend_vec(obs, self.local_dir(car.state.vel - self.car.vel) * CAR_VEL_NORM);
+        }
+
+        let num_padding = num_slots.saturating_sub(cars.len());
+        obs.resize(obs.len() + num_padding * ObsBuilder::OTHER_CAR_SIZE, 0.);
+    }
+}
+
+#[inline]
+const fn mirror(vec: Vec3) -> Vec3 {
+    Vec3::new(-vec.x, -vec.y, vec.z)
+}
+
+#[inline]
+fn extend_vec(obs: &mut Vec<f32>, vec: Vec3) {
+    obs.extend([vec.x, vec.y, vec.z]);
+}
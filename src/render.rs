@@ -1,6 +1,11 @@
-use crate::math::Vec3;
+use crate::{
+    math::{RotMat, Vec3},
+    sim::{BallState, Team},
+    CarInfo,
+};
+use std::ops;
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Vec2 {
     pub x: f32,
     pub y: f32,
@@ -8,12 +13,136 @@ pub struct Vec2 {
 
 impl Vec2 {
     pub const ZERO: Self = Self::new(0., 0.);
+    pub const ONE: Self = Self::splat(1.);
+    pub const X: Self = Self::new(1., 0.);
+    pub const Y: Self = Self::new(0., 1.);
 
     #[inline]
     #[must_use]
     pub const fn new(x: f32, y: f32) -> Self {
         Self { x, y }
     }
+
+    #[inline]
+    #[must_use]
+    pub const fn splat(value: f32) -> Self {
+        Self::new(value, value)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    #[inline]
+    #[must_use]
+    /// The z component of the 3D cross product, positive if `other` is counter-clockwise from `self`
+    pub fn perp_dot(self, other: Self) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn length_squared(self) -> f32 {
+        self.dot(self)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn length(self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn distance(self, other: Self) -> f32 {
+        (self - other).length()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Returns the vector with a length of 1, or `Vec2::ZERO` if that's not possible
+    pub fn normalize_or_zero(self) -> Self {
+        let normalized = self / self.length();
+        if normalized.is_finite() {
+            normalized
+        } else {
+            Self::ZERO
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_finite(self) -> bool {
+        self.x.is_finite() && self.y.is_finite()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Linearly interpolates towards `other`, where `t` of 0 is `self` and 1 is `other`
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl ops::Add for Vec2 {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl ops::Sub for Vec2 {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl ops::Mul<f32> for Vec2 {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: f32) -> Self {
+        Self::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl ops::Div<f32> for Vec2 {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: f32) -> Self {
+        Self::new(self.x / rhs, self.y / rhs)
+    }
+}
+
+impl ops::Neg for Vec2 {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y)
+    }
+}
+
+impl ops::AddAssign for Vec2 {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl ops::SubAssign for Vec2 {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -37,6 +166,7 @@ impl Color {
     pub const RED: Self = Self::rgb(1., 0., 0.);
     pub const GREEN: Self = Self::rgb(0., 1., 0.);
     pub const BLUE: Self = Self::rgb(0., 0., 1.);
+    pub const ORANGE: Self = Self::rgb(1., 0.5, 0.);
 
     #[inline]
     #[must_use]
@@ -49,13 +179,103 @@ impl Color {
     pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
         Self { r, g, b, a }
     }
+
+    #[inline]
+    #[must_use]
+    pub const fn team(team: Team) -> Self {
+        match team {
+            Team::Blue => Self::BLUE,
+            Team::Orange => Self::ORANGE,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum Render {
-    Line2D { start: Vec2, end: Vec2, color: Color },
-    Line { start: Vec3, end: Vec3, color: Color },
-    LineStrip { positions: Vec<Vec3>, color: Color },
+    Line2D {
+        start: Vec2,
+        end: Vec2,
+        color: Color,
+    },
+    Line {
+        start: Vec3,
+        end: Vec3,
+        color: Color,
+    },
+    LineStrip {
+        positions: Vec<Vec3>,
+        color: Color,
+    },
+    /// Text on the screen, `pos` being its top left corner
+    Text2D {
+        pos: Vec2,
+        text: String,
+        scale: f32,
+        color: Color,
+    },
+    /// Text at a point in the world that always faces the camera
+    Text3D {
+        pos: Vec3,
+        text: String,
+        scale: f32,
+        color: Color,
+    },
+    Sphere {
+        center: Vec3,
+        radius: f32,
+        color: Color,
+    },
+    /// A box that's aligned with the world axes, `size` being its full length along each axis
+    Box {
+        center: Vec3,
+        size: Vec3,
+        color: Color,
+    },
+    /// A box rotated by `rot_mat`, `size` being its full length along the forward, right and up axes
+    OrientedBox {
+        center: Vec3,
+        size: Vec3,
+        rot_mat: RotMat,
+        color: Color,
+    },
+    /// A rectangle on the screen, `pos` being its top left corner
+    Rect2D {
+        pos: Vec2,
+        size: Vec2,
+        filled: bool,
+        color: Color,
+    },
+    /// A line with an arrow head at `end`
+    Arrow {
+        start: Vec3,
+        end: Vec3,
+        color: Color,
+    },
+}
+
+impl Render {
+    #[must_use]
+    /// The hitbox of the car in the color of its team
+    pub fn car_hitbox(car: &CarInfo) -> Self {
+        // the offset is relative to the car, so it's rotated along with it
+        let center = car.state.to_world(car.config.hitbox_pos_offset);
+
+        Self::OrientedBox {
+            center,
+            size: car.config.hitbox_size,
+            rot_mat: car.state.rot_mat,
+            color: Color::team(car.team),
+        }
+    }
+
+    #[must_use]
+    /// A white line through every position of the ball, like from a ball prediction
+    pub fn ball_path(path: &[BallState]) -> Self {
+        Self::LineStrip {
+            positions: path.iter().map(|ball| ball.pos).collect(),
+            color: Color::WHITE,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -0,0 +1,915 @@
+use crate::{
+    bytes::{FromBytes, FromBytesExact, ToBytes, ToBytesExact, TryFromBytes},
+    rlviser::PacketType,
+    sim::{Arena, ArenaConfig, CarControls},
+    GameState, NoCarFound,
+};
+use core::{fmt, pin::Pin};
+use cxx::UniquePtr;
+use std::{
+    error::Error,
+    fs,
+    io::{self, BufWriter, Write},
+    net::{ToSocketAddrs, UdpSocket},
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The first bytes of every recording
+pub const MAGIC: [u8; 4] = *b"RSRP";
+/// The version of the recording format written by `Recorder`
+pub const VERSION: u16 = 1;
+/// The last bytes of a recording that was finished properly, right after the offset of the keyframe index
+const INDEX_MAGIC: [u8; 4] = *b"RSIX";
+
+const HEADER_NUM_BYTES: usize = MAGIC.len() + u16::NUM_BYTES + u32::NUM_BYTES;
+/// Every record starts with a tag and the length of its payload
+const RECORD_HEADER_NUM_BYTES: usize = 1 + u32::NUM_BYTES;
+const TRAILER_NUM_BYTES: usize = u64::NUM_BYTES + INDEX_MAGIC.len();
+const CONTROLS_NUM_BYTES: usize = u32::NUM_BYTES + CarControls::NUM_BYTES;
+const KEYFRAME_NUM_BYTES: usize = u64::NUM_BYTES * 2;
+
+mod tag {
+    pub const CONTROLS: u8 = 0;
+    pub const STEP: u8 = 1;
+    pub const KEYFRAME: u8 = 2;
+    pub const STATE: u8 = 3;
+    pub const INDEX: u8 = 4;
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    NoCarFound(NoCarFound),
+    /// The file doesn't start with `MAGIC`
+    InvalidMagic,
+    /// The file was written by a newer version of the format
+    UnsupportedVersion(u16),
+    /// The data at the given offset couldn't be read
+    Corrupt {
+        offset: usize,
+    },
+    /// The cars in the recording couldn't be recreated with the same ids
+    CarIdMismatch {
+        expected: u32,
+        found: u32,
+    },
+}
+
+impl Error for ReplayError {}
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "IO error: {e}"),
+            Self::NoCarFound(e) => e.fmt(f),
+            Self::InvalidMagic => write!(f, "Not a RocketSim recording."),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Unsupported recording version {version}, expected {VERSION} or older.")
+            }
+            Self::Corrupt { offset } => write!(f, "Corrupt recording data at offset {offset}."),
+            Self::CarIdMismatch { expected, found } => {
+                write!(f, "Expected to recreate car with ID {expected}, but got ID {found}.")
+            }
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    #[inline]
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<NoCarFound> for ReplayError {
+    #[inline]
+    fn from(e: NoCarFound) -> Self {
+        Self::NoCarFound(e)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Record {
+    /// New controls for the given cars, used until they're changed again
+    Controls(Vec<(u32, CarControls)>),
+    /// The arena was stepped by this many ticks
+    Step(u32),
+    /// The state of the arena at this point, used for seeking and checking for desyncs
+    Keyframe(GameState),
+    /// The arena was changed outside of stepping, like cars being added or a kickoff reset
+    State(GameState),
+}
+
+impl Record {
+    /// Applies the record to an arena, re-simulating what happened when it was recorded
+    pub fn apply(&self, arena: Pin<&mut Arena>) -> Result<(), NoCarFound> {
+        match self {
+            Self::Controls(controls) => arena.set_all_controls(controls),
+            Self::Step(num_ticks) => {
+                arena.step(*num_ticks);
+                Ok(())
+            }
+            Self::Keyframe(_) => Ok(()),
+            Self::State(state) => set_state(arena, state),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A full game state in the recording that playback can start from
+pub struct Keyframe {
+    pub tick_count: u64,
+    /// Where the record starts in the recording
+    pub offset: u64,
+}
+
+impl Keyframe {
+    /// The offset as an index into the recording, out of bounds if it doesn't fit
+    #[inline]
+    fn offset(self) -> usize {
+        usize::try_from(self.offset).unwrap_or(usize::MAX)
+    }
+}
+
+/// Records an arena to a compact binary format
+///
+/// The recording starts with the full state of the arena, followed by every change in controls
+/// and the number of ticks that were stepped. A keyframe with the full game state is written
+/// every `keyframe_interval` ticks so playback can quickly seek to any tick.
+///
+/// The arena must only be stepped through the recorder. If it gets changed any other way,
+/// call `record_state` so the change ends up in the recording.
+pub struct Recorder<W: Write> {
+    writer: W,
+    offset: u64,
+    keyframe_interval: u32,
+    controls: Vec<(u32, CarControls)>,
+    pending_steps: u32,
+    keyframes: Vec<Keyframe>,
+}
+
+impl Recorder<BufWriter<fs::File>> {
+    /// Starts recording the arena to a new file at the given path
+    pub fn create<P: AsRef<Path>>(path: P, arena: Pin<&mut Arena>, keyframe_interval: u32) -> Result<Self, ReplayError> {
+        Self::new(BufWriter::new(fs::File::create(path)?), arena, keyframe_interval)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// Starts recording the arena, writing its current state right away
+    pub fn new(mut writer: W, arena: Pin<&mut Arena>, keyframe_interval: u32) -> Result<Self, ReplayError> {
+        let keyframe_interval = keyframe_interval.max(1);
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_bytes())?;
+        writer.write_all(&keyframe_interval.to_bytes())?;
+
+        let mut recorder = Self {
+            writer,
+            offset: HEADER_NUM_BYTES as u64,
+            keyframe_interval,
+            controls: Vec::new(),
+            pending_steps: 0,
+            keyframes: Vec::new(),
+        };
+
+        recorder.record_state(arena)?;
+        Ok(recorder)
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn keyframe_interval(&self) -> u32 {
+        self.keyframe_interval
+    }
+
+    /// Sets the controls of the given cars, then steps the arena
+    ///
+    /// Only the controls that changed since the last step are written.
+    pub fn step(
+        &mut self,
+        mut arena: Pin<&mut Arena>,
+        controls: &[(u32, CarControls)],
+        num_ticks: u32,
+    ) -> Result<(), ReplayError> {
+        arena.as_mut().set_all_controls(controls)?;
+
+        let mut changed = Vec::new();
+        for &(car_id, car_controls) in controls {
+            match self.controls.iter_mut().find(|(id, _)| *id == car_id) {
+                Some((_, last)) if last.to_bytes() == car_controls.to_bytes() => continue,
+                Some((_, last)) => *last = car_controls,
+                None => self.controls.push((car_id, car_controls)),
+            }
+
+            changed.push((car_id, car_controls));
+        }
+
+        if !changed.is_empty() {
+            self.flush_steps()?;
+
+            let mut payload = Vec::with_capacity(u32::NUM_BYTES + changed.len() * CONTROLS_NUM_BYTES);
+            payload.extend((changed.len() as u32).to_bytes());
+            for (car_id, car_controls) in changed {
+                payload.extend(car_id.to_bytes());
+                payload.extend(car_controls.to_bytes());
+            }
+
+            self.write_record(tag::CONTROLS, &payload)?;
+        }
+
+        for _ in 0..num_ticks {
+            arena.as_mut().step(1);
+            self.pending_steps += 1;
+
+            // `is_multiple_of` needs a newer Rust than this crate otherwise requires
+            #[allow(clippy::manual_is_multiple_of)]
+            let is_keyframe = arena.get_tick_count() % u64::from(self.keyframe_interval) == 0;
+            if is_keyframe {
+                self.flush_steps()?;
+                self.write_state(tag::KEYFRAME, &arena.as_mut().get_game_state())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records the full state of the arena
+    ///
+    /// Must be called after the arena gets changed outside of `step`,
+    /// like after setting the state of a car or resetting to a kickoff
+    pub fn record_state(&mut self, mut arena: Pin<&mut Arena>) -> Result<(), ReplayError> {
+        self.flush_steps()?;
+
+        let cars = arena.get_cars();
+        self.controls.retain(|(car_id, _)| cars.contains(car_id));
+
+        self.write_state(tag::STATE, &arena.as_mut().get_game_state())
+    }
+
+    /// Writes the keyframe index and returns the writer
+    ///
+    /// Recordings that weren't finished can still be played back, but opening them is slower.
+    pub fn finish(mut self) -> Result<W, ReplayError> {
+        self.flush_steps()?;
+
+        let index_offset = self.offset;
+        let mut payload = Vec::with_capacity(u32::NUM_BYTES + self.keyframes.len() * KEYFRAME_NUM_BYTES);
+        payload.extend((self.keyframes.len() as u32).to_bytes());
+        for keyframe in &self.keyframes {
+            payload.extend(keyframe.tick_count.to_bytes());
+            payload.extend(keyframe.offset.to_bytes());
+        }
+
+        self.write_record(tag::INDEX, &payload)?;
+        self.writer.write_all(&index_offset.to_bytes())?;
+        self.writer.write_all(&INDEX_MAGIC)?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
+    fn flush_steps(&mut self) -> Result<(), ReplayError> {
+        if self.pending_steps == 0 {
+            return Ok(());
+        }
+
+        let num_ticks = self.pending_steps;
+        self.pending_steps = 0;
+        self.write_record(tag::STEP, &num_ticks.to_bytes())
+    }
+
+    fn write_state(&mut self, tag: u8, state: &GameState) -> Result<(), ReplayError> {
+        self.keyframes.push(Keyframe {
+            tick_count: state.tick_count,
+            offset: self.offset,
+        });
+
+        let num_bytes = state.num_bytes();
+        self.writer.write_all(&[tag])?;
+        self.writer.write_all(&(num_bytes as u32).to_bytes())?;
+        state.write_into(&mut self.writer)?;
+
+        self.offset += (RECORD_HEADER_NUM_BYTES + num_bytes) as u64;
+        Ok(())
+    }
+
+    fn write_record(&mut self, tag: u8, payload: &[u8]) -> Result<(), ReplayError> {
+        self.writer.write_all(&[tag])?;
+        self.writer.write_all(&(payload.len() as u32).to_bytes())?;
+        self.writer.write_all(payload)?;
+
+        self.offset += (RECORD_HEADER_NUM_BYTES + payload.len()) as u64;
+        Ok(())
+    }
+}
+
+/// A recording made by `Recorder`
+pub struct Replay {
+    bytes: Vec<u8>,
+    version: u16,
+    keyframe_interval: u32,
+    keyframes: Vec<Keyframe>,
+}
+
+impl Replay {
+    /// Reads the recording at the given path
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ReplayError> {
+        Self::new(fs::read(path)?)
+    }
+
+    /// Reads a recording from memory
+    ///
+    /// Uses the keyframe index at the end of the recording if it's there,
+    /// otherwise the keyframes are found by skipping through every record.
+    pub fn new(bytes: Vec<u8>) -> Result<Self, ReplayError> {
+        if bytes.len() < HEADER_NUM_BYTES || bytes[..MAGIC.len()] != MAGIC {
+            return Err(ReplayError::InvalidMagic);
+        }
+
+        let version = u16::from_bytes(&bytes[MAGIC.len()..MAGIC.len() + u16::NUM_BYTES]);
+        if version > VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let keyframe_interval = u32::from_bytes(&bytes[MAGIC.len() + u16::NUM_BYTES..HEADER_NUM_BYTES]);
+
+        let mut replay = Self {
+            bytes,
+            version,
+            keyframe_interval,
+            keyframes: Vec::new(),
+        };
+
+        replay.keyframes = match replay.read_index()? {
+            Some(keyframes) => keyframes,
+            None => replay.scan_keyframes()?,
+        };
+
+        if replay.keyframes.is_empty() {
+            return Err(ReplayError::Corrupt {
+                offset: HEADER_NUM_BYTES,
+            });
+        }
+
+        Ok(replay)
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn version(&self) -> u16 {
+        self.version
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn keyframe_interval(&self) -> u32 {
+        self.keyframe_interval
+    }
+
+    #[inline]
+    #[must_use]
+    /// Every full state in the recording, in order, starting with the initial state
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    #[inline]
+    #[must_use]
+    /// The last keyframe at or before the given tick
+    pub fn keyframe_before(&self, tick_count: u64) -> Keyframe {
+        let i = self.keyframes.partition_point(|keyframe| keyframe.tick_count <= tick_count);
+        self.keyframes[i.saturating_sub(1)]
+    }
+
+    /// The state the recording started with
+    pub fn initial_state(&self) -> Result<GameState, ReplayError> {
+        self.read_state(self.keyframes[0])
+    }
+
+    /// Reads the game state stored in a keyframe
+    pub fn read_state(&self, keyframe: Keyframe) -> Result<GameState, ReplayError> {
+        match self.records_from(keyframe).next() {
+            Some(Ok((_, Record::Keyframe(state) | Record::State(state)))) => Ok(state),
+            Some(Err(e)) => Err(e),
+            _ => Err(ReplayError::Corrupt {
+                offset: keyframe.offset(),
+            }),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Iterates over every record from the start of the recording
+    pub fn records(&self) -> Records<'_> {
+        self.records_from(self.keyframes[0])
+    }
+
+    #[inline]
+    #[must_use]
+    /// Iterates over every record, starting at the given keyframe
+    ///
+    /// A keyframe that's past the end of the recording gives a single `ReplayError::Corrupt`.
+    pub fn records_from(&self, keyframe: Keyframe) -> Records<'_> {
+        Records {
+            bytes: &self.bytes,
+            offset: keyframe.offset(),
+        }
+    }
+
+    /// Creates a new arena with the same cars as the initial state of the recording
+    ///
+    /// Applying every record to this arena re-simulates the recording.
+    pub fn create_arena(&self, config: ArenaConfig) -> Result<UniquePtr<Arena>, ReplayError> {
+        let state = self.initial_state()?;
+
+        let mut arena = Arena::new(state.game_mode, config, state.tick_rate.round() as u8);
+
+        let mut cars = state.cars.clone();
+        cars.sort_by_key(|car| car.id);
+
+        for car in &cars {
+            let car_id = arena.pin_mut().add_car(car.team, &car.config);
+            if car_id != car.id {
+                return Err(ReplayError::CarIdMismatch {
+                    expected: car.id,
+                    found: car_id,
+                });
+            }
+        }
+
+        set_state(arena.pin_mut(), &state)?;
+        Ok(arena)
+    }
+
+    /// The keyframes in the index, `None` if the recording wasn't finished and doesn't have one
+    fn read_index(&self) -> Result<Option<Vec<Keyframe>>, ReplayError> {
+        let len = self.bytes.len();
+        if len < HEADER_NUM_BYTES + TRAILER_NUM_BYTES || self.bytes[len - INDEX_MAGIC.len()..] != INDEX_MAGIC {
+            return Ok(None);
+        }
+
+        let trailer_offset = len - TRAILER_NUM_BYTES;
+        let index_offset = usize::try_from(u64::from_bytes(&self.bytes[trailer_offset..len - INDEX_MAGIC.len()]))
+            .map_err(|_| ReplayError::Corrupt { offset: trailer_offset })?;
+        if index_offset < HEADER_NUM_BYTES || index_offset > trailer_offset {
+            return Err(ReplayError::Corrupt { offset: trailer_offset });
+        }
+
+        let (tag, payload) = read_record(&self.bytes[..trailer_offset], index_offset)?;
+        if tag != tag::INDEX || payload.len() < u32::NUM_BYTES {
+            return Err(ReplayError::Corrupt { offset: index_offset });
+        }
+
+        let num_keyframes = u32::from_bytes(&payload[..u32::NUM_BYTES]) as usize;
+        let expected_len = num_keyframes
+            .checked_mul(KEYFRAME_NUM_BYTES)
+            .and_then(|len| len.checked_add(u32::NUM_BYTES));
+        if expected_len != Some(payload.len()) {
+            return Err(ReplayError::Corrupt { offset: index_offset });
+        }
+
+        let keyframes = payload[u32::NUM_BYTES..]
+            .chunks_exact(KEYFRAME_NUM_BYTES)
+            .map(|chunk| Keyframe {
+                tick_count: u64::from_bytes(&chunk[..u64::NUM_BYTES]),
+                offset: u64::from_bytes(&chunk[u64::NUM_BYTES..]),
+            })
+            .collect::<Vec<_>>();
+
+        // every keyframe has to point at a full state before the index, in the order they were recorded
+        let mut last = None;
+        for keyframe in &keyframes {
+            let offset = keyframe.offset();
+            let is_valid = offset >= HEADER_NUM_BYTES
+                && last.is_none_or(|(tick_count, last_offset)| keyframe.tick_count >= tick_count && offset > last_offset)
+                && matches!(
+                    read_record(&self.bytes[..index_offset], offset),
+                    Ok((tag::KEYFRAME | tag::STATE, payload)) if payload.len() >= GameState::MIN_NUM_BYTES
+                );
+
+            if !is_valid {
+                return Err(ReplayError::Corrupt { offset: index_offset });
+            }
+
+            last = Some((keyframe.tick_count, offset));
+        }
+
+        Ok(Some(keyframes))
+    }
+
+    fn scan_keyframes(&self) -> Result<Vec<Keyframe>, ReplayError> {
+        let mut keyframes = Vec::new();
+        let mut offset = HEADER_NUM_BYTES;
+
+        while offset < self.bytes.len() {
+            let Ok((tag, payload)) = read_record(&self.bytes, offset) else {
+                // the recording was cut off while writing this record
+                break;
+            };
+
+            match tag {
+                tag::KEYFRAME | tag::STATE if payload.len() >= GameState::MIN_NUM_BYTES => {
+                    keyframes.push(Keyframe {
+                        tick_count: GameState::read_tick_count(payload),
+                        offset: offset as u64,
+                    });
+                }
+                tag::INDEX => break,
+                _ => {}
+            }
+
+            offset += RECORD_HEADER_NUM_BYTES + payload.len();
+        }
+
+        Ok(keyframes)
+    }
+}
+
+/// Iterator over the records of a `Replay`, along with the offset of each record
+pub struct Records<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Iterator for Records<'_> {
+    type Item = Result<(u64, Record), ReplayError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.offset == self.bytes.len() {
+                return None;
+            }
+
+            let offset = self.offset;
+            let (tag, payload) = match read_record(self.bytes, offset) {
+                Ok(record) => record,
+                Err(e) => {
+                    self.offset = self.bytes.len();
+                    return Some(Err(e));
+                }
+            };
+
+            self.offset += RECORD_HEADER_NUM_BYTES + payload.len();
+
+            let record = match tag {
+                tag::CONTROLS => read_controls(payload),
+                tag::STEP if payload.len() == u32::NUM_BYTES => Some(Record::Step(u32::from_bytes(payload))),
+                tag::KEYFRAME => read_state(payload).map(Record::Keyframe),
+                tag::STATE => read_state(payload).map(Record::State),
+                tag::INDEX => {
+                    self.offset = self.bytes.len();
+                    return None;
+                }
+                // records from newer versions of the format can be skipped
+                tag if tag > tag::INDEX => continue,
+                _ => None,
+            };
+
+            return Some(
+                record
+                    .map(|record| (offset as u64, record))
+                    .ok_or(ReplayError::Corrupt { offset }),
+            );
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// Something in the re-simulation that didn't match a keyframe
+pub struct Desync {
+    pub tick_count: u64,
+    /// The car that desynced, `None` if it was the ball
+    pub car_id: Option<u32>,
+    /// How far away the object was from where it was recorded
+    pub distance: f32,
+}
+
+/// Plays back a recording by re-simulating it
+///
+/// Seeking loads the closest keyframe before the target tick, then steps forward using the recorded controls.
+/// Every keyframe that gets passed is compared against the re-simulation to check for desyncs.
+pub struct Player {
+    replay: Replay,
+    arena: UniquePtr<Arena>,
+    tick_count: u64,
+    /// Offset of the next record to be read
+    offset: usize,
+    /// Ticks left in the current step record
+    remaining_steps: u32,
+    desyncs: Vec<Desync>,
+    /// Reused for every state sent to rlviser
+    send_buf: Vec<u8>,
+    /// How far a car or the ball can be from its recorded position before it counts as a desync
+    pub desync_tolerance: f32,
+}
+
+impl Player {
+    /// Opens the recording at the given path and loads its initial state
+    pub fn open<P: AsRef<Path>>(path: P, config: ArenaConfig) -> Result<Self, ReplayError> {
+        Self::new(Replay::open(path)?, config)
+    }
+
+    /// Creates an arena for the recording and loads its initial state
+    pub fn new(replay: Replay, config: ArenaConfig) -> Result<Self, ReplayError> {
+        let arena = replay.create_arena(config)?;
+
+        let mut player = Self {
+            replay,
+            arena,
+            tick_count: 0,
+            offset: 0,
+            remaining_steps: 0,
+            desyncs: Vec::new(),
+            send_buf: Vec::new(),
+            desync_tolerance: 1.,
+        };
+
+        player.load_keyframe(player.replay.keyframes[0])?;
+        Ok(player)
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn arena(&self) -> &Arena {
+        &self.arena
+    }
+
+    #[inline]
+    #[must_use]
+    /// The tick of the recording the player is at
+    ///
+    /// This can be different from the tick count of the arena, since seeking doesn't change it.
+    pub const fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+
+    #[inline]
+    #[must_use]
+    /// If there's nothing left to play
+    pub fn is_finished(&self) -> bool {
+        self.remaining_steps == 0 && self.offset >= self.replay.bytes.len()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Every desync found so far
+    pub fn desyncs(&self) -> &[Desync] {
+        &self.desyncs
+    }
+
+    #[inline]
+    pub fn clear_desyncs(&mut self) {
+        self.desyncs.clear();
+    }
+
+    #[inline]
+    #[must_use]
+    /// The current state of the re-simulation, with the tick count of the recording
+    pub fn game_state(&mut self) -> GameState {
+        let mut state = self.arena.pin_mut().get_game_state();
+        state.tick_count = self.tick_count;
+        state
+    }
+
+    /// Jumps to the given tick, returning the tick that was actually reached
+    ///
+    /// Stops early if the recording ends before the given tick
+    pub fn seek(&mut self, tick_count: u64) -> Result<u64, ReplayError> {
+        let keyframe = self.replay.keyframe_before(tick_count);
+
+        // only load the keyframe if stepping forward from here would be slower
+        if tick_count < self.tick_count || keyframe.tick_count > self.tick_count {
+            self.load_keyframe(keyframe)?;
+        }
+
+        while self.tick_count < tick_count {
+            let num_ticks = u32::try_from(tick_count - self.tick_count).unwrap_or(u32::MAX);
+            if self.step(num_ticks)? == 0 {
+                break;
+            }
+        }
+
+        Ok(self.tick_count)
+    }
+
+    /// Steps the re-simulation forward, returning how many ticks were actually stepped
+    pub fn step(&mut self, num_ticks: u32) -> Result<u32, ReplayError> {
+        let mut stepped = 0;
+
+        loop {
+            // everything up to the next step record happened at the current tick
+            while self.remaining_steps == 0 {
+                let Some(record) = self.next_record()? else {
+                    return Ok(stepped);
+                };
+
+                self.apply(record)?;
+            }
+
+            if stepped == num_ticks {
+                return Ok(stepped);
+            }
+
+            let ticks = self.remaining_steps.min(num_ticks - stepped);
+            self.arena.pin_mut().step(ticks);
+            self.remaining_steps -= ticks;
+            self.tick_count += u64::from(ticks);
+            stepped += ticks;
+        }
+    }
+
+    #[inline]
+    /// Iterates over the state after every tick until the recording ends
+    pub fn states(&mut self) -> States<'_> {
+        States { player: self }
+    }
+
+    /// Sends the current state to rlviser, in the layout of `GameState::to_bytes_legacy`
+    pub fn send_to_rlviser<A: ToSocketAddrs>(&mut self, socket: &UdpSocket, addr: A) -> io::Result<()> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to send to"))?;
+
+        socket.send_to(&[PacketType::GameState as u8], addr)?;
+        let state = self.game_state();
+        self.send_buf.clear();
+        state.write_into_legacy(&mut self.send_buf)?;
+        socket.send_to(&self.send_buf, addr)?;
+
+        Ok(())
+    }
+
+    /// Plays the rest of the recording in rlviser at the given speed, 1 being real time
+    pub fn play_in_rlviser<A: ToSocketAddrs>(&mut self, socket: &UdpSocket, addr: A, speed: f32) -> Result<(), ReplayError> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to send to"))?;
+
+        let interval = Duration::from_secs_f32(1. / (self.arena.get_tick_rate() * speed.max(f32::EPSILON)));
+        let mut next_time = Instant::now();
+
+        self.send_to_rlviser(socket, addr)?;
+        while self.step(1)? != 0 {
+            next_time += interval;
+            let now = Instant::now();
+            if next_time > now {
+                thread::sleep(next_time - now);
+            }
+
+            self.send_to_rlviser(socket, addr)?;
+        }
+
+        Ok(())
+    }
+
+    fn load_keyframe(&mut self, keyframe: Keyframe) -> Result<(), ReplayError> {
+        let state = self.replay.read_state(keyframe)?;
+
+        self.apply_state(&state)?;
+        self.offset = keyframe.offset();
+        self.remaining_steps = 0;
+
+        // skip over the keyframe itself
+        let _ = self.next_record()?;
+
+        // the controls at the time of the keyframe are the ones that were last used
+        for car in &state.cars {
+            self.arena.pin_mut().set_car_controls(car.id, car.state.last_controls)?;
+        }
+
+        self.step(0)?;
+        Ok(())
+    }
+
+    fn next_record(&mut self) -> Result<Option<Record>, ReplayError> {
+        let mut records = Records {
+            bytes: &self.replay.bytes,
+            offset: self.offset,
+        };
+
+        let record = records.next().transpose()?.map(|(_, record)| record);
+        self.offset = records.offset;
+
+        Ok(record)
+    }
+
+    fn apply(&mut self, record: Record) -> Result<(), ReplayError> {
+        match record {
+            Record::Step(num_ticks) => self.remaining_steps = num_ticks,
+            Record::Keyframe(state) => self.check_desync(&state),
+            Record::State(state) => self.apply_state(&state)?,
+            Record::Controls(_) => record.apply(self.arena.pin_mut())?,
+        }
+
+        Ok(())
+    }
+
+    fn apply_state(&mut self, state: &GameState) -> Result<(), ReplayError> {
+        set_state(self.arena.pin_mut(), state)?;
+        self.tick_count = state.tick_count;
+
+        Ok(())
+    }
+
+    fn check_desync(&mut self, state: &GameState) {
+        let ball = self.arena.pin_mut().get_ball();
+        let distance = ball.pos.distance(state.ball.pos);
+        if distance > self.desync_tolerance {
+            self.desyncs.push(Desync {
+                tick_count: state.tick_count,
+                car_id: None,
+                distance,
+            });
+        }
+
+        for car in &state.cars {
+            let distance = self.arena.pin_mut().get_car(car.id).pos.distance(car.state.pos);
+            if distance > self.desync_tolerance {
+                self.desyncs.push(Desync {
+                    tick_count: state.tick_count,
+                    car_id: Some(car.id),
+                    distance,
+                });
+            }
+        }
+    }
+}
+
+/// Iterator over the state of a `Player` after every tick
+pub struct States<'a> {
+    player: &'a mut Player,
+}
+
+impl Iterator for States<'_> {
+    type Item = Result<GameState, ReplayError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.player.step(1) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(self.player.game_state())),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+fn read_record(bytes: &[u8], offset: usize) -> Result<(u8, &[u8]), ReplayError> {
+    let corrupt = || ReplayError::Corrupt { offset };
+
+    let start = offset.checked_add(RECORD_HEADER_NUM_BYTES).ok_or_else(corrupt)?;
+    let header = bytes.get(offset..start).ok_or_else(corrupt)?;
+
+    let len = u32::from_bytes(&header[1..]) as usize;
+    let end = start.checked_add(len).ok_or_else(corrupt)?;
+    let payload = bytes.get(start..end).ok_or_else(corrupt)?;
+
+    Ok((header[0], payload))
+}
+
+fn read_controls(payload: &[u8]) -> Option<Record> {
+    if payload.len() < u32::NUM_BYTES {
+        return None;
+    }
+
+    let num_controls = u32::from_bytes(&payload[..u32::NUM_BYTES]) as usize;
+    let expected_len = num_controls
+        .checked_mul(CONTROLS_NUM_BYTES)
+        .and_then(|len| len.checked_add(u32::NUM_BYTES));
+    if expected_len != Some(payload.len()) {
+        return None;
+    }
+
+    Some(Record::Controls(
+        payload[u32::NUM_BYTES..]
+            .chunks_exact(CONTROLS_NUM_BYTES)
+            .map(|chunk| {
+                (
+                    u32::from_bytes(&chunk[..u32::NUM_BYTES]),
+                    CarControls::from_bytes(&chunk[u32::NUM_BYTES..]),
+                )
+            })
+            .collect(),
+    ))
+}
+
+fn read_state(payload: &[u8]) -> Option<GameState> {
+    GameState::try_from_bytes(payload).ok()
+}
+
+/// Sets the game state along with the mutators it was recorded with
+fn set_state(mut arena: Pin<&mut Arena>, state: &GameState) -> Result<(), NoCarFound> {
+    if let Some(mutator_config) = state.mutator_config {
+        arena.as_mut().set_mutator_config(mutator_config);
+    }
+
+    arena.set_game_state(state)
+}
@@ -0,0 +1,512 @@
+use crate::{
+    bytes::{check_max_len, DecodeError, FromBytesExact, ToBytes, TryFromBytes},
+    render::{Render, RenderMessage},
+    sim::Arena,
+    GameState,
+};
+use core::{fmt, pin::Pin};
+use std::{
+    error::Error,
+    io, mem,
+    net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// The port rlviser listens on by default, its first argument
+pub const RLVISER_PORT: u16 = 45243;
+/// The port rlviser sends to by default, its second argument
+pub const ROCKETSIM_PORT: u16 = 34254;
+
+/// How long to wait for the payload of a packet after its type was received
+const PAYLOAD_TIMEOUT: Duration = Duration::from_millis(100);
+/// The most bytes a UDP packet can carry over IPv4, a longer payload can't have been sent
+const MAX_PAYLOAD_NUM_BYTES: usize = 65507;
+
+/// Every packet is sent as its type, followed by a separate payload for some types
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketType {
+    Quit,
+    GameState,
+    Connection,
+    Paused,
+    Speed,
+    Render,
+}
+
+impl TryFrom<u8> for PacketType {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Quit),
+            1 => Ok(Self::GameState),
+            2 => Ok(Self::Connection),
+            3 => Ok(Self::Paused),
+            4 => Ok(Self::Speed),
+            5 => Ok(Self::Render),
+            _ => Err(value),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum RlviserEvent {
+    /// rlviser answered the connection request
+    Connected(SocketAddr),
+    /// rlviser was paused or unpaused
+    Paused(bool),
+    /// The speed rlviser wants the simulation to run at, 1 being real time
+    Speed(f32),
+    /// The state was changed in rlviser, like by moving the ball
+    SetGameState(Box<GameState>),
+    /// rlviser was closed
+    Quit,
+}
+
+#[derive(Debug)]
+pub enum RlviserError {
+    Io(io::Error),
+    /// The payload of a packet couldn't be decoded, the packet was dropped
+    Decode(DecodeError),
+    /// A packet with a type that doesn't exist, or that rlviser doesn't send
+    UnexpectedPacket(u8),
+}
+
+impl Error for RlviserError {}
+impl fmt::Display for RlviserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "IO error: {e}"),
+            Self::Decode(e) => e.fmt(f),
+            Self::UnexpectedPacket(packet_type) => write!(f, "Unexpected packet of type {packet_type} from rlviser."),
+        }
+    }
+}
+
+impl From<io::Error> for RlviserError {
+    #[inline]
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<DecodeError> for RlviserError {
+    #[inline]
+    fn from(e: DecodeError) -> Self {
+        Self::Decode(e)
+    }
+}
+
+/// Game states are sent in the layout rlviser reads, see `GameState::to_bytes_legacy`
+struct LegacyGameState<'a>(&'a GameState);
+
+impl ToBytes for LegacyGameState<'_> {
+    #[inline]
+    fn num_bytes(&self) -> usize {
+        self.0.num_bytes_legacy()
+    }
+
+    #[inline]
+    fn write_into<W: io::Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        self.0.write_into_legacy(writer)
+    }
+}
+
+fn send_packet<T: ToBytes>(
+    socket: &UdpSocket,
+    rlviser_addr: SocketAddr,
+    packet_type: PacketType,
+    payload: &T,
+    buf: &mut Vec<u8>,
+) -> io::Result<()> {
+    buf.clear();
+    payload.encode_into(buf);
+
+    socket.send_to(&[packet_type as u8], rlviser_addr)?;
+    socket.send_to(buf, rlviser_addr)?;
+
+    Ok(())
+}
+
+/// Shared between a connection and its render groups
+#[derive(Debug, Default)]
+struct RenderGroups {
+    last_id: i32,
+    /// Groups that get removed once the tick count reaches the given tick
+    expiring: Vec<(i32, u64)>,
+    /// The tick count of the last game state that was sent
+    tick_count: u64,
+}
+
+/// A connection to rlviser over UDP
+///
+/// Nothing ever blocks for long, so it can be used from inside a game loop:
+/// call `update` before stepping the arena, then send the new state with `send_game_state`.
+pub struct RlviserConnection {
+    socket: UdpSocket,
+    rlviser_addr: SocketAddr,
+    send_buf: Vec<u8>,
+    recv_buf: Vec<u8>,
+    paused: bool,
+    speed: f32,
+    sync_cars: bool,
+    render_groups: Arc<Mutex<RenderGroups>>,
+}
+
+impl RlviserConnection {
+    /// Connects to rlviser running on this machine with its default ports
+    pub fn new() -> io::Result<Self> {
+        Self::with_addrs((Ipv4Addr::UNSPECIFIED, ROCKETSIM_PORT), (Ipv4Addr::LOCALHOST, RLVISER_PORT))
+    }
+
+    /// Listens on `local_addr` and connects to rlviser at `rlviser_addr`
+    ///
+    /// rlviser answers with `Connected`, followed by the speed and paused state it wants.
+    pub fn with_addrs<A: ToSocketAddrs, B: ToSocketAddrs>(local_addr: A, rlviser_addr: B) -> io::Result<Self> {
+        let rlviser_addr = rlviser_addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to"))?;
+
+        let socket = UdpSocket::bind(local_addr)?;
+        socket.set_nonblocking(true)?;
+        socket.set_read_timeout(Some(PAYLOAD_TIMEOUT))?;
+        socket.send_to(&[PacketType::Connection as u8], rlviser_addr)?;
+
+        Ok(Self {
+            socket,
+            rlviser_addr,
+            send_buf: Vec::new(),
+            recv_buf: Vec::new(),
+            paused: false,
+            speed: 1.,
+            sync_cars: false,
+            render_groups: Arc::default(),
+        })
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn rlviser_addr(&self) -> SocketAddr {
+        self.rlviser_addr
+    }
+
+    #[inline]
+    #[must_use]
+    /// If rlviser was last paused
+    pub const fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    #[inline]
+    #[must_use]
+    /// The last speed rlviser asked for, 1 being real time
+    pub const fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    #[inline]
+    #[must_use]
+    /// How long to wait between ticks to run at the speed rlviser asked for
+    pub fn tick_interval(&self, tick_rate: f32) -> Duration {
+        Duration::from_secs_f32(1. / (tick_rate * self.speed).max(f32::EPSILON))
+    }
+
+    #[inline]
+    /// Makes `update` add and remove cars so the arena has exactly the cars in the state from rlviser,
+    /// using `Arena::sync_game_state` instead of `Arena::set_game_state`
+    ///
+    /// Off by default.
+    pub fn set_sync_cars(&mut self, sync_cars: bool) {
+        self.sync_cars = sync_cars;
+    }
+
+    /// Sends the state, then removes the render groups whose time to live ran out
+    ///
+    /// The state is sent in the layout of `GameState::to_bytes_legacy`, since that's what rlviser reads
+    pub fn send_game_state(&mut self, state: &GameState) -> io::Result<()> {
+        send_packet(
+            &self.socket,
+            self.rlviser_addr,
+            PacketType::GameState,
+            &LegacyGameState(state),
+            &mut self.send_buf,
+        )?;
+
+        let expired = {
+            let mut groups = self.render_groups.lock().unwrap();
+            groups.tick_count = state.tick_count;
+
+            let (expired, expiring) = groups
+                .expiring
+                .iter()
+                .partition::<Vec<_>, _>(|(_, until)| *until <= state.tick_count);
+            groups.expiring = expiring;
+            expired
+        };
+
+        for (id, _) in expired {
+            self.send_render(&RenderMessage::RemoveRender(id))?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends a raw render message
+    ///
+    /// The ids of render groups are allocated counting up from 1, so don't use those here.
+    pub fn send_render(&mut self, message: &RenderMessage) -> io::Result<()> {
+        send_packet(
+            &self.socket,
+            self.rlviser_addr,
+            PacketType::Render,
+            message,
+            &mut self.send_buf,
+        )
+    }
+
+    /// Creates an empty render group with a new id
+    pub fn render_group(&self) -> io::Result<RenderGroup> {
+        let id = {
+            let mut groups = self.render_groups.lock().unwrap();
+            groups.last_id += 1;
+            groups.last_id
+        };
+
+        Ok(RenderGroup {
+            id,
+            socket: self.socket.try_clone()?,
+            rlviser_addr: self.rlviser_addr,
+            shared: self.render_groups.clone(),
+            renders: Vec::new(),
+            ttl: None,
+            is_sent: false,
+            send_buf: Vec::new(),
+        })
+    }
+
+    /// Tells rlviser to close
+    pub fn send_quit(&self) -> io::Result<()> {
+        self.socket.send_to(&[PacketType::Quit as u8], self.rlviser_addr)?;
+        Ok(())
+    }
+
+    /// Reads the payload that comes after a packet type, waiting for it if it hasn't arrived yet
+    fn recv_payload(&mut self, num_bytes: usize, peek: bool) -> io::Result<usize> {
+        self.recv_buf.resize(num_bytes, 0);
+
+        self.socket.set_nonblocking(false)?;
+        let result = if peek {
+            self.socket.peek_from(&mut self.recv_buf)
+        } else {
+            self.socket.recv_from(&mut self.recv_buf)
+        };
+        self.socket.set_nonblocking(true)?;
+
+        Ok(result?.0)
+    }
+
+    fn recv_game_state(&mut self) -> Result<GameState, RlviserError> {
+        let num_read = match self.recv_payload(GameState::MIN_NUM_BYTES, true) {
+            Ok(num_read) => num_read,
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => return Err(e.into()),
+            // some platforms return an error when the packet is bigger than the buffer, but still fill it
+            Err(_) => GameState::MIN_NUM_BYTES,
+        };
+
+        let num_bytes = match GameState::try_get_num_bytes(&self.recv_buf[..num_read])
+            .and_then(|num_bytes| check_max_len(num_bytes, MAX_PAYLOAD_NUM_BYTES))
+        {
+            Ok(num_bytes) => num_bytes,
+            Err(e) => {
+                // drop the malformed packet
+                let _ = self.recv_payload(0, false);
+                return Err(e.into());
+            }
+        };
+
+        let num_read = self.recv_payload(num_bytes, false)?;
+        Ok(GameState::try_from_bytes(&self.recv_buf[..num_read])?)
+    }
+
+    /// Receives the next event from rlviser, `None` if there aren't any waiting
+    ///
+    /// If an error is returned the packet was dropped, and the next event can still be received.
+    pub fn recv_event(&mut self) -> Result<Option<RlviserEvent>, RlviserError> {
+        let mut packet_type = [0];
+        let src = match self.socket.recv_from(&mut packet_type) {
+            Ok((_, src)) => src,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let event = match PacketType::try_from(packet_type[0]) {
+            Ok(PacketType::Quit) => RlviserEvent::Quit,
+            Ok(PacketType::Connection) => RlviserEvent::Connected(src),
+            Ok(PacketType::GameState) => RlviserEvent::SetGameState(Box::new(self.recv_game_state()?)),
+            Ok(PacketType::Paused) => {
+                let num_read = self.recv_payload(1, false)?;
+                let paused = bool::try_from_bytes(&self.recv_buf[..num_read])?;
+
+                self.paused = paused;
+                RlviserEvent::Paused(paused)
+            }
+            Ok(PacketType::Speed) => {
+                let num_read = self.recv_payload(f32::NUM_BYTES, false)?;
+                let speed = f32::try_from_bytes(&self.recv_buf[..num_read])?;
+
+                self.speed = speed;
+                RlviserEvent::Speed(speed)
+            }
+            Ok(PacketType::Render) | Err(_) => return Err(RlviserError::UnexpectedPacket(packet_type[0])),
+        };
+
+        Ok(Some(event))
+    }
+
+    /// Handles every event that's waiting, changing the state of the arena when rlviser asks to
+    ///
+    /// The state is applied with `Arena::set_game_state`, so it's skipped if it has cars the arena doesn't,
+    /// unless `set_sync_cars` was turned on.
+    /// Packets that can't be decoded are skipped, use `recv_event` to handle them yourself.
+    pub fn update(&mut self, mut arena: Pin<&mut Arena>) -> io::Result<Vec<RlviserEvent>> {
+        let mut events = Vec::new();
+
+        loop {
+            match self.recv_event() {
+                Ok(Some(event)) => {
+                    if let RlviserEvent::SetGameState(state) = &event {
+                        if self.sync_cars {
+                            arena.as_mut().sync_game_state(state);
+                        } else {
+                            // the event is still returned, so a state with other cars can be handled by the caller
+                            let _ = arena.as_mut().set_game_state(state);
+                        }
+                    }
+
+                    events.push(event);
+                }
+                Ok(None) => return Ok(events),
+                // the payload of a packet never arrived
+                Err(RlviserError::Io(e)) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {}
+                Err(RlviserError::Io(e)) => return Err(e),
+                Err(RlviserError::Decode(_) | RlviserError::UnexpectedPacket(_)) => {}
+            }
+        }
+    }
+}
+
+/// Renders that are shown in rlviser together, created with `RlviserConnection::render_group`
+///
+/// Primitives are batched with `push` and shown all at once with `send`.
+/// The renders are removed from rlviser when the group is dropped.
+pub struct RenderGroup {
+    id: i32,
+    socket: UdpSocket,
+    rlviser_addr: SocketAddr,
+    shared: Arc<Mutex<RenderGroups>>,
+    renders: Vec<Render>,
+    ttl: Option<u64>,
+    is_sent: bool,
+    send_buf: Vec<u8>,
+}
+
+impl RenderGroup {
+    #[inline]
+    #[must_use]
+    pub const fn id(&self) -> i32 {
+        self.id
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn renders(&self) -> &[Render] {
+        &self.renders
+    }
+
+    #[inline]
+    #[must_use]
+    /// Removes the renders from rlviser once this many ticks have passed since they were sent
+    ///
+    /// Ticks are counted using the game states sent with `RlviserConnection::send_game_state`.
+    pub const fn with_ttl(mut self, ticks: u64) -> Self {
+        self.ttl = Some(ticks);
+        self
+    }
+
+    #[inline]
+    /// Changes the time to live of the renders, used the next time they're sent
+    pub fn set_ttl(&mut self, ticks: Option<u64>) {
+        self.ttl = ticks;
+    }
+
+    #[inline]
+    pub fn push(&mut self, render: Render) -> &mut Self {
+        self.renders.push(render);
+        self
+    }
+
+    #[inline]
+    pub fn extend<I: IntoIterator<Item = Render>>(&mut self, renders: I) -> &mut Self {
+        self.renders.extend(renders);
+        self
+    }
+
+    #[inline]
+    /// Removes every render from the batch, but not from rlviser
+    pub fn clear(&mut self) {
+        self.renders.clear();
+    }
+
+    /// Shows the renders in rlviser, replacing what the group showed before
+    pub fn send(&mut self) -> io::Result<()> {
+        let message = RenderMessage::AddRender(self.id, mem::take(&mut self.renders));
+        let result = send_packet(
+            &self.socket,
+            self.rlviser_addr,
+            PacketType::Render,
+            &message,
+            &mut self.send_buf,
+        );
+
+        if let RenderMessage::AddRender(_, renders) = message {
+            self.renders = renders;
+        }
+        result?;
+
+        let mut groups = self.shared.lock().unwrap();
+        groups.expiring.retain(|(id, _)| *id != self.id);
+        if let Some(ttl) = self.ttl {
+            let until = groups.tick_count + ttl;
+            groups.expiring.push((self.id, until));
+        }
+
+        self.is_sent = true;
+        Ok(())
+    }
+
+    /// Removes the renders from rlviser, they can be shown again with `send`
+    pub fn remove(&mut self) -> io::Result<()> {
+        self.shared.lock().unwrap().expiring.retain(|(id, _)| *id != self.id);
+        self.is_sent = false;
+
+        send_packet(
+            &self.socket,
+            self.rlviser_addr,
+            PacketType::Render,
+            &RenderMessage::RemoveRender(self.id),
+            &mut self.send_buf,
+        )
+    }
+}
+
+impl Drop for RenderGroup {
+    fn drop(&mut self) {
+        if self.is_sent {
+            // there's nothing to do if rlviser can't be reached anymore
+            let _ = self.remove();
+        }
+    }
+}
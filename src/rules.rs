@@ -0,0 +1,369 @@
+use crate::{
+    sim::{Arena, Team},
+    stats::{StatEvent, StatTracker},
+};
+use core::pin::Pin;
+use cxx::UniquePtr;
+
+#[cfg(feature = "serde_utils")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub struct MatchConfig {
+    /// Length of regulation time, in seconds
+    pub game_length: f32,
+    /// How long cars are frozen before each kickoff, in seconds
+    pub kickoff_countdown: f32,
+    /// How long the game waits after a goal before resetting to the next kickoff, in seconds
+    pub goal_reset_delay: f32,
+    /// If a tied game should go to overtime, otherwise it ends in a draw
+    pub overtime: bool,
+    /// If the game clock should never run out
+    pub unlimited_time: bool,
+    /// The seed used for the kickoff positions, `None` for a random seed every kickoff
+    pub kickoff_seed: Option<u32>,
+}
+
+impl Default for MatchConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            game_length: 300.,
+            kickoff_countdown: 3.,
+            goal_reset_delay: 3.,
+            overtime: true,
+            unlimited_time: false,
+            kickoff_seed: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub enum MatchPhase {
+    /// Cars are frozen while the kickoff countdown runs
+    Countdown { ticks_left: u64 },
+    /// The ball is in play
+    Active,
+    /// A goal was just scored, waiting before the next kickoff
+    GoalScored { team: Team, ticks_left: u64 },
+    /// The match is over
+    Ended { winner: Option<Team> },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub enum MatchEvent {
+    /// Cars have been reset to kickoff positions and the countdown started
+    KickoffCountdown,
+    /// The countdown is over and the ball is in play
+    KickoffStarted,
+    /// A goal was scored by the given team
+    Goal { team: Team },
+    /// Regulation time ran out and the ball hit the ground
+    TimeExpired,
+    /// The score was tied when time ran out
+    OvertimeStarted,
+    /// The given team forfeited
+    Forfeit { team: Team },
+    /// The match is over, `None` if it ended in a draw
+    Ended { winner: Option<Team> },
+    /// A stat was awarded, only sent if stats are being tracked
+    Stat(StatEvent),
+}
+
+/// Runs an `Arena` under Rocket League's match rules
+///
+/// Handles the game clock, score, kickoff countdown, post-goal delay, overtime and forfeits.
+/// Regulation time only ends once the ball touches the ground, just like in Rocket League.
+///
+/// `Match` checks for goals itself after every tick,
+/// so the arena must not have a goal scored callback that resets the ball.
+pub struct Match {
+    arena: UniquePtr<Arena>,
+    config: MatchConfig,
+    phase: MatchPhase,
+    score: [u16; 2],
+    clock_ticks: u64,
+    overtime_ticks: u64,
+    is_overtime: bool,
+    stats: Option<StatTracker>,
+}
+
+impl Match {
+    /// Takes ownership of the arena and starts the first kickoff countdown
+    pub fn new(arena: UniquePtr<Arena>, config: MatchConfig) -> Self {
+        let mut game = Self {
+            clock_ticks: secs_to_ticks(config.game_length, arena.get_tick_rate()),
+            arena,
+            config,
+            phase: MatchPhase::Active,
+            score: [0; 2],
+            overtime_ticks: 0,
+            is_overtime: false,
+            stats: None,
+        };
+
+        game.reset_kickoff();
+        game
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn arena(&self) -> &Arena {
+        &self.arena
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn arena_mut(&mut self) -> Pin<&mut Arena> {
+        self.arena.pin_mut()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Gives back ownership of the arena
+    pub fn into_arena(self) -> UniquePtr<Arena> {
+        self.arena
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn config(&self) -> &MatchConfig {
+        &self.config
+    }
+
+    /// Starts tracking player stats, does nothing if they're already being tracked
+    ///
+    /// This sets the arena's car bump callback, see `StatTracker::attach`
+    pub fn track_stats(&mut self) {
+        if self.stats.is_none() {
+            StatTracker::attach(self.arena.pin_mut());
+            self.stats = Some(StatTracker::new());
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// The player stats, if they're being tracked
+    pub const fn stats(&self) -> Option<&StatTracker> {
+        self.stats.as_ref()
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn phase(&self) -> MatchPhase {
+        self.phase
+    }
+
+    #[inline]
+    #[must_use]
+    /// The score as `[blue, orange]`
+    pub const fn score(&self) -> [u16; 2] {
+        self.score
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn is_overtime(&self) -> bool {
+        self.is_overtime
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn is_ended(&self) -> bool {
+        matches!(self.phase, MatchPhase::Ended { .. })
+    }
+
+    #[inline]
+    #[must_use]
+    /// The winner of the match, `None` if it hasn't ended or ended in a draw
+    pub const fn winner(&self) -> Option<Team> {
+        match self.phase {
+            MatchPhase::Ended { winner } => winner,
+            _ => None,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Seconds left in regulation time
+    pub fn time_remaining(&self) -> f32 {
+        self.clock_ticks as f32 / self.arena.get_tick_rate()
+    }
+
+    #[inline]
+    #[must_use]
+    /// Seconds played in overtime
+    pub fn overtime_elapsed(&self) -> f32 {
+        self.overtime_ticks as f32 / self.arena.get_tick_rate()
+    }
+
+    #[inline]
+    #[must_use]
+    /// If cars currently can't move, either because of the kickoff countdown or the post-goal delay
+    pub const fn is_frozen(&self) -> bool {
+        !matches!(self.phase, MatchPhase::Active)
+    }
+
+    /// Ends the match with the given team losing
+    ///
+    /// Returns no events if the match had already ended
+    pub fn forfeit(&mut self, team: Team) -> Vec<MatchEvent> {
+        if self.is_ended() {
+            return Vec::new();
+        }
+
+        let winner = Some(other_team(team));
+        self.phase = MatchPhase::Ended { winner };
+
+        vec![MatchEvent::Forfeit { team }, MatchEvent::Ended { winner }]
+    }
+
+    /// Steps the match by the given number of ticks, returning everything that happened
+    ///
+    /// The arena isn't stepped while cars are frozen or after the match has ended
+    pub fn step(&mut self, num_ticks: u32) -> Vec<MatchEvent> {
+        let mut events = Vec::new();
+
+        for _ in 0..num_ticks {
+            if self.is_ended() {
+                break;
+            }
+
+            self.step_tick(&mut events);
+        }
+
+        events
+    }
+
+    fn step_tick(&mut self, events: &mut Vec<MatchEvent>) {
+        match self.phase {
+            MatchPhase::Countdown { ticks_left } => {
+                if ticks_left > 1 {
+                    self.phase = MatchPhase::Countdown {
+                        ticks_left: ticks_left - 1,
+                    };
+                } else {
+                    self.phase = MatchPhase::Active;
+                    events.push(MatchEvent::KickoffStarted);
+                }
+            }
+            MatchPhase::GoalScored { team, ticks_left } => {
+                if ticks_left > 1 {
+                    self.phase = MatchPhase::GoalScored {
+                        team,
+                        ticks_left: ticks_left - 1,
+                    };
+                } else if self.clock_ticks == 0 && !self.is_overtime && !self.config.unlimited_time {
+                    // a goal was scored as time ran out
+                    self.end_regulation(events);
+                } else {
+                    self.reset_kickoff();
+                    events.push(MatchEvent::KickoffCountdown);
+                }
+            }
+            MatchPhase::Active => self.step_active(events),
+            MatchPhase::Ended { .. } => {}
+        }
+    }
+
+    fn step_active(&mut self, events: &mut Vec<MatchEvent>) {
+        self.arena.pin_mut().step(1);
+
+        if let Some(stats) = &mut self.stats {
+            events.extend(stats.update(self.arena.pin_mut()).into_iter().map(MatchEvent::Stat));
+        }
+
+        if self.is_overtime {
+            self.overtime_ticks += 1;
+        } else if !self.config.unlimited_time {
+            self.clock_ticks = self.clock_ticks.saturating_sub(1);
+        }
+
+        if self.arena.is_ball_scored() {
+            let ball = self.arena.pin_mut().get_ball();
+            // the ball is in the net of the team that got scored on
+            let team = if ball.pos.y > 0. { Team::Blue } else { Team::Orange };
+            self.score[team as usize] += 1;
+            events.push(MatchEvent::Goal { team });
+
+            if self.is_overtime {
+                self.end(events);
+            } else {
+                self.phase = MatchPhase::GoalScored {
+                    team,
+                    ticks_left: self.secs_to_ticks(self.config.goal_reset_delay).max(1),
+                };
+            }
+
+            return;
+        }
+
+        if self.clock_ticks == 0 && !self.is_overtime && !self.config.unlimited_time && self.is_ball_on_ground() {
+            self.end_regulation(events);
+        }
+    }
+
+    fn end_regulation(&mut self, events: &mut Vec<MatchEvent>) {
+        events.push(MatchEvent::TimeExpired);
+
+        if self.score[0] == self.score[1] && self.config.overtime {
+            self.is_overtime = true;
+            events.push(MatchEvent::OvertimeStarted);
+            self.reset_kickoff();
+            events.push(MatchEvent::KickoffCountdown);
+        } else {
+            self.end(events);
+        }
+    }
+
+    fn end(&mut self, events: &mut Vec<MatchEvent>) {
+        let winner = match self.score[0].cmp(&self.score[1]) {
+            core::cmp::Ordering::Greater => Some(Team::Blue),
+            core::cmp::Ordering::Less => Some(Team::Orange),
+            core::cmp::Ordering::Equal => None,
+        };
+
+        self.phase = MatchPhase::Ended { winner };
+        events.push(MatchEvent::Ended { winner });
+    }
+
+    fn reset_kickoff(&mut self) {
+        self.arena.pin_mut().reset_to_random_kickoff(self.config.kickoff_seed);
+
+        let ticks_left = self.secs_to_ticks(self.config.kickoff_countdown);
+        self.phase = if ticks_left == 0 {
+            MatchPhase::Active
+        } else {
+            MatchPhase::Countdown { ticks_left }
+        };
+    }
+
+    fn is_ball_on_ground(&mut self) -> bool {
+        // allow a bit of leeway for the ball sinking into the floor
+        const GROUND_MARGIN: f32 = 5.;
+
+        let radius = self.arena.get_ball_radius();
+        self.arena.pin_mut().get_ball().pos.z <= radius + GROUND_MARGIN
+    }
+
+    #[inline]
+    fn secs_to_ticks(&self, secs: f32) -> u64 {
+        secs_to_ticks(secs, self.arena.get_tick_rate())
+    }
+}
+
+#[inline]
+fn secs_to_ticks(secs: f32, tick_rate: f32) -> u64 {
+    (secs * tick_rate).round().max(0.) as u64
+}
+
+#[inline]
+const fn other_team(team: Team) -> Team {
+    match team {
+        Team::Blue => Team::Orange,
+        Team::Orange => Team::Blue,
+    }
+}
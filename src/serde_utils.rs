@@ -5,11 +5,11 @@ pub use serde;
 use crate::{
     math::{RotMat, Vec3},
     sim::{
-        BallHitInfo, BallState, BoostPadConfig, BoostPadState, CarConfig, CarContact, CarControls, CarState, DropshotInfo,
-        HeatseekerInfo, WheelPairConfig, WorldContact,
+        ArenaConfig, ArenaMemWeightMode, BallHitInfo, BallState, BoostPadConfig, BoostPadState, CarConfig, CarContact,
+        CarControls, CarState, DemoMode, DropshotInfo, HeatseekerInfo, MutatorConfig, WheelPairConfig, WorldContact,
     },
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(Serialize, Deserialize)]
 #[serde(remote = "BallHitInfo")]
@@ -139,3 +139,82 @@ pub struct BallStateDerive {
     hs_info: HeatseekerInfo,
     ds_info: DropshotInfo,
 }
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "MutatorConfig")]
+pub struct MutatorConfigDerive {
+    gravity: Vec3,
+    car_mass: f32,
+    car_world_friction: f32,
+    car_world_restitution: f32,
+    ball_mass: f32,
+    ball_max_speed: f32,
+    ball_drag: f32,
+    ball_world_friction: f32,
+    ball_world_restitution: f32,
+    jump_accel: f32,
+    jump_immediate_force: f32,
+    boost_accel_ground: f32,
+    boost_accel_air: f32,
+    boost_used_per_second: f32,
+    respawn_delay: f32,
+    bump_cooldown_time: f32,
+    boost_pad_cooldown_big: f32,
+    boost_pad_cooldown_small: f32,
+    car_spawn_boost_amount: f32,
+    ball_hit_extra_force_scale: f32,
+    bump_force_scale: f32,
+    ball_radius: f32,
+    unlimited_flips: bool,
+    unlimited_double_jumps: bool,
+    recharge_boost_enabled: bool,
+    recharge_boost_per_second: f32,
+    recharge_boost_delay: f32,
+    demo_mode: DemoMode,
+    enable_team_demos: bool,
+    goal_base_threshold_y: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "ArenaConfig")]
+pub struct ArenaConfigDerive {
+    mem_weight_mode: ArenaMemWeightMode,
+    min_pos: Vec3,
+    max_pos: Vec3,
+    max_aabb_len: f32,
+    no_ball_rot: bool,
+    use_custom_broadphase: bool,
+    max_objects: u32,
+}
+
+/// For `Option<MutatorConfig>` fields, use with `#[serde(with = "option_mutator_config")]`
+pub mod option_mutator_config {
+    use super::{Deserialize, Deserializer, MutatorConfig, MutatorConfigDerive, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "MutatorConfigDerive")] MutatorConfig);
+
+    pub fn serialize<S: Serializer>(value: &Option<MutatorConfig>, serializer: S) -> Result<S::Ok, S::Error> {
+        value.map(Wrapper).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<MutatorConfig>, D::Error> {
+        Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(value)| value))
+    }
+}
+
+/// For `Option<ArenaConfig>` fields, use with `#[serde(with = "option_arena_config")]`
+pub mod option_arena_config {
+    use super::{ArenaConfig, ArenaConfigDerive, Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "ArenaConfigDerive")] ArenaConfig);
+
+    pub fn serialize<S: Serializer>(value: &Option<ArenaConfig>, serializer: S) -> Result<S::Ok, S::Error> {
+        value.map(Wrapper).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<ArenaConfig>, D::Error> {
+        Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(value)| value))
+    }
+}
@@ -45,6 +45,18 @@ mod base {
         type BoostPadConfig = crate::sim::BoostPadConfig;
         #[namespace = "RocketSim"]
         type DropshotTilesState = crate::sim::DropshotTilesState;
+        #[cxx_name = "EArenaConfig"]
+        type ArenaConfig = crate::sim::ArenaConfig;
+        #[cxx_name = "ECarWheels"]
+        type CarWheels = crate::sim::CarWheels;
+
+        /// A copy of everything needed to put an arena back to the way it was, see `Arena::snapshot`
+        type ArenaSnapshot;
+
+        #[must_use]
+        #[doc(hidden)]
+        #[rust_name = "rstr"]
+        fn Restore(self: Pin<&mut Arena>, snapshot: &ArenaSnapshot) -> u32;
 
         #[must_use]
         #[doc(hidden)]
@@ -61,6 +73,11 @@ mod base {
         #[rust_name = "rmvc"]
         fn RemoveCar(self: Pin<&mut Arena>, car_id: u32) -> bool;
 
+        #[must_use]
+        #[doc(hidden)]
+        #[rust_name = "acwi"]
+        fn AddCarWithId(self: Pin<&mut Arena>, car_id: u32, team: Team, car_config: &CarConfig) -> bool;
+
         #[doc(hidden)]
         #[rust_name = "rtrk"]
         fn ResetToRandomKickoff(self: Pin<&mut Arena>, seed: i32);
@@ -70,6 +87,11 @@ mod base {
         #[rust_name = "dc"]
         fn DemolishCar(self: Pin<&mut Arena>, car_id: u32) -> bool;
 
+        #[must_use]
+        #[doc(hidden)]
+        #[rust_name = "gcw"]
+        fn GetCarWheels(self: &Arena, car_id: u32, wheels: &mut CarWheels) -> bool;
+
         #[must_use]
         #[doc(hidden)]
         #[rust_name = "rspc"]
@@ -161,6 +183,11 @@ mod base {
         #[cxx_name = "SetMutatorConfig"]
         fn set_mutator_config(self: Pin<&mut Arena>, config: MutatorConfig);
 
+        /// Returns the config the arena was created with
+        #[must_use]
+        #[cxx_name = "GetArenaConfig"]
+        fn get_arena_config(self: &Arena) -> ArenaConfig;
+
         /// Deep clone the arena, optionally copying the callbacks
         ///
         /// If `copy_callbacks` is true, the callbacks will be copied,
@@ -169,6 +196,16 @@ mod base {
         #[cxx_name = "Clone"]
         fn clone(self: &Arena, copy_callbacks: bool) -> UniquePtr<Arena>;
 
+        /// Captures the full state of the arena, including Bullet's internal state,
+        /// so it can be put back exactly with `restore`
+        #[must_use]
+        #[cxx_name = "Snapshot"]
+        fn snapshot(self: &Arena) -> UniquePtr<ArenaSnapshot>;
+
+        /// Same as `snapshot`, but reuses an existing snapshot to avoid allocating
+        #[cxx_name = "SnapshotInto"]
+        fn snapshot_into(self: &Arena, snapshot: Pin<&mut ArenaSnapshot>);
+
         /// Returns the number of cars in the arena
         #[must_use]
         #[cxx_name = "NumCars"]
@@ -188,6 +225,10 @@ mod base {
         #[cxx_name = "ResetTickCount"]
         fn reset_tick_count(self: Pin<&mut Arena>);
 
+        /// Sets the tick count
+        #[cxx_name = "SetTickCount"]
+        fn set_tick_count(self: Pin<&mut Arena>, ticks: u64);
+
         /// Returns the tick count
         #[must_use]
         #[cxx_name = "GetTickCount"]
@@ -218,4 +259,4 @@ mod base {
 
 unsafe impl Send for Arena {}
 
-pub use base::Arena;
+pub use base::{Arena, ArenaSnapshot};
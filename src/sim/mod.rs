@@ -8,8 +8,9 @@ mod car_controls;
 mod car_state;
 mod dropshot_tiles;
 mod mutator_config;
+mod wheel_info;
 
-pub use arena::{Arena, GameMode};
+pub use arena::{Arena, ArenaSnapshot, GameMode};
 pub use arena_config::{ArenaConfig, ArenaMemWeightMode};
 pub use ball_hit_info::BallHitInfo;
 pub use ball_state::{BallState, DropshotInfo, HeatseekerInfo};
@@ -19,3 +20,4 @@ pub use car_controls::CarControls;
 pub use car_state::{CarContact, CarState, Team, WorldContact};
 pub use dropshot_tiles::{DropshotTileState, DropshotTilesState, GetTilePos};
 pub use mutator_config::{DemoMode, MutatorConfig};
+pub use wheel_info::{CarWheels, WheelInfo};
@@ -0,0 +1,41 @@
+#[cxx::bridge]
+mod wheelinfo {
+    unsafe extern "C++" {
+        include!("arenar.h");
+
+        #[rust_name = "Vec3"]
+        #[namespace = "RocketSim"]
+        type Vec = crate::math::Vec3;
+
+        type EWheelInfo;
+        type ECarWheels;
+    }
+
+    #[derive(Clone, Copy, Debug, Default)]
+    struct EWheelInfo {
+        /// If the wheel's suspension ray hit something last tick
+        is_in_contact: bool,
+        /// Where the wheel touches the ground, in world space
+        contact_point: Vec3,
+        /// The normal of the surface at the contact point
+        contact_normal: Vec3,
+        /// How long the suspension currently is
+        suspension_length: f32,
+        /// How long the suspension is when it's not compressed
+        suspension_rest_length: f32,
+        radius: f32,
+        /// The max friction between the wheel and the ground
+        friction_slip: f32,
+        /// Steering angle in radians
+        steering: f32,
+        /// 1 when the wheel has full grip, less than that when it's skidding
+        skid_info: f32,
+    }
+
+    #[derive(Clone, Copy, Debug, Default)]
+    struct ECarWheels {
+        wheels: [EWheelInfo; 4],
+    }
+}
+
+pub use wheelinfo::{ECarWheels as CarWheels, EWheelInfo as WheelInfo};
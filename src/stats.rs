@@ -0,0 +1,426 @@
+use crate::{
+    consts,
+    sim::{Arena, Team},
+    CarInfo,
+};
+use core::pin::Pin;
+use std::sync::Mutex;
+
+#[cfg(feature = "serde_utils")]
+use serde::{Deserialize, Serialize};
+
+/// Points awarded for each stat, same as the in-game scoreboard
+pub mod points {
+    pub const GOAL: u32 = 100;
+    pub const ASSIST: u32 = 50;
+    pub const SAVE: u32 = 50;
+    /// Replaces the points of a normal save
+    pub const EPIC_SAVE: u32 = 75;
+    pub const SHOT: u32 = 20;
+    pub const CLEAR: u32 = 20;
+    pub const CENTER: u32 = 10;
+    pub const DEMOLITION: u32 = 25;
+}
+
+/// Max time between the assisting touch and the scorer's touch, in seconds
+pub const ASSIST_MAX_TIME: f32 = 5.;
+/// How close the ball must be to the goal line for a save to be an epic save
+pub const EPIC_SAVE_DIST: f32 = 300.;
+/// Min speed the ball must leave the defensive third with for a touch to be a clear
+pub const CLEAR_MIN_SPEED: f32 = 500.;
+/// Min distance from the middle of the field for a touch to be a center
+pub const CENTER_MIN_X: f32 = 1500.;
+
+/// A third of the field in the y direction, used for clears and centers
+const FIELD_THIRD_Y: f32 = consts::ARENA_EXTENT_Y / 3.;
+
+/// Demos reported by the bump callback set in `StatTracker::attach`, as `(arena, bumper, victim)`
+static DEMOS: Mutex<Vec<(usize, u32, u32)>> = Mutex::new(Vec::new());
+
+fn arena_id(arena: &Arena) -> usize {
+    core::ptr::from_ref(arena) as usize
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub struct PlayerStats {
+    /// Total points, as shown on the scoreboard
+    pub score: u32,
+    pub goals: u16,
+    /// Rocket League tracks this stat in secret and doesn't show it on the scoreboard
+    pub own_goals: u16,
+    pub assists: u16,
+    pub shots: u16,
+    /// Includes epic saves
+    pub saves: u16,
+    pub epic_saves: u16,
+    pub clears: u16,
+    pub centers: u16,
+    pub demolitions: u16,
+    /// The number of times this car was demolished
+    pub demolished: u16,
+    pub touches: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub struct CarStats {
+    pub id: u32,
+    pub team: Team,
+    pub stats: PlayerStats,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub enum StatKind {
+    Goal,
+    OwnGoal,
+    Assist,
+    Shot,
+    Save,
+    EpicSave,
+    Clear,
+    Center,
+    Demolition,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+/// A stat that was awarded to a car
+pub struct StatEvent {
+    pub car_id: u32,
+    pub kind: StatKind,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Touch {
+    car_id: u32,
+    team: Team,
+    tick: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+/// Tracks per-car stats using the official scoring rules
+///
+/// Call `attach` once and `update` after every step of the arena, or attach it to a `Match` with `Match::track_stats`.
+///
+/// Goals are detected when the ball is scored, so if the arena has a goal scored callback
+/// that resets the ball, call `on_goal` from there instead.
+pub struct StatTracker {
+    cars: Vec<CarStats>,
+    /// Touches since the last kickoff, oldest first
+    touches: Vec<Touch>,
+    last_touch_ticks: Vec<(u32, u64)>,
+    demoed: Vec<u32>,
+    /// The team whose net the ball was heading into after the last update
+    going_in: Option<Team>,
+    /// The touch that was last awarded a shot
+    last_shot: Option<(u32, u64)>,
+    was_scored: bool,
+    score: [u16; 2],
+}
+
+impl StatTracker {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn stats(&self) -> &[CarStats] {
+        &self.cars
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn get(&self, car_id: u32) -> Option<&PlayerStats> {
+        self.cars.iter().find(|car| car.id == car_id).map(|car| &car.stats)
+    }
+
+    #[inline]
+    #[must_use]
+    /// The goals counted by this tracker as `[blue, orange]`
+    pub const fn score(&self) -> [u16; 2] {
+        self.score
+    }
+
+    /// Resets all stats back to zero
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Sets the arena's car bump callback so `update` can credit demolitions to the car that did the bump
+    ///
+    /// This replaces any bump callback the arena already had
+    pub fn attach(arena: Pin<&mut Arena>) {
+        let id = arena_id(&arena);
+        DEMOS.lock().unwrap().retain(|demo| demo.0 != id);
+
+        arena.set_car_bump_callback(
+            |_, bumper, victim, is_demo, id| {
+                if is_demo {
+                    DEMOS.lock().unwrap().push((id, bumper, victim));
+                }
+            },
+            id,
+        );
+    }
+
+    /// Checks for new touches, demos and goals
+    ///
+    /// Should be called after every step of the arena
+    pub fn update(&mut self, mut arena: Pin<&mut Arena>) -> Vec<StatEvent> {
+        let mut events = Vec::new();
+        let cars = arena.as_mut().get_car_infos();
+        self.add_cars(&cars);
+
+        self.check_demos(arena_id(&arena), &cars, &mut events);
+
+        let new_touches = self.new_touches(&cars);
+        for touch in &new_touches {
+            self.add_touch(*touch);
+        }
+
+        let is_scored = arena.is_ball_scored();
+        if is_scored && !self.was_scored {
+            let ball = arena.as_mut().get_ball();
+            let team = if ball.pos.y > 0. { Team::Blue } else { Team::Orange };
+            self.goal(team, arena.get_tick_rate(), &mut events);
+        }
+        self.was_scored = is_scored;
+
+        if is_scored {
+            return events;
+        }
+
+        let ball = arena.as_mut().get_ball();
+        let going_in = if arena.is_ball_probably_going_in(None, None) {
+            Some(if ball.vel.y > 0. { Team::Orange } else { Team::Blue })
+        } else {
+            None
+        };
+
+        // only the latest touch decides where the ball is going
+        if let Some(&touch) = new_touches.last() {
+            let own_goal_dir = if touch.team == Team::Blue { -1. } else { 1. };
+
+            if going_in.is_some_and(|team| team != touch.team) {
+                self.award(touch.car_id, StatKind::Shot, &mut events);
+                self.last_shot = Some((touch.car_id, touch.tick));
+            } else if self.going_in == Some(touch.team) && going_in.is_none() {
+                let dist_to_goal = consts::ARENA_EXTENT_Y - ball.pos.y * own_goal_dir;
+                let kind = if dist_to_goal < EPIC_SAVE_DIST {
+                    StatKind::EpicSave
+                } else {
+                    StatKind::Save
+                };
+                self.award(touch.car_id, kind, &mut events);
+            } else if ball.pos.y * own_goal_dir > FIELD_THIRD_Y && ball.vel.y * -own_goal_dir > CLEAR_MIN_SPEED {
+                self.award(touch.car_id, StatKind::Clear, &mut events);
+            } else if ball.pos.y * -own_goal_dir > FIELD_THIRD_Y
+                && ball.pos.x.abs() > CENTER_MIN_X
+                && ball.vel.x * ball.pos.x < 0.
+            {
+                self.award(touch.car_id, StatKind::Center, &mut events);
+            }
+        }
+
+        self.going_in = going_in;
+        events
+    }
+
+    /// Awards the goal, assist and own goal stats for a goal scored by the given team
+    ///
+    /// Only needs to be called manually if the ball gets reset before `update` can see that it was scored,
+    /// this must be called before the ball is reset
+    pub fn on_goal(&mut self, mut arena: Pin<&mut Arena>, team: Team) -> Vec<StatEvent> {
+        let mut events = Vec::new();
+
+        // pick up any touch that happened since the last update
+        let cars = arena.as_mut().get_car_infos();
+        self.add_cars(&cars);
+
+        for touch in self.new_touches(&cars) {
+            self.add_touch(touch);
+        }
+
+        self.goal(team, arena.get_tick_rate(), &mut events);
+        events
+    }
+
+    /// Awards the demolition stat, for use in the arena's car bump callback
+    ///
+    /// Not needed when using `update` on an arena passed to `attach`
+    pub fn on_demo(&mut self, bumper: u32, victim: u32) -> Vec<StatEvent> {
+        let mut events = Vec::new();
+        self.award(bumper, StatKind::Demolition, &mut events);
+
+        if let Some(car) = self.cars.iter_mut().find(|car| car.id == victim) {
+            car.stats.demolished += 1;
+        }
+
+        events
+    }
+
+    fn add_cars(&mut self, cars: &[CarInfo]) {
+        for car in cars {
+            if !self.cars.iter().any(|stats| stats.id == car.id) {
+                self.cars.push(CarStats {
+                    id: car.id,
+                    team: car.team,
+                    stats: PlayerStats::default(),
+                });
+            }
+        }
+    }
+
+    fn check_demos(&mut self, arena_id: usize, cars: &[CarInfo], events: &mut Vec<StatEvent>) {
+        let mut bumpers = Vec::new();
+        DEMOS.lock().unwrap().retain(|&(id, bumper, _)| {
+            if id == arena_id {
+                bumpers.push(bumper);
+            }
+
+            id != arena_id
+        });
+
+        for bumper in bumpers {
+            self.award(bumper, StatKind::Demolition, events);
+        }
+
+        for car in cars {
+            let was_demoed = self.demoed.contains(&car.id);
+
+            if car.state.is_demoed && !was_demoed {
+                self.demoed.push(car.id);
+
+                if let Some(victim) = self.cars.iter_mut().find(|stats| stats.id == car.id) {
+                    victim.stats.demolished += 1;
+                }
+            } else if !car.state.is_demoed && was_demoed {
+                self.demoed.retain(|&id| id != car.id);
+            }
+        }
+    }
+
+    /// Touches that haven't been seen yet, oldest first
+    fn new_touches(&self, cars: &[CarInfo]) -> Vec<Touch> {
+        let mut touches = cars
+            .iter()
+            .filter(|car| car.state.ball_hit_info.is_valid)
+            .map(|car| Touch {
+                car_id: car.id,
+                team: car.team,
+                tick: car.state.ball_hit_info.tick_count_when_hit,
+            })
+            .filter(|touch| {
+                self.last_touch_ticks
+                    .iter()
+                    .find(|(id, _)| *id == touch.car_id)
+                    .is_none_or(|(_, tick)| *tick != touch.tick)
+            })
+            .collect::<Vec<_>>();
+
+        touches.sort_by_key(|touch| touch.tick);
+        touches
+    }
+
+    fn add_touch(&mut self, touch: Touch) {
+        if let Some(stats) = self.cars.iter_mut().find(|car| car.id == touch.car_id) {
+            stats.stats.touches += 1;
+        }
+
+        match self.last_touch_ticks.iter_mut().find(|(id, _)| *id == touch.car_id) {
+            Some((_, tick)) => *tick = touch.tick,
+            None => self.last_touch_ticks.push((touch.car_id, touch.tick)),
+        }
+
+        self.touches.push(touch);
+    }
+
+    fn goal(&mut self, team: Team, tick_rate: f32, events: &mut Vec<StatEvent>) {
+        self.score[team as usize] += 1;
+
+        let team_touches = self
+            .touches
+            .iter()
+            .filter(|touch| touch.team == team)
+            .copied()
+            .collect::<Vec<_>>();
+
+        if let Some(scorer) = team_touches.last().copied() {
+            self.award(scorer.car_id, StatKind::Goal, events);
+
+            // every goal counts as a shot, even if it wasn't predicted to go in
+            if self.last_shot != Some((scorer.car_id, scorer.tick)) {
+                self.award(scorer.car_id, StatKind::Shot, events);
+            }
+
+            if let Some(assist) = team_touches.iter().rev().find(|touch| touch.car_id != scorer.car_id) {
+                if scorer.tick.saturating_sub(assist.tick) as f32 / tick_rate < ASSIST_MAX_TIME {
+                    self.award(assist.car_id, StatKind::Assist, events);
+                }
+            }
+        }
+
+        if let Some(last_touch) = self.touches.last().copied() {
+            // the last touch was from the team that got scored on
+            if last_touch.team != team {
+                self.award(last_touch.car_id, StatKind::OwnGoal, events);
+            }
+        }
+
+        // the next kickoff starts with a clean slate
+        self.touches.clear();
+        self.going_in = None;
+        self.last_shot = None;
+    }
+
+    fn award(&mut self, car_id: u32, kind: StatKind, events: &mut Vec<StatEvent>) {
+        let Some(car) = self.cars.iter_mut().find(|car| car.id == car_id) else {
+            return;
+        };
+
+        let stats = &mut car.stats;
+        match kind {
+            StatKind::Goal => {
+                stats.goals += 1;
+                stats.score += points::GOAL;
+            }
+            StatKind::OwnGoal => stats.own_goals += 1,
+            StatKind::Assist => {
+                stats.assists += 1;
+                stats.score += points::ASSIST;
+            }
+            StatKind::Shot => {
+                stats.shots += 1;
+                stats.score += points::SHOT;
+            }
+            StatKind::Save => {
+                stats.saves += 1;
+                stats.score += points::SAVE;
+            }
+            StatKind::EpicSave => {
+                stats.saves += 1;
+                stats.epic_saves += 1;
+                stats.score += points::EPIC_SAVE;
+            }
+            StatKind::Clear => {
+                stats.clears += 1;
+                stats.score += points::CLEAR;
+            }
+            StatKind::Center => {
+                stats.centers += 1;
+                stats.score += points::CENTER;
+            }
+            StatKind::Demolition => {
+                stats.demolitions += 1;
+                stats.score += points::DEMOLITION;
+            }
+        }
+
+        events.push(StatEvent { car_id, kind });
+    }
+}
@@ -0,0 +1,456 @@
+use crate::{
+    agent::{Agent, MatchResult, MatchRunner, RunnerConfig},
+    sim::{CarConfig, Team},
+};
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+#[cfg(feature = "serde_utils")]
+use serde::{Deserialize, Serialize};
+
+/// The rating every new contestant starts with
+pub const DEFAULT_RATING: f32 = 1000.;
+/// How much a single game can move a rating
+pub const DEFAULT_K_FACTOR: f32 = 32.;
+
+type AgentFactory = Arc<dyn Fn() -> Box<dyn Agent> + Send + Sync>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub enum Schedule {
+    /// Every contestant plays every other contestant
+    RoundRobin,
+    /// The contestant at the given index plays every other contestant
+    Gauntlet { challenger: usize },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TournamentConfig {
+    /// Used for every match, this is also where the game mode is chosen
+    pub runner: RunnerConfig,
+    /// The car every agent drives
+    pub car_config: CarConfig,
+    /// How many cars each contestant controls in a match
+    pub team_size: usize,
+    /// How many matches each pairing plays, contestants switch sides every match
+    pub games_per_pairing: usize,
+    pub schedule: Schedule,
+    /// How many arenas are simulated at the same time, each on its own thread
+    pub num_threads: usize,
+}
+
+impl Default for TournamentConfig {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            runner: RunnerConfig::default(),
+            car_config: *CarConfig::octane(),
+            team_size: 1,
+            games_per_pairing: 2,
+            schedule: Schedule::RoundRobin,
+            num_threads: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub struct Standing {
+    pub name: String,
+    pub rating: f32,
+    pub games: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    pub goals_for: u32,
+    pub goals_against: u32,
+}
+
+impl Standing {
+    #[inline]
+    #[must_use]
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            rating: DEFAULT_RATING,
+            games: 0,
+            wins: 0,
+            losses: 0,
+            draws: 0,
+            goals_for: 0,
+            goals_against: 0,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    /// Draws count as half a win
+    pub fn win_rate(&self) -> f32 {
+        if self.games == 0 {
+            return 0.;
+        }
+
+        (self.wins as f32 + self.draws as f32 / 2.) / self.games as f32
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn goal_diff(&self) -> i64 {
+        self.goals_for as i64 - self.goals_against as i64
+    }
+}
+
+/// Elo ratings and results of every contestant that has played
+///
+/// Can be saved to and loaded from disk so ratings carry over between tournaments.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub struct Leaderboard {
+    pub k_factor: f32,
+    standings: Vec<Standing>,
+}
+
+impl Default for Leaderboard {
+    #[inline]
+    fn default() -> Self {
+        Self::new(DEFAULT_K_FACTOR)
+    }
+}
+
+impl Leaderboard {
+    #[inline]
+    #[must_use]
+    pub const fn new(k_factor: f32) -> Self {
+        Self {
+            k_factor,
+            standings: Vec::new(),
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn standings(&self) -> &[Standing] {
+        &self.standings
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Standing> {
+        self.standings.iter().find(|standing| standing.name == name)
+    }
+
+    /// Gets the index of the standing with the given name, adding it if it doesn't exist yet
+    fn index_of(&mut self, name: &str) -> usize {
+        if let Some(i) = self.standings.iter().position(|standing| standing.name == name) {
+            return i;
+        }
+
+        self.standings.push(Standing::new(name.to_string()));
+        self.standings.len() - 1
+    }
+
+    /// Sorts the standings by rating, highest first
+    pub fn sort(&mut self) {
+        self.standings.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+    }
+
+    /// Updates the ratings and results using the result of a match between `blue` and `orange`
+    pub fn record(&mut self, blue: &str, orange: &str, result: &MatchResult) {
+        let blue = self.index_of(blue);
+        let orange = self.index_of(orange);
+
+        let score = match result.winner {
+            Some(Team::Blue) => 1.,
+            Some(Team::Orange) => 0.,
+            None => 0.5,
+        };
+
+        let expected = 1. / (1. + 10f32.powf((self.standings[orange].rating - self.standings[blue].rating) / 400.));
+        let change = self.k_factor * (score - expected);
+
+        self.standings[blue].rating += change;
+        self.standings[orange].rating -= change;
+
+        for (i, team) in [(blue, Team::Blue), (orange, Team::Orange)] {
+            let goals_for = u32::from(result.score[team as usize]);
+            let goals_against = u32::from(result.score[1 - team as usize]);
+
+            let standing = &mut self.standings[i];
+            standing.games += 1;
+            standing.goals_for += goals_for;
+            standing.goals_against += goals_against;
+
+            match result.winner {
+                Some(winner) if winner == team => standing.wins += 1,
+                Some(_) => standing.losses += 1,
+                None => standing.draws += 1,
+            }
+        }
+    }
+
+    /// Writes the leaderboard to a file, one standing per line
+    ///
+    /// Backslashes and line breaks in names are escaped so every standing stays on its own line
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = BufWriter::new(fs::File::create(path)?);
+
+        writeln!(file, "{}", self.k_factor)?;
+        for standing in &self.standings {
+            // the name goes last since it's the only field that can contain spaces
+            writeln!(
+                file,
+                "{} {} {} {} {} {} {} {}",
+                standing.rating,
+                standing.games,
+                standing.wins,
+                standing.losses,
+                standing.draws,
+                standing.goals_for,
+                standing.goals_against,
+                escape_name(&standing.name)
+            )?;
+        }
+
+        file.flush()
+    }
+
+    /// Reads a leaderboard written by `save`
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut lines = BufReader::new(fs::File::open(path)?).lines();
+
+        let k_factor = match lines.next() {
+            Some(line) => parse_field(line?.trim())?,
+            None => return Err(invalid_data("missing k factor")),
+        };
+
+        let mut leaderboard = Self::new(k_factor);
+
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let mut fields = line.splitn(8, ' ');
+            let mut next = || fields.next().ok_or_else(|| invalid_data("missing field"));
+
+            let rating = parse_field(next()?)?;
+            let games = parse_field(next()?)?;
+            let wins = parse_field(next()?)?;
+            let losses = parse_field(next()?)?;
+            let draws = parse_field(next()?)?;
+            let goals_for = parse_field(next()?)?;
+            let goals_against = parse_field(next()?)?;
+            let name = unescape_name(next()?)?;
+
+            leaderboard.standings.push(Standing {
+                name,
+                rating,
+                games,
+                wins,
+                losses,
+                draws,
+                goals_for,
+                goals_against,
+            });
+        }
+
+        Ok(leaderboard)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_utils", derive(Serialize, Deserialize))]
+pub struct GameRecord {
+    /// Index of the contestant that played on blue
+    pub blue: usize,
+    /// Index of the contestant that played on orange
+    pub orange: usize,
+    pub result: MatchResult,
+}
+
+struct Contestant {
+    name: String,
+    factory: AgentFactory,
+}
+
+/// Plays matches between contestants and keeps track of their ratings
+///
+/// Each contestant is a function that creates a new agent,
+/// so a contestant can control multiple cars and play multiple matches at once.
+pub struct Tournament {
+    pub config: TournamentConfig,
+    pub leaderboard: Leaderboard,
+    contestants: Vec<Contestant>,
+}
+
+impl Tournament {
+    #[inline]
+    #[must_use]
+    pub fn new(config: TournamentConfig) -> Self {
+        Self::with_leaderboard(config, Leaderboard::default())
+    }
+
+    #[inline]
+    #[must_use]
+    /// Continues from an existing leaderboard, like one loaded from disk
+    pub const fn with_leaderboard(config: TournamentConfig, leaderboard: Leaderboard) -> Self {
+        Self {
+            config,
+            leaderboard,
+            contestants: Vec::new(),
+        }
+    }
+
+    /// Adds a contestant, returning its index
+    ///
+    /// The name is used to find the contestant's rating in the leaderboard
+    pub fn add_contestant<A, F>(&mut self, name: impl Into<String>, factory: F) -> usize
+    where
+        A: Agent + 'static,
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        self.contestants.push(Contestant {
+            name: name.into(),
+            factory: Arc::new(move || Box::new(factory())),
+        });
+
+        self.contestants.len() - 1
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn num_contestants(&self) -> usize {
+        self.contestants.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn name(&self, index: usize) -> &str {
+        &self.contestants[index].name
+    }
+
+    /// Every match that will be played, as `(blue, orange)` contestant indices
+    #[must_use]
+    pub fn pairings(&self) -> Vec<(usize, usize)> {
+        let num_contestants = self.contestants.len();
+
+        let pairs = match self.config.schedule {
+            Schedule::RoundRobin => (0..num_contestants)
+                .flat_map(|a| (a + 1..num_contestants).map(move |b| (a, b)))
+                .collect::<Vec<_>>(),
+            Schedule::Gauntlet { challenger } => (0..num_contestants)
+                .filter(|&other| other != challenger && challenger < num_contestants)
+                .map(|other| (challenger, other))
+                .collect(),
+        };
+
+        pairs
+            .into_iter()
+            .flat_map(|(a, b)| (0..self.config.games_per_pairing).map(move |i| if i % 2 == 0 { (a, b) } else { (b, a) }))
+            .collect()
+    }
+
+    /// Plays every match in the schedule and updates the leaderboard
+    ///
+    /// Results are recorded in the order of `pairings`, no matter which thread finishes first.
+    pub fn run(&mut self) -> Vec<GameRecord> {
+        let pairings = self.pairings();
+        let next_game = AtomicUsize::new(0);
+        let records = Mutex::new(Vec::with_capacity(pairings.len()));
+
+        let config = self.config;
+        let contestants = &self.contestants;
+
+        thread::scope(|scope| {
+            for _ in 0..config.num_threads.clamp(1, pairings.len().max(1)) {
+                scope.spawn(|| loop {
+                    let game = next_game.fetch_add(1, Ordering::Relaxed);
+                    let Some(&(blue, orange)) = pairings.get(game) else {
+                        break;
+                    };
+
+                    let mut runner = MatchRunner::new(config.runner);
+                    for (contestant, team) in [(blue, Team::Blue), (orange, Team::Orange)] {
+                        for _ in 0..config.team_size {
+                            runner.add_boxed_agent(team, &config.car_config, (contestants[contestant].factory)());
+                        }
+                    }
+
+                    let result = runner.run();
+                    records.lock().unwrap().push((game, GameRecord { blue, orange, result }));
+                });
+            }
+        });
+
+        let mut records = records.into_inner().unwrap();
+        records.sort_by_key(|(game, _)| *game);
+
+        records
+            .into_iter()
+            .map(|(_, record)| {
+                self.leaderboard.record(
+                    &self.contestants[record.blue].name,
+                    &self.contestants[record.orange].name,
+                    &record.result,
+                );
+
+                record
+            })
+            .collect()
+    }
+}
+
+#[inline]
+fn invalid_data(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+fn escape_name(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn unescape_name(escaped: &str) -> io::Result<String> {
+    let mut name = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            name.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('\\') => name.push('\\'),
+            Some('n') => name.push('\n'),
+            Some('r') => name.push('\r'),
+            _ => return Err(invalid_data("invalid escape in name")),
+        }
+    }
+
+    Ok(name)
+}
+
+#[inline]
+fn parse_field<T: std::str::FromStr>(field: &str) -> io::Result<T> {
+    field.parse().map_err(|_| invalid_data("invalid field"))
+}
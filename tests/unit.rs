@@ -1,7 +1,15 @@
 use rocketsim_rs::{
+    agent::{Agent, MatchRunner, RunnerConfig},
+    bots::{Atba, BallChaser, KickoffBot},
+    determinism::verify_determinism,
     init,
     math::{Angle, RotMat, Vec3},
-    sim::{Arena, BallState, CarConfig, CarControls, CarState, Team},
+    obs::{ObsBuilder, ObsConfig},
+    rules::{Match, MatchConfig, MatchEvent, MatchPhase},
+    sim::{Arena, BallState, CarConfig, CarContact, CarControls, CarState, GameMode, Team},
+    stats::{StatKind, StatTracker},
+    tournament::{Leaderboard, Tournament, TournamentConfig},
+    GameState, UnappliedField,
 };
 use std::{
     f32::consts::PI,
@@ -136,6 +144,57 @@ fn game_state() {
     arena.pin_mut().reset_tick_count();
 }
 
+#[test]
+fn observations() {
+    INIT.call_once(|| init(None, true));
+    let mut arena = Arena::default_standard();
+    let blue = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    let orange = arena.pin_mut().add_car(Team::Orange, CarConfig::octane());
+
+    // put both cars in mirrored positions, facing each other
+    let blue_state = CarState {
+        pos: Vec3::new(-256., -3840., 17.),
+        rot_mat: Angle {
+            yaw: PI / 2.,
+            pitch: 0.,
+            roll: 0.,
+        }
+        .to_rotmat(),
+        vel: Vec3::new(10., 500., 0.),
+        ..Default::default()
+    };
+    let orange_state = CarState {
+        pos: Vec3::new(256., 3840., 17.),
+        rot_mat: Angle {
+            yaw: -PI / 2.,
+            pitch: 0.,
+            roll: 0.,
+        }
+        .to_rotmat(),
+        vel: Vec3::new(-10., -500., 0.),
+        ..Default::default()
+    };
+    arena.pin_mut().set_car(blue, blue_state).unwrap();
+    arena.pin_mut().set_car(orange, orange_state).unwrap();
+
+    let game_state = arena.pin_mut().get_game_state();
+    let mut builder = ObsBuilder::new(ObsConfig {
+        max_team_size: 2,
+        ..Default::default()
+    });
+
+    let blue_obs = builder.build(&game_state, blue).unwrap();
+    let orange_obs = builder.build(&game_state, orange).unwrap();
+    assert_eq!(blue_obs.len(), builder.obs_size(game_state.pads.len()));
+    assert_eq!(orange_obs.len(), blue_obs.len());
+    assert!(builder.build(&game_state, 0).is_none());
+
+    // the situation is symmetrical, so both cars should see the same thing
+    for (b, o) in blue_obs.iter().zip(&orange_obs) {
+        assert!((b - o).abs() < 1e-4, "{b} != {o}");
+    }
+}
+
 #[test]
 fn angles() {
     INIT.call_once(|| init(None, true));
@@ -197,6 +256,517 @@ fn goal_score() {
     assert!(SCORED.load(Ordering::Relaxed));
 }
 
+#[test]
+fn match_rules() {
+    INIT.call_once(|| init(None, true));
+    let mut arena = Arena::default_standard();
+    let _ = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    let _ = arena.pin_mut().add_car(Team::Orange, CarConfig::octane());
+
+    let mut game = Match::new(
+        arena,
+        MatchConfig {
+            game_length: 1.,
+            kickoff_countdown: 0.5,
+            ..Default::default()
+        },
+    );
+    assert_eq!(game.phase(), MatchPhase::Countdown { ticks_left: 60 });
+
+    // cars are frozen during the countdown
+    let events = game.step(60);
+    assert_eq!(events, [MatchEvent::KickoffStarted]);
+    assert_eq!(game.arena().get_tick_count(), 0);
+
+    // nobody is driving, so the ball stays on the ground and time runs out with a tie
+    let events = game.step(120);
+    assert_eq!(
+        events,
+        [
+            MatchEvent::TimeExpired,
+            MatchEvent::OvertimeStarted,
+            MatchEvent::KickoffCountdown
+        ]
+    );
+    assert!(game.is_overtime());
+
+    game.step(60);
+    game.arena_mut().set_ball(BallState {
+        pos: Vec3::new(0., 5000., 100.),
+        vel: Vec3::new(0., 2000., 0.),
+        ..Default::default()
+    });
+
+    // overtime is sudden death
+    let events = game.step(15);
+    assert_eq!(
+        events,
+        [
+            MatchEvent::Goal { team: Team::Blue },
+            MatchEvent::Ended {
+                winner: Some(Team::Blue)
+            }
+        ]
+    );
+    assert_eq!(game.score(), [1, 0]);
+    assert!(game.forfeit(Team::Blue).is_empty());
+}
+
+#[test]
+fn bots() {
+    INIT.call_once(|| init(None, true));
+
+    let agents: [Box<dyn Agent>; 3] = [
+        Box::new(Atba),
+        Box::new(BallChaser::default()),
+        Box::new(KickoffBot::default()),
+    ];
+    for mut agent in agents {
+        let mut arena = Arena::default_standard();
+        let car_id = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+        arena.pin_mut().reset_to_random_kickoff(Some(0));
+
+        // every bot should be able to hit the ball on kickoff
+        let mut touched = false;
+        for _ in 0..120 {
+            let state = arena.pin_mut().get_game_state();
+            let controls = agent.get_controls(&state, car_id);
+            arena.pin_mut().set_car_controls(car_id, controls).unwrap();
+            arena.pin_mut().step(8);
+
+            if arena.pin_mut().get_car(car_id).ball_hit_info.is_valid {
+                touched = true;
+                break;
+            }
+        }
+
+        assert!(touched);
+    }
+}
+
+#[test]
+fn match_runner() {
+    INIT.call_once(|| init(None, true));
+
+    let mut runner = MatchRunner::new(RunnerConfig {
+        match_config: MatchConfig {
+            game_length: 1.,
+            overtime: false,
+            ..Default::default()
+        },
+        max_ticks: Some(120 * 60),
+        ..Default::default()
+    });
+
+    let drive = |_: &GameState, _: u32| CarControls {
+        throttle: 1.,
+        ..Default::default()
+    };
+    assert_eq!(runner.add_agent(Team::Blue, CarConfig::octane(), drive), 0);
+    assert_eq!(runner.add_agent(Team::Orange, CarConfig::dominus(), drive), 1);
+
+    let result = runner.run();
+    assert!(result.ticks <= 120 * 60);
+    if result.timed_out {
+        assert_eq!(result.ticks, 120 * 60);
+    }
+    assert_eq!(result.agents.len(), 2);
+    assert_eq!(result.agents[0].team, Team::Blue);
+    assert_eq!(result.agents[1].team, Team::Orange);
+    assert_ne!(result.agents[0].car_id, result.agents[1].car_id);
+
+    // the runner can be reused for another match
+    assert_eq!(runner.run().agents.len(), 2);
+}
+
+#[test]
+fn tournament() {
+    INIT.call_once(|| init(None, true));
+
+    let mut tournament = Tournament::new(TournamentConfig {
+        runner: RunnerConfig {
+            match_config: MatchConfig {
+                game_length: 5.,
+                overtime: false,
+                ..Default::default()
+            },
+            max_ticks: Some(120 * 60),
+            ..Default::default()
+        },
+        team_size: 2,
+        num_threads: 2,
+        ..Default::default()
+    });
+
+    tournament.add_contestant("atba", || Atba);
+    tournament.add_contestant("ball chaser", BallChaser::default);
+    tournament.add_contestant("kickoff", KickoffBot::default);
+    assert_eq!(tournament.pairings().len(), 6);
+
+    let records = tournament.run();
+    assert_eq!(records.len(), 6);
+    assert!(records.iter().all(|record| record.result.agents.len() == 4));
+
+    // every contestant played 4 games, and ratings are zero-sum
+    let standings = tournament.leaderboard.standings();
+    assert_eq!(standings.len(), 3);
+    assert!(standings.iter().all(|standing| standing.games == 4));
+    let total_rating = standings.iter().map(|standing| standing.rating).sum::<f32>();
+    assert!((total_rating - 3000.).abs() < 0.1);
+
+    let path = std::env::temp_dir().join("rocketsim_rs_leaderboard.txt");
+    tournament.leaderboard.save(&path).unwrap();
+    let loaded = Leaderboard::load(&path).unwrap();
+    assert_eq!(loaded, tournament.leaderboard);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn leaderboard_names() {
+    use rocketsim_rs::agent::MatchResult;
+
+    let result = MatchResult {
+        score: [2, 1],
+        winner: Some(Team::Blue),
+        ticks: 120,
+        overtime: false,
+        timed_out: false,
+        agents: Vec::new(),
+    };
+
+    // names that would otherwise break the line-based format
+    let awkward = "two\nlines \\n\r";
+    let mut leaderboard = Leaderboard::default();
+    leaderboard.record(awkward, "plain name", &result);
+    leaderboard.record("plain name", " \\", &result);
+
+    let path = std::env::temp_dir().join("rocketsim_rs_leaderboard_names.txt");
+    leaderboard.save(&path).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 4);
+
+    let loaded = Leaderboard::load(&path).unwrap();
+    assert_eq!(loaded, leaderboard);
+    assert_eq!(loaded.get(awkward).unwrap().wins, 1);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn determinism() {
+    INIT.call_once(|| init(None, true));
+    let mut arena = Arena::default_standard();
+    let blue = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    let orange = arena.pin_mut().add_car(Team::Orange, CarConfig::dominus());
+    arena.pin_mut().reset_to_random_kickoff(Some(2));
+
+    let controls = (0..240)
+        .map(|i| {
+            let controls = CarControls {
+                throttle: 1.,
+                steer: ((i / 30) as f32).sin(),
+                boost: i % 50 < 25,
+                jump: i > 200,
+                ..Default::default()
+            };
+
+            vec![(blue, controls), (orange, controls)]
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(verify_determinism(&arena, &controls, 3).unwrap(), None);
+
+    // the hash ignores differences smaller than the tolerance
+    let state = arena.pin_mut().get_game_state();
+    let mut nudged = state.clone();
+    nudged.ball.pos.z += 0.01;
+    assert_ne!(state.stable_hash(0.), nudged.stable_hash(0.));
+    assert_eq!(state.first_difference(&nudged, 0.001).unwrap().0, "ball.pos.z");
+    assert!(state.first_difference(&nudged, 0.1).is_none());
+}
+
+#[test]
+fn snapshot_restore() {
+    INIT.call_once(|| init(None, true));
+    let mut arena = Arena::default_standard();
+    let blue = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    let _ = arena.pin_mut().add_car(Team::Orange, CarConfig::dominus());
+    arena.pin_mut().reset_to_random_kickoff(Some(1));
+    arena.pin_mut().step(30);
+
+    let controls = CarControls {
+        throttle: 1.,
+        boost: true,
+        ..Default::default()
+    };
+    arena.pin_mut().set_car_controls(blue, controls).unwrap();
+
+    let mut snapshot = arena.snapshot();
+    arena.pin_mut().step(240);
+    let expected = arena.pin_mut().get_game_state();
+
+    // rolling back and replaying gives exactly the same result, any number of times
+    for _ in 0..3 {
+        arena.pin_mut().restore(&snapshot).unwrap();
+        assert_eq!(arena.get_tick_count(), 30);
+        arena.pin_mut().step(240);
+
+        let state = arena.pin_mut().get_game_state();
+        assert_eq!(state.stable_hash(0.), expected.stable_hash(0.));
+    }
+
+    arena.snapshot_into(snapshot.pin_mut());
+    let new_car = arena.pin_mut().add_car(Team::Blue, CarConfig::merc());
+    assert_eq!(arena.pin_mut().restore(&snapshot).unwrap_err().0, new_car);
+}
+
+#[test]
+fn snapshot_restore_contacts() {
+    INIT.call_once(|| init(None, true));
+    let mut arena = Arena::default_standard();
+    let car_id = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    arena
+        .pin_mut()
+        .set_car(
+            car_id,
+            CarState {
+                pos: Vec3::new(-300., 0., 17.),
+                vel: Vec3::new(1500., 0., 0.),
+                rot_mat: RotMat::IDENTITY,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    arena.pin_mut().set_ball(BallState {
+        pos: Vec3::new(0., 0., 93.15),
+        ..Default::default()
+    });
+
+    // take the snapshot while the car is touching the ball
+    while !arena.pin_mut().get_car(car_id).ball_hit_info.is_valid {
+        arena.pin_mut().step(1);
+        assert!(arena.get_tick_count() < 120);
+    }
+    let snapshot = arena.snapshot();
+
+    // the ball gets knocked away, so Bullet throws out the contacts between them
+    arena.pin_mut().step(60);
+    let ball = arena.pin_mut().get_ball();
+    assert!(ball.pos.distance(arena.pin_mut().get_car(car_id).pos) > 500.);
+
+    let expected = (0..30)
+        .map(|_| {
+            arena.pin_mut().step(1);
+            arena.pin_mut().get_game_state().stable_hash(0.)
+        })
+        .collect::<Vec<_>>();
+
+    arena.pin_mut().restore(&snapshot).unwrap();
+    arena.pin_mut().step(60);
+    for hash in expected {
+        arena.pin_mut().step(1);
+        assert_eq!(arena.pin_mut().get_game_state().stable_hash(0.), hash);
+    }
+}
+
+#[test]
+fn sync_game_state() {
+    INIT.call_once(|| init(None, true));
+    let mut source = Arena::default_standard();
+    let _ = source.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    let kept = source.pin_mut().add_car(Team::Orange, CarConfig::dominus());
+    let added = source.pin_mut().add_car(Team::Blue, CarConfig::plank());
+    source.pin_mut().remove_car(1).unwrap();
+    source.pin_mut().reset_to_random_kickoff(Some(2));
+    source.pin_mut().step(90);
+    let state = source.pin_mut().get_game_state();
+
+    let mut arena = Arena::default_standard();
+    let removed = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    // same id as `kept`, but with a different team and config
+    let _ = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+
+    let report = arena.pin_mut().sync_game_state(&state);
+    assert!(report.is_complete());
+    assert_eq!(report.removed, [removed]);
+    assert_eq!(report.replaced, [kept]);
+    assert_eq!(report.added, [added]);
+
+    let synced = arena.pin_mut().get_game_state();
+    assert_eq!(synced.tick_count, state.tick_count);
+    assert_eq!(synced.ball.pos, state.ball.pos);
+    for car in &state.cars {
+        let synced_car = synced.cars.iter().find(|synced_car| synced_car.id == car.id).unwrap();
+        assert_eq!(synced_car.state.pos, car.state.pos);
+        assert_eq!(synced_car.state.boost, car.state.boost);
+    }
+    assert_eq!(arena.get_car_team(kept), Team::Orange);
+    assert_eq!(arena.get_car_config(added), *CarConfig::plank());
+
+    // new cars keep counting up from the highest id
+    assert_eq!(arena.pin_mut().add_car(Team::Blue, CarConfig::octane()), added + 1);
+
+    let mut hoops = Arena::default_hoops();
+    let report = hoops.pin_mut().sync_game_state(&state);
+    assert!(!report.is_complete());
+    assert!(report.unapplied.contains(&UnappliedField::GameMode {
+        state: GameMode::Soccar,
+        arena: GameMode::Hoops
+    }));
+    assert_eq!(hoops.num_cars(), state.cars.len());
+
+    // pads and tiles that the arena's game mode doesn't have are reported too
+    let mut dropshot = Arena::default_dropshot();
+    let report = dropshot.pin_mut().sync_game_state(&state);
+    assert!(report.unapplied.contains(&UnappliedField::Pads {
+        state: state.pads.len(),
+        arena: 0
+    }));
+
+    let dropshot_state = dropshot.pin_mut().get_game_state();
+    let report = Arena::default_standard().pin_mut().sync_game_state(&dropshot_state);
+    for team in 0..2 {
+        assert!(report.unapplied.contains(&UnappliedField::Tiles {
+            team,
+            state: dropshot_state.tiles[team].len(),
+            arena: 0
+        }));
+    }
+}
+
+#[test]
+fn stats() {
+    INIT.call_once(|| init(None, true));
+    let mut arena = Arena::default_standard();
+    let blue = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    let orange = arena.pin_mut().add_car(Team::Orange, CarConfig::octane());
+
+    // drive the blue car into the ball right in front of the orange net
+    arena
+        .pin_mut()
+        .set_car(
+            blue,
+            CarState {
+                pos: Vec3::new(0., 4200., 17.),
+                vel: Vec3::new(0., 2000., 0.),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    arena.pin_mut().set_ball(BallState {
+        pos: Vec3::new(0., 4500., 93.),
+        ..Default::default()
+    });
+    arena
+        .pin_mut()
+        .set_car_controls(
+            blue,
+            CarControls {
+                throttle: 1.,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let mut tracker = StatTracker::new();
+    let mut events = Vec::new();
+    for _ in 0..240 {
+        arena.pin_mut().step(1);
+        events.extend(tracker.update(arena.pin_mut()));
+
+        if arena.is_ball_scored() {
+            break;
+        }
+    }
+
+    assert!(events
+        .iter()
+        .any(|event| event.car_id == blue && event.kind == StatKind::Goal));
+    assert_eq!(tracker.score(), [1, 0]);
+
+    let stats = tracker.get(blue).unwrap();
+    assert_eq!(stats.goals, 1);
+    assert_eq!(stats.shots, 1);
+    assert_eq!(stats.own_goals, 0);
+    assert_eq!(tracker.get(orange).unwrap().score, 0);
+}
+
+#[test]
+fn demo_stats() {
+    INIT.call_once(|| init(None, true));
+
+    let mut arena = Arena::default_standard();
+    let victim = arena.pin_mut().add_car(Team::Orange, CarConfig::breakout());
+    let toucher = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    let bumper = arena.pin_mut().add_car(Team::Blue, CarConfig::hybrid());
+
+    arena
+        .pin_mut()
+        .set_car(
+            victim,
+            CarState {
+                pos: Vec3::new(0., 0., 17.),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    // touched the victim earlier, so its last contact still points at it
+    arena
+        .pin_mut()
+        .set_car(
+            toucher,
+            CarState {
+                pos: Vec3::new(0., -2000., 17.),
+                car_contact: CarContact {
+                    other_car_id: victim,
+                    cooldown_timer: 0.,
+                },
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    arena
+        .pin_mut()
+        .set_car(
+            bumper,
+            CarState {
+                pos: Vec3::new(-300., 0., 17.),
+                vel: Vec3::new(2300., 0., 0.),
+                boost: 100.,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    arena
+        .pin_mut()
+        .set_car_controls(
+            bumper,
+            CarControls {
+                throttle: 1.,
+                boost: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let mut tracker = StatTracker::new();
+    StatTracker::attach(arena.pin_mut());
+
+    let mut events = Vec::new();
+    for _ in 0..15 {
+        arena.pin_mut().step(1);
+        events.extend(tracker.update(arena.pin_mut()));
+    }
+
+    assert!(events
+        .iter()
+        .any(|event| event.car_id == bumper && event.kind == StatKind::Demolition));
+    assert_eq!(tracker.get(bumper).unwrap().demolitions, 1);
+    assert_eq!(tracker.get(toucher).unwrap().demolitions, 0);
+    assert_eq!(tracker.get(victim).unwrap().demolished, 1);
+}
+
 #[test]
 fn demoed() {
     static DEMOED: AtomicBool = AtomicBool::new(false);
@@ -353,3 +923,1194 @@ fn game_state_serialize() {
 
     arena.pin_mut().reset_tick_count();
 }
+
+#[cfg(feature = "bin")]
+#[test]
+fn game_state_configs() {
+    use rocketsim_rs::bytes::{FromBytes, ToBytes};
+
+    INIT.call_once(|| init(None, true));
+    let mut arena = Arena::default_standard();
+    let _ = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+
+    let mut mutators = arena.get_mutator_config();
+    mutators.gravity = Vec3::new(0., 0., -325.);
+    mutators.unlimited_flips = true;
+    arena.pin_mut().set_mutator_config(mutators);
+
+    let state = arena.pin_mut().get_game_state();
+    assert_eq!(state.arena_config, Some(arena.get_arena_config()));
+
+    let bytes = state.to_bytes();
+    assert_eq!(GameState::get_num_bytes(&bytes[..GameState::MIN_NUM_BYTES]), bytes.len());
+
+    let state = GameState::from_bytes(&bytes);
+    let mutator_config = state.mutator_config.unwrap();
+    assert_eq!(mutator_config.gravity, mutators.gravity);
+    assert!(mutator_config.unlimited_flips);
+
+    // only syncing applies the mutators when loading the state into a different arena
+    let mut new_arena = Arena::default_standard();
+    let _ = new_arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    let default_gravity = new_arena.get_mutator_config().gravity;
+    new_arena.pin_mut().set_game_state(&state).unwrap();
+    assert_eq!(new_arena.get_mutator_config().gravity, default_gravity);
+    assert!(new_arena.pin_mut().sync_game_state(&state).is_complete());
+    assert_eq!(new_arena.get_mutator_config().gravity, mutators.gravity);
+
+    // the configs are optional
+    let state = GameState {
+        mutator_config: None,
+        arena_config: None,
+        ..state
+    };
+    let bytes = state.to_bytes();
+    assert_eq!(GameState::get_num_bytes(&bytes), bytes.len());
+    assert!(GameState::from_bytes(&bytes).mutator_config.is_none());
+}
+
+#[cfg(feature = "bin")]
+#[test]
+fn decode_errors() {
+    use rocketsim_rs::{
+        bytes::{DecodeReason, ToBytes, TryFromBytes},
+        render::{Color, Render, RenderMessage},
+    };
+
+    INIT.call_once(|| init(None, true));
+    let mut arena = Arena::default_standard();
+    let _ = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    let bytes = arena.pin_mut().get_game_state().to_bytes();
+    assert!(GameState::try_from_bytes(&bytes).is_ok());
+
+    // the total length is in the header
+    let err = GameState::try_from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+    assert_eq!(
+        err.reason,
+        DecodeReason::LengthMismatch {
+            expected: bytes.len(),
+            found: bytes.len() - 1
+        }
+    );
+
+    let err = GameState::try_from_bytes(&bytes[..10]).unwrap_err();
+    assert!(matches!(err.reason, DecodeReason::UnexpectedEnd { .. }));
+
+    // the game mode comes after the magic, version, length, sections, tick count and tick rate
+    let mut bad_mode = bytes.clone();
+    bad_mode[24] = 200;
+    let err = GameState::try_from_bytes(&bad_mode).unwrap_err();
+    assert_eq!(err.offset, 24);
+    assert!(GameState::try_get_num_bytes(&bad_mode).is_err());
+
+    let message = RenderMessage::AddRender(
+        0,
+        vec![Render::Line {
+            start: Vec3::ZERO,
+            end: Vec3::new(0., 0., 100.),
+            color: Color::RED,
+        }],
+    );
+    let mut bytes = message.to_bytes();
+    assert!(RenderMessage::try_from_bytes(&bytes).is_ok());
+
+    // the tag of the first render
+    bytes[11] = 200;
+    let err = RenderMessage::try_from_bytes(&bytes).unwrap_err();
+    assert_eq!(err.offset, 11);
+    assert_eq!(
+        err.reason,
+        DecodeReason::InvalidValue {
+            name: "Render",
+            value: 200
+        }
+    );
+}
+
+#[cfg(feature = "bin")]
+#[test]
+fn wire_format() {
+    use rocketsim_rs::bytes::{DecodeReason, Sections, ToBytes, TryFromBytes};
+
+    INIT.call_once(|| init(None, true));
+    let mut arena = Arena::default_standard();
+    let car_id = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    arena
+        .pin_mut()
+        .set_car_controls(
+            car_id,
+            CarControls {
+                throttle: 1.,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    arena.pin_mut().step(10);
+
+    let state = arena.pin_mut().get_game_state();
+    let bytes = state.to_bytes();
+    assert!(bytes.starts_with(&GameState::MAGIC));
+    assert_eq!(GameState::read_version(&bytes), GameState::VERSION);
+    assert_eq!(GameState::read_tick_count(&bytes), state.tick_count);
+    assert_eq!(GameState::read_num_cars(&bytes), 1);
+
+    let decoded = GameState::try_from_bytes(&bytes).unwrap();
+    assert_eq!(decoded.stable_hash(0.), state.stable_hash(0.));
+    assert_eq!(decoded.pads.len(), state.pads.len());
+
+    // leaving out sections makes the bytes smaller and uses defaults when decoding
+    let small = state.to_bytes_with(Sections::ALL.difference(Sections::CONTROLS | Sections::PADS));
+    assert!(small.len() < bytes.len());
+    assert!(!GameState::read_sections(&small).contains(Sections::CONTROLS));
+
+    let decoded = GameState::try_from_bytes(&small).unwrap();
+    assert!(decoded.pads.is_empty());
+    assert_eq!(decoded.cars[0].state.pos, state.cars[0].state.pos);
+    assert_eq!(decoded.cars[0].state.last_controls.throttle, 0.);
+
+    // a section from a newer encoder, written after all of the known ones
+    let mut newer = bytes.clone();
+    let sections = GameState::read_sections(&newer).bits() | 1 << 15;
+    newer[10..12].copy_from_slice(&sections.to_le_bytes());
+    newer.extend(3u32.to_le_bytes());
+    newer.extend([1, 2, 3]);
+    let num_bytes = newer.len() as u32;
+    newer[6..10].copy_from_slice(&num_bytes.to_le_bytes());
+
+    assert_eq!(GameState::get_num_bytes(&newer), newer.len());
+    let decoded = GameState::try_from_bytes(&newer).unwrap();
+    assert_eq!(decoded.stable_hash(0.), state.stable_hash(0.));
+
+    // a newer version can't be read
+    let mut unsupported = bytes;
+    unsupported[4..6].copy_from_slice(&(GameState::VERSION + 1).to_le_bytes());
+    let err = GameState::try_from_bytes(&unsupported).unwrap_err();
+    assert_eq!(err.reason, DecodeReason::UnsupportedVersion(GameState::VERSION + 1));
+}
+
+#[cfg(feature = "bin")]
+/// The game state encoder from before the format had a magic number and version
+fn baseline_to_bytes(state: &GameState) -> Vec<u8> {
+    use rocketsim_rs::{
+        bytes::{FromBytesExact, ToBytesExact},
+        BoostPad, CarInfo, DropshotTile,
+    };
+
+    let mut bytes = Vec::new();
+
+    bytes.extend(state.tick_count.to_bytes());
+    bytes.extend(state.tick_rate.to_bytes());
+    bytes.extend(state.game_mode.to_bytes());
+    bytes.extend(&(state.pads.len() as u32).to_bytes());
+    bytes.extend(&(state.cars.len() as u32).to_bytes());
+    bytes.extend(state.ball.to_bytes());
+    bytes.extend(state.pads.iter().flat_map(ToBytesExact::<{ BoostPad::NUM_BYTES }>::to_bytes));
+    bytes.extend(state.cars.iter().flat_map(ToBytesExact::<{ CarInfo::NUM_BYTES }>::to_bytes));
+    bytes.extend(
+        state.tiles[0]
+            .iter()
+            .flat_map(ToBytesExact::<{ DropshotTile::NUM_BYTES }>::to_bytes),
+    );
+    bytes.extend(
+        state.tiles[1]
+            .iter()
+            .flat_map(ToBytesExact::<{ DropshotTile::NUM_BYTES }>::to_bytes),
+    );
+
+    bytes
+}
+
+#[cfg(feature = "bin")]
+#[test]
+fn legacy_magic_collision() {
+    use rocketsim_rs::{bytes::TryFromBytes, CarInfo};
+
+    let mut state = GameState {
+        tick_rate: 120.,
+        ..Default::default()
+    };
+    state.ball.pos = Vec3::new(0., 0., 93.);
+    state.cars.push(CarInfo {
+        id: 1,
+        ..Default::default()
+    });
+
+    // tick counts whose low bytes spell out the magic number, followed by a version of 0,
+    // a version of 1 with a length shorter than the header, and one with a length that doesn't match
+    for tick_bytes in [*b"RSGS\0\0\0\0", *b"RSGS\x01\0\0\0", *b"RSGS\x01\0\x40\0"] {
+        state.tick_count = u64::from_le_bytes(tick_bytes);
+
+        let bytes = baseline_to_bytes(&state);
+        assert!(bytes.starts_with(&GameState::MAGIC));
+        assert_eq!(state.to_bytes_legacy(), bytes);
+
+        let decoded = GameState::try_from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.tick_count, state.tick_count);
+        assert_eq!(decoded.ball.pos, state.ball.pos);
+        assert_eq!(decoded.cars.len(), 1);
+    }
+
+    // the header alone is enough to tell the first two apart from the current layout
+    state.tick_count = u64::from_le_bytes(*b"RSGS\x01\0\0\0");
+    let bytes = baseline_to_bytes(&state);
+    assert_eq!(GameState::read_version(&bytes), 0);
+    assert_eq!(GameState::get_num_bytes(&bytes), bytes.len());
+}
+
+#[cfg(feature = "bin")]
+#[test]
+fn legacy_wire_format() {
+    use rocketsim_rs::bytes::TryFromBytes;
+
+    INIT.call_once(|| init(None, true));
+
+    for game_mode in [GameMode::Soccar, GameMode::Dropshot] {
+        let mut arena = Arena::new(game_mode, Default::default(), 120);
+        let _ = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+        let _ = arena.pin_mut().add_car(Team::Orange, CarConfig::dominus());
+        arena.pin_mut().step(10);
+
+        let state = arena.pin_mut().get_game_state();
+        let bytes = baseline_to_bytes(&state);
+        assert_eq!(state.to_bytes_legacy(), bytes);
+        assert_eq!(state.num_bytes_legacy(), bytes.len());
+        assert_eq!(GameState::read_version(&bytes), 0);
+        assert_eq!(GameState::get_num_bytes(&bytes), bytes.len());
+        assert_eq!(GameState::try_get_num_bytes(&bytes[..21]).unwrap(), bytes.len());
+        assert_eq!(GameState::read_tick_count(&bytes), state.tick_count);
+        assert_eq!(GameState::read_num_cars(&bytes), 2);
+        assert!(!GameState::read_is_ball_scored(&bytes));
+
+        // the ball starts right after the 21 byte header
+        let decoded = GameState::try_from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.game_mode, game_mode);
+        assert_eq!(decoded.ball.pos, state.ball.pos);
+        assert_eq!(decoded.pads.len(), state.pads.len());
+        assert_eq!(decoded.tiles[0].len(), state.tiles[0].len());
+        assert_eq!(decoded.cars[1].config.hitbox_size, state.cars[1].config.hitbox_size);
+        assert_eq!(decoded.stable_hash(0.), state.stable_hash(0.));
+
+        // nothing in the old layout can carry these
+        assert!(!decoded.is_ball_scored);
+        assert!(decoded.mutator_config.is_none());
+        assert!(decoded.arena_config.is_none());
+    }
+}
+
+#[cfg(feature = "bin")]
+#[test]
+fn legacy_remove_render() {
+    use rocketsim_rs::{
+        bytes::{FromReader, ToBytes, ToBytesExact, TryFromBytes},
+        render::RenderMessage,
+    };
+
+    // older encoders left the tag out of the length of `RemoveRender`
+    let mut bytes = 8u32.to_bytes().to_vec();
+    bytes.push(1);
+    bytes.extend((-3i32).to_bytes());
+    assert_eq!(RenderMessage::get_num_bytes(&bytes), bytes.len() - 1);
+    assert!(matches!(
+        RenderMessage::try_from_bytes(&bytes),
+        Ok(RenderMessage::RemoveRender(-3))
+    ));
+
+    let current = RenderMessage::RemoveRender(7).to_bytes();
+    assert_eq!(RenderMessage::get_num_bytes(&current), current.len());
+
+    // the extra byte is read from streams too, so the next message still lines up
+    let stream = [bytes.as_slice(), &current].concat();
+    let mut reader = stream.as_slice();
+    let mut buf = Vec::new();
+    assert!(matches!(
+        RenderMessage::read_from(&mut reader, &mut buf),
+        Ok(RenderMessage::RemoveRender(-3))
+    ));
+    assert!(matches!(
+        RenderMessage::read_from(&mut reader, &mut buf),
+        Ok(RenderMessage::RemoveRender(7))
+    ));
+    assert!(reader.is_empty());
+}
+
+#[cfg(feature = "bin")]
+#[test]
+fn stream_encoding() {
+    use rocketsim_rs::{
+        bytes::{FromReader, ToBytes},
+        render::{Color, Render, RenderMessage},
+    };
+    use std::io::{Cursor, ErrorKind};
+
+    INIT.call_once(|| init(None, true));
+    let mut arena = Arena::default_standard();
+    let _ = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    arena.pin_mut().step(10);
+
+    let state = arena.pin_mut().get_game_state();
+    let bytes = state.to_bytes();
+    assert_eq!(state.num_bytes(), bytes.len());
+
+    // encoding into a buffer appends to what's already there
+    let mut buf = vec![7];
+    state.encode_into(&mut buf);
+    assert_eq!(buf[0], 7);
+    assert_eq!(&buf[1..], &bytes);
+
+    buf.clear();
+    state.encode_into(&mut buf);
+    assert_eq!(buf, bytes);
+
+    let messages = [
+        RenderMessage::AddRender(
+            3,
+            vec![Render::Line {
+                start: Vec3::ZERO,
+                end: Vec3::new(0., 0., 100.),
+                color: Color::RED,
+            }],
+        ),
+        RenderMessage::RemoveRender(3),
+    ];
+
+    // multiple values can be streamed one after the other and read back
+    let mut stream = Vec::new();
+    state.write_into(&mut stream).unwrap();
+    for message in &messages {
+        message.write_into(&mut stream).unwrap();
+        assert_eq!(message.num_bytes(), message.to_bytes().len());
+    }
+    state.write_into(&mut stream).unwrap();
+
+    let mut reader = Cursor::new(stream);
+    let mut scratch = Vec::new();
+    let decoded = GameState::read_from(&mut reader, &mut scratch).unwrap();
+    assert_eq!(decoded.stable_hash(0.), state.stable_hash(0.));
+    for message in &messages {
+        let decoded = RenderMessage::read_from(&mut reader, &mut scratch).unwrap();
+        assert_eq!(decoded.to_bytes(), message.to_bytes());
+    }
+    let decoded = GameState::read_from(&mut reader, &mut scratch).unwrap();
+    assert_eq!(decoded.stable_hash(0.), state.stable_hash(0.));
+
+    let err = GameState::read_from(&mut reader, &mut scratch).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+    // values longer than the limit are rejected before they're read
+    let err = GameState::read_from_with_limit(&mut Cursor::new(&bytes), &mut scratch, bytes.len() - 1).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(GameState::read_from_with_limit(&mut Cursor::new(&bytes), &mut scratch, bytes.len()).is_ok());
+
+    let mut huge = RenderMessage::RemoveRender(0).to_bytes();
+    huge[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+    let err = RenderMessage::read_from(&mut Cursor::new(huge), &mut scratch).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    // malformed bytes are reported as invalid data
+    let mut corrupted = bytes;
+    corrupted[24] = 200;
+    let err = GameState::read_from(&mut Cursor::new(corrupted), &mut scratch).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    // `to_bytes` and `encode_into` come from `num_bytes` and `write_into`
+    struct Raw(Vec<u8>);
+
+    impl ToBytes for Raw {
+        fn num_bytes(&self) -> usize {
+            self.0.len()
+        }
+
+        fn write_into<W: std::io::Write + ?Sized>(&self, writer: &mut W) -> std::io::Result<()> {
+            writer.write_all(&self.0)
+        }
+    }
+
+    let raw = Raw(vec![1, 2, 3]);
+    assert_eq!(raw.to_bytes(), [1, 2, 3]);
+
+    let mut buf = vec![0];
+    raw.encode_into(&mut buf);
+    raw.write_into(&mut buf).unwrap();
+    assert_eq!(buf, [0, 1, 2, 3, 1, 2, 3]);
+}
+
+#[cfg(feature = "bin")]
+#[test]
+fn render_primitives() {
+    use rocketsim_rs::{
+        bytes::{DecodeReason, ToBytes, TryFromBytes},
+        render::{Color, Render, RenderMessage, Vec2},
+        CarInfo,
+    };
+
+    let car = CarInfo {
+        id: 1,
+        team: Team::Orange,
+        state: CarState {
+            pos: Vec3::new(100., 0., 17.),
+            rot_mat: RotMat::IDENTITY,
+            ..Default::default()
+        },
+        config: *CarConfig::octane(),
+    };
+
+    let Render::OrientedBox { center, size, color, .. } = Render::car_hitbox(&car) else {
+        panic!("car hitbox isn't an oriented box");
+    };
+    assert_eq!(center.x, 100. + car.config.hitbox_pos_offset.x);
+    assert_eq!(center.z, 17. + car.config.hitbox_pos_offset.z);
+    assert_eq!(size, car.config.hitbox_size);
+    assert_eq!(color.r, Color::ORANGE.r);
+
+    let path = [BallState::default(), BallState::default()];
+    assert!(matches!(Render::ball_path(&path), Render::LineStrip { positions, .. } if positions.len() == 2));
+
+    let renders = vec![
+        Render::Text2D {
+            pos: Vec2::new(10., 10.),
+            text: "Hello, rlviser! ⚽".to_string(),
+            scale: 1.,
+            color: Color::WHITE,
+        },
+        Render::Text3D {
+            pos: Vec3::new(0., 0., 100.),
+            text: String::new(),
+            scale: 2.,
+            color: Color::GREEN,
+        },
+        Render::Sphere {
+            center: Vec3::new(0., 0., 93.),
+            radius: 91.25,
+            color: Color::rgba(1., 1., 1., 0.5),
+        },
+        Render::Box {
+            center: Vec3::ZERO,
+            size: Vec3::new(10., 20., 30.),
+            color: Color::BLUE,
+        },
+        Render::car_hitbox(&car),
+        Render::Rect2D {
+            pos: Vec2::ZERO,
+            size: Vec2::new(100., 50.),
+            filled: true,
+            color: Color::BLACK,
+        },
+        Render::Arrow {
+            start: Vec3::ZERO,
+            end: Vec3::new(0., 500., 0.),
+            color: Color::RED,
+        },
+        Render::ball_path(&path),
+    ];
+
+    let message = RenderMessage::AddRender(1, renders);
+    let bytes = message.to_bytes();
+    assert_eq!(bytes.len(), message.num_bytes());
+
+    let decoded = RenderMessage::try_from_bytes(&bytes).unwrap();
+    assert_eq!(decoded.to_bytes(), bytes);
+    let RenderMessage::AddRender(_, decoded) = decoded else {
+        panic!("decoded the wrong message");
+    };
+    assert!(matches!(&decoded[0], Render::Text2D { text, .. } if text == "Hello, rlviser! ⚽"));
+
+    // the first byte of the text, after the length, tag, id, number of renders, render tag, position and text length
+    let mut invalid_text = bytes;
+    invalid_text[4 + 1 + 4 + 2 + 1 + 8 + 2] = 0xff;
+    let err = RenderMessage::try_from_bytes(&invalid_text).unwrap_err();
+    assert!(matches!(err.reason, DecodeReason::InvalidValue { value: 0xff, .. }));
+}
+
+#[cfg(feature = "bin")]
+#[test]
+fn delta_streaming() {
+    use rocketsim_rs::{
+        bytes::{FromBytes, ToBytes},
+        delta::{DeltaConfig, DeltaDecoder, DeltaEncoder, DeltaError, FrameKind},
+    };
+
+    INIT.call_once(|| init(None, true));
+    let mut arena = Arena::default_standard();
+    let blue = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    let orange = arena.pin_mut().add_car(Team::Orange, CarConfig::dominus());
+    arena.pin_mut().reset_to_random_kickoff(Some(4));
+    arena
+        .pin_mut()
+        .set_car_controls(
+            blue,
+            CarControls {
+                throttle: 1.,
+                boost: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let mut encoder = DeltaEncoder::new(DeltaConfig {
+        keyframe_interval: 60,
+        quantize: false,
+    });
+    let mut decoder = DeltaDecoder::new();
+    let mut quantized_encoder = DeltaEncoder::new(DeltaConfig {
+        keyframe_interval: 60,
+        quantize: true,
+    });
+    let mut quantized_decoder = DeltaDecoder::new();
+    let is_close = |a: Vec3, b: Vec3| (a.x - b.x).abs() < 1. && (a.y - b.y).abs() < 1. && (a.z - b.z).abs() < 1.;
+
+    for tick in 0..150 {
+        if tick == 100 {
+            arena.pin_mut().remove_car(orange).unwrap();
+            let _ = arena.pin_mut().add_car(Team::Orange, CarConfig::merc());
+        }
+
+        arena.pin_mut().step(1);
+        let state = arena.pin_mut().get_game_state();
+        let bytes = state.to_bytes();
+
+        let frame = encoder.encode(&state);
+        assert_eq!(DeltaDecoder::get_num_bytes(&frame), frame.len());
+        if tick > 0 && tick % 60 != 0 {
+            assert!(frame.len() < bytes.len());
+        }
+
+        let decoded = decoder.decode(&frame).unwrap();
+        assert_eq!(decoded.to_bytes(), GameState::from_bytes(&bytes).to_bytes());
+
+        let mut frame = Vec::new();
+        quantized_encoder.encode_into(&state, &mut frame);
+        let decoded = quantized_decoder.decode(&frame).unwrap();
+        assert_eq!(decoded.tick_count, state.tick_count);
+        assert_eq!(decoded.cars.len(), state.cars.len());
+        assert!(is_close(decoded.ball.pos, state.ball.pos));
+        for (decoded, car) in decoded.cars.iter().zip(&state.cars) {
+            assert_eq!(decoded.id, car.id);
+            assert_eq!(decoded.state.boost, car.state.boost);
+            assert!(is_close(decoded.state.pos, car.state.pos));
+            assert!(is_close(decoded.state.vel, car.state.vel));
+        }
+    }
+
+    // a car that's replaced under the same id is sent in full again
+    arena.pin_mut().step(1);
+    let mut state = arena.pin_mut().get_game_state();
+    state.cars[0].team = Team::Orange;
+    state.cars[0].config = *CarConfig::breakout();
+    let mut frame = Vec::new();
+    assert_eq!(encoder.encode_into(&state, &mut frame), FrameKind::Delta);
+    assert_eq!(decoder.decode(&frame).unwrap().to_bytes(), state.to_bytes());
+
+    // a lost frame can't be recovered from until the next keyframe
+    arena.pin_mut().step(1);
+    let _lost = encoder.encode(&arena.pin_mut().get_game_state());
+    arena.pin_mut().step(1);
+    let frame = encoder.encode(&arena.pin_mut().get_game_state());
+    assert!(matches!(decoder.decode(&frame), Err(DeltaError::MissingBase { .. })));
+
+    encoder.force_keyframe();
+    let state = arena.pin_mut().get_game_state();
+    let mut frame = Vec::new();
+    assert_eq!(encoder.encode_into(&state, &mut frame), FrameKind::Keyframe);
+    assert_eq!(decoder.decode(&frame).unwrap().to_bytes(), state.to_bytes());
+}
+
+#[cfg(feature = "bin")]
+#[test]
+fn rlviser_connection() {
+    use rocketsim_rs::{
+        bytes::{FromBytes, ToBytes},
+        rlviser::{PacketType, RlviserConnection, RlviserError, RlviserEvent},
+    };
+    use std::net::UdpSocket;
+
+    INIT.call_once(|| init(None, true));
+    let mut arena = Arena::default_standard();
+
+    // pretend to be rlviser
+    let rlviser = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut connection = RlviserConnection::with_addrs("127.0.0.1:0", rlviser.local_addr().unwrap()).unwrap();
+
+    let mut buf = [0; 1024];
+    let (_, addr) = rlviser.recv_from(&mut buf).unwrap();
+    assert_eq!(buf[0], PacketType::Connection as u8);
+
+    let mut state = arena.pin_mut().get_game_state();
+    state.ball.pos = Vec3::new(100., 200., 300.);
+
+    rlviser.send_to(&[PacketType::Connection as u8], addr).unwrap();
+    rlviser.send_to(&[PacketType::Paused as u8], addr).unwrap();
+    rlviser.send_to(&[1], addr).unwrap();
+    rlviser.send_to(&[PacketType::Speed as u8], addr).unwrap();
+    rlviser.send_to(&0.5f32.to_le_bytes(), addr).unwrap();
+    // unknown packets are skipped
+    rlviser.send_to(&[200], addr).unwrap();
+    rlviser.send_to(&[PacketType::GameState as u8], addr).unwrap();
+    rlviser.send_to(&state.to_bytes(), addr).unwrap();
+
+    // give the packets time to arrive
+    std::thread::sleep(std::time::Duration::from_millis(50));
+
+    let events = connection.update(arena.pin_mut()).unwrap();
+    assert_eq!(events.len(), 4);
+    assert!(matches!(events[0], RlviserEvent::Connected(_)));
+    assert!(connection.is_paused());
+    assert_eq!(connection.speed(), 0.5);
+    assert!(matches!(&events[3], RlviserEvent::SetGameState(state) if state.ball.pos.x == 100.));
+    assert_eq!(arena.pin_mut().get_ball().pos.z, 300.);
+
+    // nothing left to receive, and unknown packets are reported by `recv_event`
+    assert!(connection.recv_event().unwrap().is_none());
+    rlviser.send_to(&[200], addr).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    assert!(matches!(connection.recv_event(), Err(RlviserError::UnexpectedPacket(200))));
+
+    arena.pin_mut().step(1);
+    connection.send_game_state(&arena.pin_mut().get_game_state()).unwrap();
+    rlviser.recv_from(&mut buf).unwrap();
+    assert_eq!(buf[0], PacketType::GameState as u8);
+
+    let mut state_buf = vec![0; 65536];
+    let (num_bytes, _) = rlviser.recv_from(&mut state_buf).unwrap();
+    // sent in the layout rlviser reads
+    assert_eq!(GameState::read_version(&state_buf[..num_bytes]), 0);
+    let received = GameState::from_bytes(&state_buf[..num_bytes]);
+    assert_eq!(received.tick_count, arena.get_tick_count());
+}
+
+#[cfg(feature = "bin")]
+#[test]
+fn render_groups() {
+    use rocketsim_rs::{
+        bytes::FromBytes,
+        render::{Color, Render, RenderMessage},
+        rlviser::{PacketType, RlviserConnection},
+    };
+    use std::net::UdpSocket;
+
+    let rlviser = UdpSocket::bind("127.0.0.1:0").unwrap();
+    rlviser.set_read_timeout(Some(std::time::Duration::from_secs(1))).unwrap();
+    let mut connection = RlviserConnection::with_addrs("127.0.0.1:0", rlviser.local_addr().unwrap()).unwrap();
+
+    let mut buf = vec![0; 65536];
+    let mut render_buf = vec![0; 65536];
+    let mut recv_render = || {
+        rlviser.recv_from(&mut render_buf).unwrap();
+        assert_eq!(render_buf[0], PacketType::Render as u8);
+        let (num_bytes, _) = rlviser.recv_from(&mut render_buf).unwrap();
+        RenderMessage::from_bytes(&render_buf[..num_bytes])
+    };
+
+    // skip the connection packet
+    rlviser.recv_from(&mut [0; 8]).unwrap();
+
+    let mut overlay = connection.render_group().unwrap().with_ttl(10);
+    let mut hitbox = connection.render_group().unwrap();
+    assert_ne!(overlay.id(), hitbox.id());
+
+    let sphere = Render::Sphere {
+        center: Vec3::new(0., 0., 100.),
+        radius: 50.,
+        color: Color::RED,
+    };
+    overlay.push(sphere.clone()).push(sphere.clone());
+    overlay.send().unwrap();
+    assert_eq!(overlay.renders().len(), 2);
+    let overlay_id = overlay.id();
+    assert!(matches!(recv_render(), RenderMessage::AddRender(id, renders) if id == overlay_id && renders.len() == 2));
+
+    hitbox.push(sphere);
+    hitbox.send().unwrap();
+    let hitbox_id = hitbox.id();
+    assert!(matches!(recv_render(), RenderMessage::AddRender(id, renders) if id == hitbox_id && renders.len() == 1));
+
+    // the overlay expires once 10 ticks have passed
+    let mut state = GameState::default();
+    for tick_count in [5, 10] {
+        state.tick_count = tick_count;
+        connection.send_game_state(&state).unwrap();
+        rlviser.recv_from(&mut buf).unwrap();
+        assert_eq!(buf[0], PacketType::GameState as u8);
+        rlviser.recv_from(&mut buf).unwrap();
+    }
+    assert!(matches!(recv_render(), RenderMessage::RemoveRender(id) if id == overlay_id));
+
+    // groups that aren't shown don't send anything when dropped
+    drop(connection.render_group().unwrap());
+
+    drop(hitbox);
+    assert!(matches!(recv_render(), RenderMessage::RemoveRender(id) if id == hitbox_id));
+}
+
+#[cfg(feature = "bin")]
+#[test]
+fn replay_recording() {
+    use rocketsim_rs::{
+        replay::{Keyframe, Player, Record, Recorder, Replay, ReplayError},
+        sim::ArenaConfig,
+    };
+
+    INIT.call_once(|| init(None, true));
+    let mut arena = Arena::default_standard();
+    let blue = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    let orange = arena.pin_mut().add_car(Team::Orange, CarConfig::octane());
+    arena.pin_mut().reset_to_random_kickoff(Some(0));
+
+    let mut recorder = Recorder::new(Vec::new(), arena.pin_mut(), 30).unwrap();
+    for i in 0..15 {
+        let controls = CarControls {
+            throttle: 1.,
+            steer: if i % 2 == 0 { 0.5 } else { -0.5 },
+            boost: i > 5,
+            ..Default::default()
+        };
+
+        recorder
+            .step(arena.pin_mut(), &[(blue, controls), (orange, controls)], 8)
+            .unwrap();
+    }
+    let bytes = recorder.finish().unwrap();
+
+    let replay = Replay::new(bytes.clone()).unwrap();
+    let ticks = replay
+        .keyframes()
+        .iter()
+        .map(|keyframe| keyframe.tick_count)
+        .collect::<Vec<_>>();
+    assert_eq!(ticks, [0, 30, 60, 90, 120]);
+    assert_eq!(replay.keyframe_before(75).tick_count, 60);
+
+    // recordings that weren't finished have the same keyframes, found without the index
+    let unfinished = Replay::new(bytes[..bytes.len() - 200].to_vec()).unwrap();
+    assert_eq!(unfinished.keyframes(), &replay.keyframes()[..4]);
+
+    // offsets that point outside of the recording are reported instead of panicking
+    let len = bytes.len();
+    let mut bad_index = bytes.clone();
+    bad_index[len - 12..len - 4].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(matches!(Replay::new(bad_index), Err(ReplayError::Corrupt { .. })));
+
+    // the last keyframe in the index pointing into the header
+    let mut bad_keyframe = bytes.clone();
+    bad_keyframe[len - 20..len - 12].copy_from_slice(&1u64.to_le_bytes());
+    assert!(matches!(Replay::new(bad_keyframe), Err(ReplayError::Corrupt { .. })));
+
+    let past_end = Keyframe {
+        tick_count: 0,
+        offset: u64::MAX,
+    };
+    let mut records = replay.records_from(past_end);
+    assert!(matches!(records.next(), Some(Err(ReplayError::Corrupt { .. }))));
+    assert!(records.next().is_none());
+
+    // re-simulating the recording ends up in the same place
+    let mut resim = replay.create_arena(ArenaConfig::default()).unwrap();
+    for record in replay.records() {
+        let (_, record) = record.unwrap();
+        if let Record::Keyframe(state) = &record {
+            assert_eq!(state.tick_count, resim.get_tick_count());
+        }
+
+        record.apply(resim.pin_mut()).unwrap();
+    }
+
+    let expected = arena.pin_mut().get_car(blue);
+    let actual = resim.pin_mut().get_car(blue);
+    assert_eq!(resim.get_tick_count(), 120);
+    assert!((expected.pos.x - actual.pos.x).abs() < 1.);
+    assert!((expected.pos.y - actual.pos.y).abs() < 1.);
+
+    // play back the recording, jumping around
+    let mut player = Player::new(replay, ArenaConfig::default()).unwrap();
+    assert_eq!(player.seek(75).unwrap(), 75);
+    let pos_at_75 = player.game_state().cars[0].state.pos;
+
+    assert_eq!(player.seek(10).unwrap(), 10);
+    assert_eq!(player.seek(75).unwrap(), 75);
+    let pos = player.game_state().cars[0].state.pos;
+    assert!((pos.x - pos_at_75.x).abs() < 1.);
+    assert!((pos.y - pos_at_75.y).abs() < 1.);
+
+    // iterating goes until the end of the recording
+    assert_eq!(player.states().count(), 45);
+    assert!(player.is_finished());
+    assert_eq!(player.seek(1000).unwrap(), 120);
+    assert!(player.desyncs().is_empty());
+}
+
+#[cfg(feature = "minimap")]
+#[test]
+fn minimap_images() {
+    use rocketsim_rs::{
+        consts,
+        minimap::{Minimap, PALETTE},
+        CarInfo,
+    };
+    use std::time::Duration;
+
+    let mut state = GameState {
+        game_mode: GameMode::Soccar,
+        ..Default::default()
+    };
+    state.ball.pos = Vec3::new(0., 2000., 93.);
+    state.cars.push(CarInfo {
+        id: 1,
+        team: Team::Orange,
+        state: CarState {
+            pos: Vec3::new(-2000., -3000., 17.),
+            rot_mat: RotMat::IDENTITY,
+            ..Default::default()
+        },
+        config: CarConfig {
+            hitbox_size: Vec3::new(120., 84., 36.),
+            ..Default::default()
+        },
+    });
+
+    let mut minimap = Minimap::new(GameMode::Soccar, 1024);
+    assert_eq!(minimap.width(), 1024);
+    assert!(minimap.height() > minimap.width());
+
+    minimap.draw(&state);
+    assert_eq!(minimap.pixels().len(), 1024 * usize::from(minimap.height()));
+    assert_eq!(minimap.to_rgb().len(), minimap.pixels().len() * 3);
+
+    let pixel_at = |minimap: &Minimap, pos: Vec3| {
+        let (x, y) = minimap.to_pixel(pos);
+        PALETTE[usize::from(minimap.pixels()[y as usize * usize::from(minimap.width()) + x as usize])]
+    };
+    assert_eq!(pixel_at(&minimap, state.ball.pos), [245, 245, 245]);
+    // the car faces +x, with a line showing where its nose is
+    assert_eq!(pixel_at(&minimap, Vec3::new(-2030., -3030., 17.)), [255, 140, 20]);
+    assert_eq!(pixel_at(&minimap, Vec3::new(-1960., -3000., 17.)), [210, 210, 210]);
+    // without pads in the state, the game mode's pad layout is drawn
+    assert_eq!(pixel_at(&minimap, consts::boostpads::LOCS_BIG_SOCCAR[0]), [255, 215, 0]);
+    assert_eq!(pixel_at(&minimap, consts::boostpads::LOCS_SMALL_SOCCAR[0]), [190, 170, 90]);
+    // the corners are cut off, the center of the field isn't
+    assert_ne!(
+        pixel_at(&minimap, Vec3::new(-4000., 5000., 0.)),
+        pixel_at(&minimap, Vec3::new(1000., 1000., 0.))
+    );
+
+    let mut png = Vec::new();
+    minimap.write_png(&mut png).unwrap();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+    let mut later = state.clone();
+    later.ball.pos.y = -2000.;
+    let mut gif = Vec::new();
+    minimap
+        .write_gif(&mut gif, [&state, &later], Duration::from_millis(50))
+        .unwrap();
+    assert!(gif.starts_with(b"GIF89a"));
+    assert_eq!(pixel_at(&minimap, later.ball.pos), [245, 245, 245]);
+}
+
+#[test]
+fn heatmaps() {
+    use rocketsim_rs::{
+        analysis::{Heatmap, HeatmapConfig, HeatmapSource, Trajectory},
+        CarInfo,
+    };
+
+    let car = |id, team, pos| CarInfo {
+        id,
+        team,
+        state: CarState {
+            pos,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let mut state = GameState::default();
+    state.ball.pos = Vec3::new(0., 0., 93.);
+    state.cars = vec![
+        car(1, Team::Blue, Vec3::new(-1000., -4000., 17.)),
+        car(2, Team::Orange, Vec3::new(1000., 4000., 17.)),
+    ];
+
+    let config = HeatmapConfig::default().with_cells(8, 10, 4);
+    let mut blue = Heatmap::new(config);
+    let mut orange = Heatmap::new(config);
+    blue.add_states([&state, &state], HeatmapSource::Team(Team::Blue));
+    orange.add_state(&state, HeatmapSource::Team(Team::Orange));
+
+    // orange is mirrored onto blue's side of the field
+    let cell = blue.cell_of(state.cars[0].state.pos);
+    assert_eq!(cell, orange.cell_of(Vec3::new(-1000., -4000., 17.)));
+    assert_eq!(blue.get(cell[0], cell[1], cell[2]), 2);
+    assert_eq!(orange.get(cell[0], cell[1], cell[2]), 1);
+
+    blue.merge(&orange).unwrap();
+    assert_eq!(blue.total(), 3);
+    assert_eq!(blue.get(cell[0], cell[1], cell[2]), 3);
+    assert_eq!(blue.density(cell[0], cell[1], cell[2]), 1.);
+    assert!(blue.merge(&Heatmap::default()).is_err());
+
+    // mirroring the grid moves the counts back to orange's side
+    let mirrored = blue.mirrored();
+    let [x, y, z] = mirrored.cell_of(Vec3::new(1000., 4000., 17.));
+    assert_eq!(mirrored.get(x, y, z), 3);
+
+    // positions outside of the grid are counted in the closest cell
+    let mut ball = Heatmap::new(config);
+    ball.add_position(Vec3::new(0., 6000., 3000.));
+    assert_eq!(ball.get(4, 9, 3), 1);
+    assert_eq!(ball.flatten().get(4, 9, 0), 1);
+
+    let mut csv = Vec::new();
+    blue.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(csv.lines().count(), 1 + 8 * 10 * 4);
+    assert!(csv.starts_with("x,y,z,count,density\n"));
+
+    let mut svg = Vec::new();
+    let path = Trajectory::car([&state, &state], 2, true);
+    assert_eq!(path.positions[0], Vec3::new(-1000., -4000., 17.));
+    blue.write_svg_with(&mut svg, &[path, Trajectory::ball([&state])]).unwrap();
+    let svg = String::from_utf8(svg).unwrap();
+    assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("<polyline").count(), 2);
+}
+
+#[test]
+fn vec3_math() {
+    let a = Vec3::new(1., 2., 3.);
+    let b = Vec3::new(4., -5., 6.);
+
+    assert_eq!(a + b, Vec3::new(5., -3., 9.));
+    assert_eq!(a - b, Vec3::new(-3., 7., -3.));
+    assert_eq!(a * b, Vec3::new(4., -10., 18.));
+    assert_eq!(a * 2., 2. * a);
+    assert_eq!(-a / 2., Vec3::new(-0.5, -1., -1.5));
+    assert_eq!([a, b].iter().sum::<Vec3>(), a + b);
+
+    let mut c = a;
+    c += b;
+    c *= 2.;
+    assert_eq!(c, Vec3::new(10., -6., 18.));
+
+    assert_eq!(a.dot(b), 12.);
+    assert_eq!(Vec3::X.cross(Vec3::Y), Vec3::Z);
+    assert_eq!(a.cross(b).dot(a), 0.);
+    assert_eq!(Vec3::new(3., 4., 0.).length(), 5.);
+    assert_eq!(Vec3::new(0., 3., 0.).distance(Vec3::new(0., 0., 4.)), 5.);
+    assert!((a.normalize().length() - 1.).abs() < 1e-6);
+    assert_eq!(Vec3::ZERO.normalize_or_zero(), Vec3::ZERO);
+
+    assert_eq!(a.lerp(b, 0.5), Vec3::new(2.5, -1.5, 4.5));
+    assert_eq!(a.project(Vec3::Z * 10.), Vec3::new(0., 0., 3.));
+    assert_eq!(a.reject(Vec3::Z), Vec3::new(1., 2., 0.));
+    assert!((Vec3::X.angle_between(Vec3::Y) - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+    assert_eq!(Vec3::X.angle_between(Vec3::ZERO), 0.);
+
+    assert_eq!(Vec3::new(30., 40., 0.).clamp_length(0., 5.), Vec3::new(3., 4., 0.));
+    assert_eq!(Vec3::new(0.3, 0.4, 0.).clamp_length(5., 10.), Vec3::new(3., 4., 0.));
+    assert_eq!(a.clamp_length_max(10.), a);
+
+    assert!(a.is_finite());
+    assert!(!Vec3::new(f32::NAN, 0., 0.).is_finite());
+    assert!(!Vec3::new(0., f32::INFINITY, 0.).is_finite());
+
+    // the results of every operation still match what's sent to RocketSim
+    assert_eq!(a + b, Vec3::from([5., -3., 9.]));
+    assert_eq!(<[f32; 3]>::from(a.max(b)), [4., 2., 6.]);
+}
+
+#[test]
+fn rot_mat_math() {
+    use rocketsim_rs::math::Quat;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!(a.distance(b) < 1e-4, "{a} != {b}");
+    }
+
+    let angle = Angle {
+        yaw: 0.7,
+        pitch: -0.3,
+        roll: 1.2,
+    };
+    let rot_mat = RotMat::from_angle(angle);
+    assert!((rot_mat.determinant() - 1.).abs() < 1e-5);
+
+    // the same as RocketSim
+    let cpp_rot_mat = angle.to_rotmat();
+    assert_close(rot_mat.forward, cpp_rot_mat.forward);
+    assert_close(rot_mat.right, cpp_rot_mat.right);
+    assert_close(rot_mat.up, cpp_rot_mat.up);
+    let cpp_angle = Angle::from_rotmat(rot_mat);
+    assert!((cpp_angle.roll - rot_mat.to_angle().roll).abs() < 1e-5);
+
+    let back = rot_mat.to_angle();
+    assert!((back.yaw - angle.yaw).abs() < 1e-5);
+    assert!((back.pitch - angle.pitch).abs() < 1e-5);
+    assert!((back.roll - angle.roll).abs() < 1e-5);
+
+    // a yaw of 90° turns forward into +y
+    let turned = RotMat::from_angle(Angle {
+        yaw: PI / 2.,
+        pitch: 0.,
+        roll: 0.,
+    });
+    assert_close(turned * Vec3::X, Vec3::Y);
+    assert_close((turned * turned) * Vec3::X, -Vec3::X);
+
+    let inverse = rot_mat.inverse().unwrap();
+    assert_close(inverse.forward, rot_mat.transpose().forward);
+    assert_close((rot_mat * inverse) * Vec3::new(1., 2., 3.), Vec3::new(1., 2., 3.));
+    assert!(RotMat::new(Vec3::X, Vec3::X, Vec3::Z).inverse().is_none());
+
+    let quat = rot_mat.to_quat();
+    assert!((quat.length() - 1.).abs() < 1e-5);
+    let from_quat = RotMat::from_quat(quat);
+    assert_close(from_quat.forward, rot_mat.forward);
+    assert_close(from_quat.right, rot_mat.right);
+    assert_close(from_quat.up, rot_mat.up);
+    assert_close(quat.rotate(Vec3::new(1., 2., 3.)), rot_mat * Vec3::new(1., 2., 3.));
+    assert_close(
+        (Quat::from(angle) * quat.conjugate()).rotate(Vec3::new(1., 2., 3.)),
+        Vec3::new(1., 2., 3.),
+    );
+
+    let skewed = RotMat::new(rot_mat.forward * 1.1, rot_mat.right + rot_mat.forward * 0.05, rot_mat.up);
+    let fixed = skewed.orthonormalize();
+    assert!((fixed.determinant() - 1.).abs() < 1e-5);
+    assert_close(fixed.forward, rot_mat.forward);
+
+    let car = CarState {
+        pos: Vec3::new(100., 200., 17.),
+        rot_mat: turned,
+        ..Default::default()
+    };
+    // in front of the car is +y in world space
+    assert_close(car.to_local(Vec3::new(100., 300., 17.)), Vec3::new(100., 0., 0.));
+    assert_close(car.to_world(Vec3::new(100., 0., 50.)), Vec3::new(100., 300., 67.));
+    assert_close(car.to_world(car.to_local(Vec3::new(-5., 7., 9.))), Vec3::new(-5., 7., 9.));
+}
+
+#[test]
+fn state_interpolation() {
+    use rocketsim_rs::math::Quat;
+
+    let yaw = |yaw| {
+        RotMat::from_angle(Angle {
+            yaw,
+            pitch: 0.,
+            roll: 0.,
+        })
+    };
+
+    let start = CarState {
+        pos: Vec3::new(0., 0., 17.),
+        rot_mat: yaw(0.),
+        vel: Vec3::new(1000., 0., 0.),
+        boost: 50.,
+        has_jumped: false,
+        ..Default::default()
+    };
+    let end = CarState {
+        pos: Vec3::new(100., 50., 17.),
+        rot_mat: yaw(PI / 2.),
+        vel: Vec3::new(0., 1000., 0.),
+        boost: 40.,
+        has_jumped: true,
+        ..Default::default()
+    };
+
+    let mid = start.lerp(&end, 0.5);
+    assert_eq!(mid.pos, Vec3::new(50., 25., 17.));
+    assert_eq!(mid.vel, Vec3::new(500., 500., 0.));
+    assert!((mid.rot_mat.to_angle().yaw - PI / 4.).abs() < 1e-5);
+    assert!((mid.rot_mat.determinant() - 1.).abs() < 1e-5);
+    assert_eq!(mid.boost, 45.);
+    // discrete fields aren't blended
+    assert!(mid.has_jumped);
+    assert!(!start.lerp(&end, 0.25).has_jumped);
+
+    // picking up boost is instant
+    let picked_up = CarState { boost: 100., ..end };
+    assert_eq!(start.lerp(&picked_up, 0.25).boost, 50.);
+    assert_eq!(start.lerp(&picked_up, 0.75).boost, 100.);
+
+    // demoed cars teleport instead of sliding across the field
+    let demoed = CarState { is_demoed: true, ..end };
+    assert_eq!(start.lerp(&demoed, 0.25).pos, start.pos);
+    assert!(start.lerp(&demoed, 0.75).is_demoed);
+
+    let ball_start = BallState::default();
+    let ball_end = BallState {
+        pos: Vec3::new(0., 1000., 500.),
+        tick_count_since_update: 8,
+        ..Default::default()
+    };
+    let ball_mid = ball_start.lerp(&ball_end, 0.5);
+    assert_eq!(ball_mid.pos.y, (ball_start.pos.y + 1000.) / 2.);
+    assert_eq!(ball_mid.tick_count_since_update, 8);
+
+    // slerp takes the shortest way around
+    let a = Quat::from_axis_angle(Vec3::Z, 0.1);
+    let b = Quat::from_axis_angle(Vec3::Z, -0.1);
+    let negated = Quat::new(-b.x, -b.y, -b.z, -b.w);
+    let halfway = a.slerp(negated, 0.5);
+    assert!(halfway.rotate(Vec3::X).distance(Vec3::X) < 1e-5);
+    assert!((a.slerp(b, 0.).dot(a) - 1.).abs() < 1e-6);
+}
+
+#[test]
+fn car_hitbox_corners() {
+    use rocketsim_rs::CarInfo;
+
+    let car = CarInfo {
+        state: CarState {
+            pos: Vec3::new(100., 200., 17.),
+            rot_mat: RotMat::from_angle(Angle {
+                yaw: PI / 2.,
+                pitch: 0.,
+                roll: 0.,
+            }),
+            ..Default::default()
+        },
+        config: CarConfig {
+            hitbox_size: Vec3::new(120., 80., 40.),
+            hitbox_pos_offset: Vec3::new(10., 0., 15.),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let corners = car.hitbox_corners();
+    // the car faces +y, so the front top right corner is at +y and -x
+    assert!(corners[7].distance(Vec3::new(60., 270., 52.)) < 1e-3);
+    assert!(corners[0].distance(Vec3::new(140., 150., 12.)) < 1e-3);
+
+    let center = corners.iter().sum::<Vec3>() / 8.;
+    assert!(center.distance(car.state.to_world(car.config.hitbox_pos_offset)) < 1e-3);
+}
+
+#[test]
+fn car_wheels() {
+    INIT.call_once(|| init(None, true));
+
+    let mut arena = Arena::default_standard();
+    let car_id = arena.pin_mut().add_car(Team::Blue, CarConfig::octane());
+    arena.pin_mut().reset_to_random_kickoff(None);
+    arena.pin_mut().step(30);
+
+    let car = arena.pin_mut().get_car(car_id);
+    let wheels = arena.get_car_wheels(car_id).unwrap();
+    for (wheel, has_contact) in wheels.iter().zip(car.wheels_with_contact) {
+        assert_eq!(wheel.is_in_contact, has_contact);
+        assert!(wheel.is_in_contact);
+        assert!(wheel.contact_point.z.abs() < 5.);
+        assert!(wheel.contact_normal.distance(Vec3::Z) < 1e-3);
+        assert!(wheel.suspension_compression() >= 0.);
+        assert!(wheel.radius > 0.);
+    }
+
+    let corners = arena.pin_mut().get_car_hitbox_corners(car_id).unwrap();
+    // sitting on its wheels, the hitbox is above the ground
+    assert!(corners.iter().all(|corner| corner.z > 0.));
+
+    assert!(arena.get_car_wheels(car_id + 1).is_err());
+    assert!(arena.pin_mut().get_car_hitbox_corners(car_id + 1).is_err());
+}